@@ -0,0 +1,91 @@
+use chrono::NaiveDate;
+use poise::CreateReply;
+use serenity::all::User;
+
+use super::{Context, Error, helper, shorten};
+use crate::commands::permissions_module::is_admin;
+use crate::main_modules::command_audit::AuditFilter;
+
+const MAX_RESULTS: usize = 20;
+
+/// Turns a `YYYY-MM-DD` date into the unix time it starts at, `end_of_day` giving the last second
+/// of it instead.
+fn parse_date(date: &str, end_of_day: bool) -> Result<u64, String> {
+    let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("`{}` isn't a date like 2025-01-31.", date))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.unwrap().and_utc().timestamp().max(0) as u64)
+}
+
+#[poise::command(slash_command, prefix_command, category = "Admin", check = "is_admin")]
+/// Searches the log of every command run through the bot.
+pub async fn audit(
+    ctx: Context<'_>,
+    #[description = "Only show commands run by this user."] user: Option<User>,
+    #[description = "Only show this command and its subcommands, e.g. timed_role."] command: Option<
+        String,
+    >,
+    #[description = "Only show commands run on or after this date (YYYY-MM-DD)."] since: Option<
+        String,
+    >,
+    #[description = "Only show commands run on or before this date (YYYY-MM-DD)."] until: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    let dates = (
+        since
+            .as_deref()
+            .map(|date| parse_date(date, false))
+            .transpose(),
+        until
+            .as_deref()
+            .map(|date| parse_date(date, true))
+            .transpose(),
+    );
+    let (since, until) = match dates {
+        (Ok(since), Ok(until)) => (since, until),
+        (Err(err), _) | (_, Err(err)) => {
+            ctx.say(err).await?;
+            return Ok(());
+        }
+    };
+    let filter = AuditFilter {
+        user_id: user.map(|user| user.id.get()),
+        command: command.map(|command| command.trim().trim_start_matches('/').to_string()),
+        since,
+        until,
+    };
+
+    let invocations = ctx.data().command_audit.query(&filter, MAX_RESULTS)?;
+    let description = if invocations.is_empty() {
+        "No commands matched.".to_string()
+    } else {
+        invocations
+            .iter()
+            .map(|invocation| {
+                format!(
+                    "<t:{}:f> <@{}> `/{}` {} - {} ({}ms)",
+                    invocation.invoked_at,
+                    invocation.user_id,
+                    invocation.command,
+                    shorten(&invocation.arguments, 80),
+                    shorten(&invocation.outcome.to_string(), 80),
+                    invocation.duration_ms
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = helper::new_embed_from_template(ctx.data())
+        .await
+        .title(format!("Command Audit (newest {} at most)", MAX_RESULTS))
+        .description(shorten(&description, 4096));
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use serenity::all::CreateMessage;
+use std::time::Duration;
+
+use super::{Context, Error, helper};
+use crate::main_modules::command_audit::{CommandInvocation, CommandOutcome, describe_options};
+use crate::main_modules::game_sanctions::unix_now;
+use crate::main_modules::guild_settings::GuildModule;
+use crate::main_modules::metrics::METRICS;
+
+pub mod audit;
+
+fn shorten(text: &str, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        text.chars().take(max_chars - 3).collect::<String>() + "..."
+    } else {
+        text.to_string()
+    }
+}
+
+fn arguments(ctx: Context<'_>) -> String {
+    match ctx {
+        poise::Context::Application(ctx) => {
+            describe_options(&ctx.interaction.data.options).join(", ")
+        }
+        poise::Context::Prefix(ctx) => ctx.args.to_string(),
+    }
+}
+
+/// Runs before every command, timing it for the audit log.
+pub async fn pre_command(ctx: Context<'_>) {
+    ctx.data().command_audit.start(ctx.id(), unix_now());
+}
+
+/// Runs after every command that didn't return an error.
+pub async fn post_command(ctx: Context<'_>) {
+    record(ctx, CommandOutcome::Succeeded).await;
+}
+
+/// Stores the invocation and mirrors it into the guild's command audit channel.
+pub async fn record(ctx: Context<'_>, outcome: CommandOutcome) {
+    let data = ctx.data();
+    let (invoked_at, duration) = data
+        .command_audit
+        .finish(ctx.id())
+        .unwrap_or_else(|| (unix_now(), Duration::ZERO));
+    METRICS.command(&ctx.command().qualified_name, outcome.kind(), duration);
+    let invocation = CommandInvocation {
+        id: 0,
+        user_id: ctx.author().id.get(),
+        command: ctx.command().qualified_name.clone(),
+        arguments: arguments(ctx),
+        guild_id: ctx.guild_id().map(|guild_id| guild_id.get()),
+        channel_id: ctx.channel_id().get(),
+        invoked_at,
+        duration_ms: duration.as_millis() as u64,
+        outcome,
+    };
+    tracing::info!(
+        command = invocation.command,
+        user_id = invocation.user_id,
+        duration_ms = invocation.duration_ms,
+        outcome = %invocation.outcome,
+        "Command finished"
+    );
+    if let Err(err) = data.command_audit.record(invocation.clone()) {
+        tracing::error!(
+            command = invocation.command,
+            error = %err,
+            "Couldn't record the command in the audit log"
+        );
+    }
+
+    let Some(guild_id) = ctx.guild_id() else {
+        return;
+    };
+    let log_channel = match data.guild_settings.get(guild_id, &data.config.get()) {
+        Ok(settings) => settings.log_channel(GuildModule::CommandAudit),
+        Err(err) => {
+            tracing::error!(error = %err, "Couldn't get guild settings for the audit log");
+            return;
+        }
+    };
+    let Some(log_channel) = log_channel else {
+        return;
+    };
+
+    let arguments = if invocation.arguments.is_empty() {
+        "None".to_string()
+    } else {
+        shorten(&invocation.arguments, 1024)
+    };
+    let embed = helper::new_embed_from_template(data)
+        .await
+        .title(format!("/{}", invocation.command))
+        .field("User", format!("<@{}>", invocation.user_id), true)
+        .field("Channel", format!("<#{}>", invocation.channel_id), true)
+        .field("Duration", format!("{}ms", invocation.duration_ms), true)
+        .field("Arguments", arguments, false)
+        .field(
+            "Outcome",
+            shorten(&invocation.outcome.to_string(), 1024),
+            false,
+        );
+    if let Err(err) = log_channel
+        .send_message(ctx.http(), CreateMessage::new().embed(embed))
+        .await
+    {
+        tracing::warn!(
+            command = invocation.command,
+            error = %err,
+            "Couldn't post the command to the audit channel"
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use crate::Data;
+use super::{Context, Error};
+use crate::commands::permissions_module::is_admin;
+
+#[poise::command(slash_command, subcommands("reload"), subcommand_required, category = "Admin", check = "is_admin")]
+/// Command for managing the bot's config
+pub async fn config(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Reload the config file without restarting the bot
+pub async fn reload(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+) -> Result<(), Error> {
+
+    ctx.say(ctx.data().config.reload_and_describe()).await?;
+    Ok(())
+}
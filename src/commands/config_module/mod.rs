@@ -0,0 +1,4 @@
+use super::{Context, Error};
+
+pub mod config;
+pub mod settings;
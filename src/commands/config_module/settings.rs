@@ -0,0 +1,168 @@
+use crate::Data;
+use crate::main_modules::guild_settings::GuildModule;
+use crate::main_modules::helper;
+use super::{Context, Error};
+use serenity::all::{ChannelId, GuildId, RoleId};
+use crate::commands::permissions_module::{is_admin, is_guild_admin};
+
+#[poise::command(slash_command, guild_only,
+    subcommands("view", "log_channel", "add_admin_role", "remove_admin_role", "module", "bloxlink", "add_guild"),
+    subcommand_required, category = "Admin", check = "is_guild_admin")]
+/// Command for managing this server's settings
+pub async fn settings(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+fn parse_guild_id(guild: &str) -> Option<GuildId> {
+    guild.trim().parse::<u64>().ok().filter(|id| *id != 0).map(GuildId::new)
+}
+
+#[poise::command(slash_command)]
+/// View this server's settings
+pub async fn view(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    let settings = ctx.data().guild_settings.get(guild_id, &ctx.data().config.get())?;
+
+    let channel = |channel: Option<ChannelId>| channel.map_or("None".to_string(), |channel| format!("<#{}>", channel));
+    let admin_roles = if settings.admin_role_ids.is_empty() {
+        "None".to_string()
+    } else {
+        settings.admin_role_ids.iter().map(|role| format!("<@&{}>", role)).collect::<Vec<_>>().join(", ")
+    };
+    let modules = GuildModule::ALL
+        .iter()
+        .map(|module| format!("{}: {}", module, if settings.is_enabled(*module) {"Enabled"} else {"Disabled"}))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let embed = helper::new_embed_from_template(ctx.data()).await
+        .title(format!("Settings for {}", guild_id))
+        .field("Attachment Logging Channel", channel(settings.attachment_logging_channel_id), true)
+        .field("Reaction Logging Channel", channel(settings.reaction_logging_channel_id), true)
+        .field("Command Audit Channel", channel(settings.command_audit_channel_id), true)
+        .field("Admin Roles", admin_roles, false)
+        .field("Modules", modules, false)
+        .field("Bloxlink Server", settings.bloxlink_guild_id.to_string(), false);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Set where a module logs to, leave the channel empty to go back to the default
+pub async fn log_channel(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The module to set the log channel of."] module: GuildModule,
+    #[description = "The channel to log to."] channel: Option<ChannelId>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    if !ctx.data().guild_settings.set_log_channel(guild_id, module, channel)? {
+        ctx.say(format!("{} doesn't log anywhere.", module)).await?;
+        return Ok(())
+    }
+
+    match channel {
+        Some(channel) => ctx.say(format!("{} now logs to <#{}>.", module, channel)).await?,
+        None => ctx.say(format!("{} log channel reset to the default.", module)).await?,
+    };
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Let a role manage this server's settings
+pub async fn add_admin_role(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The role to add."] role: RoleId,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    let guild_settings = &ctx.data().guild_settings;
+    let mut roles = guild_settings.get(guild_id, &ctx.data().config.get())?.admin_role_ids;
+    if !roles.contains(&role) {
+        roles.push(role);
+    }
+    guild_settings.set_admin_roles(guild_id, &roles)?;
+
+    ctx.say(format!("<@&{}> is now an admin role.", role)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Stop a role from being an admin role in this server
+pub async fn remove_admin_role(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The role to remove."] role: RoleId,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    let guild_settings = &ctx.data().guild_settings;
+    let mut roles = guild_settings.get(guild_id, &ctx.data().config.get())?.admin_role_ids;
+    roles.retain(|admin_role| *admin_role != role);
+    guild_settings.set_admin_roles(guild_id, &roles)?;
+
+    ctx.say(format!("<@&{}> is no longer an admin role.", role)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Enable or disable a module in this server
+pub async fn module(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The module to change."] module: GuildModule,
+    #[description = "Whether the module should run in this server."] enabled: bool,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    ctx.data().guild_settings.set_module_enabled(guild_id, module, enabled)?;
+
+    ctx.say(format!("{} {}.", module, if enabled {"enabled"} else {"disabled"})).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Set which server's Bloxlink links are used, leave it empty to use this server's
+pub async fn bloxlink(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The server ID to look up Bloxlink links in."] server_id: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(())
+    };
+    let bloxlink_guild_id = match server_id.as_deref().map(parse_guild_id) {
+        Some(None) => {
+            ctx.say("That isn't a valid server ID.").await?;
+            return Ok(())
+        }
+        Some(Some(bloxlink_guild_id)) => Some(bloxlink_guild_id),
+        None => None,
+    };
+    ctx.data().guild_settings.set_bloxlink_guild(guild_id, bloxlink_guild_id)?;
+
+    ctx.say(format!("Bloxlink links are now looked up in {}.", bloxlink_guild_id.unwrap_or(guild_id))).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, check = "is_admin")]
+/// Start serving another server, registering the bot's commands there
+pub async fn add_guild(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The ID of the server to add."] server_id: String,
+) -> Result<(), Error> {
+    let Some(new_guild_id) = parse_guild_id(&server_id) else {
+        ctx.say("That isn't a valid server ID.").await?;
+        return Ok(())
+    };
+
+    ctx.data().guild_settings.add_guild(new_guild_id)?;
+    poise::builtins::register_in_guild(ctx.http(), &ctx.framework().options().commands, new_guild_id).await?;
+    ctx.say(format!("Commands registered in {}, use `/settings` there to set it up.", new_guild_id)).await?;
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use super::{
+    Context, Error, helper, post_log, push_to_game, resolve_players, schedule_ban_lift,
+    temp_ban_note,
+};
+use crate::commands::log_module::roblox_log::RobloxInfTypes;
+use crate::main_modules::game_sanctions::{GameSanction, SanctionKind, unix_now};
+use crate::main_modules::mod_log::ModLog;
+use crate::commands::permissions_module::is_moderator;
+
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
+/// Bans the users inputted from Rise of Nations and makes the matching Roblox log.
+pub async fn game_ban(
+    ctx: Context<'_>,
+    #[description = "Users for the command, accepts Discord ids, ROBLOX users and ROBLOX ids."]
+    users: String,
+    #[description = "Reason for the ban."] reason: String,
+    #[description = "Duration of the ban (e.g., '1h', '2d', '1w'), permanent if left empty."]
+    duration: Option<String>,
+    #[description = "Note for the ban."] note: Option<String>,
+) -> Result<(), Error> {
+    ctx.reply("Banning users, please standby!").await?;
+
+    let expiry = match duration {
+        Some(duration) => match helper::duration_conversion(duration).await {
+            Ok((_, unix_timestamp, timestamp_string)) => Some((unix_timestamp, timestamp_string)),
+            Err(err) => {
+                ctx.say(err.to_string()).await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
+    let now = unix_now();
+    let mut banned = Vec::new();
+    for player in resolve_players(ctx, &users).await? {
+        if ctx.data().game_sanctions.is_banned(player.id, now)? {
+            ctx.say(format!(
+                "`{}` is already banned in game, skipping them.",
+                player.username
+            ))
+            .await?;
+            continue;
+        }
+        let mut sanction =
+            GameSanction::new(SanctionKind::Ban, player.id, ctx.author().id, &reason, now);
+        if let Some((unix_timestamp, _)) = &expiry {
+            sanction = sanction.expires_at(*unix_timestamp);
+        }
+        sanction.id = ctx.data().game_sanctions.add(sanction.clone())?;
+        push_to_game(ctx, &sanction, &player).await?;
+        schedule_ban_lift(ctx.data(), &sanction, ctx.channel_id()).await?;
+        banned.push(player);
+    }
+
+    if banned.is_empty() {
+        ctx.say("Command failed; no valid users were found to ban.")
+            .await?;
+        return Ok(());
+    }
+
+    let note = note.filter(|note| !note.is_empty());
+    let (infraction, note) = match expiry {
+        Some((unix_timestamp, timestamp_string)) => (
+            RobloxInfTypes::TempBan,
+            Some(temp_ban_note(note, &timestamp_string, unix_timestamp)),
+        ),
+        None => (RobloxInfTypes::Ban, note),
+    };
+    let log = ModLog::Roblox {
+        infraction,
+        users: banned,
+        reason,
+        note,
+    };
+    post_log(ctx, &log).await
+}
@@ -0,0 +1,74 @@
+use serenity::all::{CreateEmbedAuthor, CreateMessage};
+
+use super::{Context, Error, helper, resolve_players};
+use crate::main_modules::game_sanctions::{GameSanction, unix_now};
+use crate::main_modules::logging_database::GameActionAudit;
+use crate::commands::permissions_module::is_moderator;
+
+fn sanction_field(sanction: &GameSanction, audits: &[GameActionAudit]) -> (String, String) {
+    let mut value = format!("**Reason:** {}", sanction.reason);
+    value.push_str(&format!("\n**Moderator:** <@{}>", sanction.moderator_id));
+    value.push_str(&format!("\n**Issued:** <t:{}:f>", sanction.issued_at));
+    if let Some(expires_at) = sanction.expires_at {
+        value.push_str(&format!("\n**Expires:** <t:{}:R>", expires_at));
+    }
+    let in_game = match audits
+        .iter()
+        .rev()
+        .find(|audit| audit.sanction_id == sanction.id)
+    {
+        Some(GameActionAudit { error: None, .. }) => "Applied".to_string(),
+        Some(GameActionAudit {
+            error: Some(err), ..
+        }) => format!("Failed, {}", err),
+        None => "Not pushed".to_string(),
+    };
+    value.push_str(&format!("\n**In game:** {}", in_game));
+
+    (format!("{} #{}", sanction.kind, sanction.id), value)
+}
+
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
+/// Lists the active game bans and warns of the users inputted.
+pub async fn game_infractions(
+    ctx: Context<'_>,
+    #[description = "Users for the command, accepts Discord ids, ROBLOX users and ROBLOX ids."]
+    users: String,
+) -> Result<(), Error> {
+    ctx.reply("Getting game sanctions, please standby!").await?;
+
+    let now = unix_now();
+    let players = resolve_players(ctx, &users).await?;
+    if players.is_empty() {
+        ctx.say("Command failed; no valid users were found.")
+            .await?;
+        return Ok(());
+    }
+
+    for player in players {
+        let sanctions = ctx.data().game_sanctions.active(player.id, now)?;
+        let audits = ctx.data().logging_db.get_game_actions(player.id)?;
+        let avatar =
+            helper::get_roblox_avatar_bust(&ctx.data().reqwest_client, player.id.to_string()).await?;
+        let mut embed = helper::new_embed_from_template(ctx.data()).await.author(
+            CreateEmbedAuthor::new(format!("{}'s Active Game Sanctions", player.username))
+                .icon_url(avatar),
+        );
+
+        if sanctions.is_empty() {
+            embed = embed.description("No active game sanctions.");
+        } else {
+            embed = embed.description(format!("**{}** active sanction(s)", sanctions.len()));
+            // Embeds are limited to 25 fields, show the most recent ones.
+            for sanction in sanctions.iter().rev().take(25) {
+                let (name, value) = sanction_field(sanction, &audits);
+                embed = embed.field(name, value, false);
+            }
+        }
+
+        ctx.channel_id()
+            .send_message(&ctx.http(), CreateMessage::new().embed(embed))
+            .await?;
+    }
+    Ok(())
+}
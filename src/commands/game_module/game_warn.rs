@@ -0,0 +1,39 @@
+use super::{Context, Error, post_log, push_to_game, resolve_players};
+use crate::commands::log_module::roblox_log::RobloxInfTypes;
+use crate::main_modules::game_sanctions::{GameSanction, SanctionKind, unix_now};
+use crate::main_modules::mod_log::ModLog;
+use crate::commands::permissions_module::is_moderator;
+
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
+/// Warns the users inputted in Rise of Nations and makes the matching Roblox log.
+pub async fn game_warn(
+    ctx: Context<'_>,
+    #[description = "Users for the command, accepts Discord ids, ROBLOX users and ROBLOX ids."]
+    users: String,
+    #[description = "Reason for the warn."] reason: String,
+    #[description = "Note for the warn."] note: Option<String>,
+) -> Result<(), Error> {
+    ctx.reply("Warning users, please standby!").await?;
+
+    let now = unix_now();
+    let players = resolve_players(ctx, &users).await?;
+    if players.is_empty() {
+        ctx.say("Command failed; no valid users were found to warn.")
+            .await?;
+        return Ok(());
+    }
+    for player in &players {
+        let mut sanction =
+            GameSanction::new(SanctionKind::Warn, player.id, ctx.author().id, &reason, now);
+        sanction.id = ctx.data().game_sanctions.add(sanction.clone())?;
+        push_to_game(ctx, &sanction, player).await?;
+    }
+
+    let log = ModLog::Roblox {
+        infraction: RobloxInfTypes::Warn,
+        users: players,
+        reason,
+        note: note.filter(|note| !note.is_empty()),
+    };
+    post_log(ctx, &log).await
+}
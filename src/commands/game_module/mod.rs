@@ -1,3 +1,192 @@
+use serenity::all::{ChannelId, Http, UserId};
+
+use super::{Context, Error, helper};
+use crate::Data;
+use crate::commands::probation_module::flag_escalations;
+use crate::commands::log_module::roblox_log::RobloxInfTypes;
+use crate::main_modules::error::BotError;
+use crate::main_modules::game_sanctions::{GameSanction, SanctionKind, unix_now};
+use crate::main_modules::logging_database::{GameActionAudit, LogEntry};
+use crate::main_modules::mod_log::{ModLog, RobloxUser};
+use crate::main_modules::timer::Action;
+
 pub mod game_ban;
 pub mod game_infractions;
-pub mod game_warn;
\ No newline at end of file
+pub mod game_warn;
+
+/// Resolves the users inputted to Roblox accounts, reporting the ones that couldn't be.
+async fn resolve_players(ctx: Context<'_>, users: &str) -> Result<Vec<RobloxUser>, Error> {
+    let users: Vec<String> = users.split_whitespace().map(str::to_string).collect();
+    let (roblox_ids, errors) = helper::merge_types(
+        &ctx.data().reqwest_client,
+        &ctx.data().rbx_client,
+        &helper::bloxlink_for(ctx.data(), ctx.guild_id()),
+        users,
+    )
+    .await;
+    for error in errors {
+        ctx.say(error).await?;
+    }
+
+    let mut players = Vec::new();
+    for id in roblox_ids {
+        let Ok(roblox_id) = id.parse::<u64>() else {
+            ctx.say(format!("`{}` isn't a valid Roblox id, skipping it.", id))
+                .await?;
+            continue;
+        };
+        let user_details = match ctx.data().rbx_client.user_details(roblox_id).await {
+            Ok(user_details) => user_details,
+            Err(err) => {
+                ctx.say(format!(
+                    "Couldn't look up Roblox user `{}`, skipping it: {}",
+                    roblox_id,
+                    BotError::roblox(err)
+                ))
+                .await?;
+                continue;
+            }
+        };
+        players.push(RobloxUser {
+            username: user_details.username,
+            id: user_details.id,
+        });
+    }
+    Ok(players)
+}
+
+/// Posts the robloxlog for a game action and records it, like `/robloxlog` would.
+async fn post_log(ctx: Context<'_>, log: &ModLog) -> Result<(), Error> {
+    let message = ctx.say(log.to_string()).await?.into_message().await?;
+    ctx.data().logging_db.record(
+        LogEntry::from(log)
+            .moderated_by(ctx.author().id)
+            .posted_as(&message),
+    )?;
+    flag_escalations(ctx, log).await
+}
+
+/// Pushes a sanction into the game through Open Cloud and adds the outcome to the audit trail,
+/// telling the moderator if the game couldn't be reached.
+async fn push_to_game(
+    ctx: Context<'_>,
+    sanction: &GameSanction,
+    player: &RobloxUser,
+) -> Result<(), Error> {
+    let open_cloud = &ctx.data().open_cloud;
+    let result = match sanction.kind {
+        SanctionKind::Ban => open_cloud.ban(sanction).await,
+        SanctionKind::Warn => open_cloud.warn(sanction).await,
+    };
+    ctx.data().logging_db.record_game_action(GameActionAudit {
+        id: 0,
+        roblox_id: sanction.roblox_id,
+        sanction_id: sanction.id,
+        action: sanction.kind.to_string(),
+        moderator_id: ctx.author().id.get(),
+        error: result.as_ref().err().map(ToString::to_string),
+        timestamp: unix_now(),
+    })?;
+
+    if let Err(err) = result {
+        ctx.say(format!(
+            "Couldn't push the {} for `{}` into the game, it is only stored on our side: {}",
+            sanction.kind.to_string().to_lowercase(),
+            player.username,
+            err
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// The note on a temporary ban's log, saying when it ends.
+pub fn temp_ban_note(note: Option<String>, duration: &str, expires_at: u64) -> String {
+    let expiry_note = format!("{}, expires <t:{}:f>", duration, expires_at);
+    match note {
+        Some(note) => format!("{}\n{}", note, expiry_note),
+        None => expiry_note,
+    }
+}
+
+/// Schedules a temporary game ban to be lifted when it expires, with the unban logged in
+/// `log_channel`.
+pub async fn schedule_ban_lift(
+    data: &Data,
+    sanction: &GameSanction,
+    log_channel: ChannelId,
+) -> Result<(), Error> {
+    let Some(expires_at) = sanction.expires_at else {
+        return Ok(());
+    };
+    data.timer_system
+        .schedule(
+            format!("roblox-{}", sanction.roblox_id),
+            Action::LiftGameBan {
+                roblox_id: sanction.roblox_id,
+                sanction_id: sanction.id,
+                log_channel_id: log_channel.get(),
+            },
+            expires_at.saturating_sub(unix_now()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Lifts a temporary game ban whose timer ran out and posts its unban log, skipping bans that
+/// were already lifted.
+pub async fn lift_game_ban(
+    http: &Http,
+    data: &Data,
+    bot_id: UserId,
+    roblox_id: u64,
+    sanction_id: u64,
+    log_channel: ChannelId,
+) -> Result<(), Error> {
+    let Some(sanction) = data.game_sanctions.revoke(roblox_id, sanction_id)? else {
+        return Ok(());
+    };
+    let result = data.open_cloud.unban(roblox_id).await;
+    data.logging_db.record_game_action(GameActionAudit {
+        id: 0,
+        roblox_id,
+        sanction_id,
+        action: "Unban".to_string(),
+        moderator_id: bot_id.get(),
+        error: result.as_ref().err().map(ToString::to_string),
+        timestamp: unix_now(),
+    })?;
+
+    let username = match data.rbx_client.user_details(roblox_id).await {
+        Ok(user_details) => user_details.username,
+        Err(_) => "null".to_string(),
+    };
+    let log = ModLog::Roblox {
+        infraction: RobloxInfTypes::Unban,
+        users: vec![RobloxUser {
+            username: username.clone(),
+            id: roblox_id,
+        }],
+        reason: "Temporary game ban expired".to_string(),
+        note: Some(format!("Original reason: {}", sanction.reason)),
+    };
+    let message = log_channel.say(http, log.to_string()).await?;
+    data.logging_db.record(
+        LogEntry::from(&log)
+            .moderated_by(bot_id)
+            .posted_as(&message),
+    )?;
+
+    if let Err(err) = result {
+        log_channel
+            .say(
+                http,
+                format!(
+                    "Couldn't lift the ban for `{}` in game, it has to be lifted by hand: {}",
+                    username, err
+                ),
+            )
+            .await?;
+    }
+    Ok(())
+}
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use crate::main_modules::helper;
 
 use super::{Context, Error, UserId, serenity, FromStr};
+use crate::commands::permissions_module::is_staff;
 
 fn split_string(s: String, chunk_size: usize) -> Vec<String> {
     s.chars()
@@ -14,7 +15,7 @@ fn split_string(s: String, chunk_size: usize) -> Vec<String> {
         .collect()
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// Gets all possible information about the discord account.
 pub async fn discordinfo(
     ctx: Context<'_>,
@@ -28,7 +29,10 @@ pub async fn discordinfo(
     }
     let users = purified_users.split(' ');
     for snowflake in users {
-        let userid: UserId = UserId::from_str(snowflake).expect("something went wrong.");
+        let Ok(userid) = UserId::from_str(snowflake) else {
+            ctx.say(format!("`{}` isn't a valid Discord id, skipping it.", snowflake)).await?;
+            continue;
+        };
         let user: User = match userid.to_user(&ctx.http()).await {
             Ok(user) => user,
             Err(_) => {
@@ -1,11 +1,13 @@
-use super::{CONFIG, Context, Error, FromStr, helper};
+use super::{Context, Error, FromStr, helper};
 use chrono::{DateTime, Local};
 use regex::Regex;
 use serenity::all::{ButtonStyle, CreateActionRow, CreateButton, EditMessage};
 use serenity::builder::CreateMessage;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::commands::permissions_module::is_staff;
+use crate::main_modules::error::BotError;
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// Gets the ROBLOX info of the users inputted. Do not input Discord IDs as a test, please.
 pub async fn getinfo(
     ctx: Context<'_>,
@@ -17,11 +19,13 @@ pub async fn getinfo(
 ) -> Result<(), Error> {
     ctx.reply("Getting user info, please standby!").await?;
     let new_line_regex = Regex::new(r"(?:\r?\n){4,}").expect("Invalid regex");
-    let badge_iterations = badge_max_iterations.unwrap_or(CONFIG.main.default_badge_iterations);
+    let badge_iterations =
+        badge_max_iterations.unwrap_or(ctx.data().config.get().main.default_badge_iterations);
+    let bloxlink = helper::bloxlink_for(ctx.data(), ctx.guild_id());
 
     let users: Vec<String> = users.split_whitespace().map(str::to_string).collect();
     let (roblox_ids, roblox_conversion_errors) =
-        helper::merge_types(&ctx.data().reqwest_client, &ctx.data().rbx_client, users).await;
+        helper::merge_types(&ctx.data().reqwest_client, &ctx.data().rbx_client, &bloxlink, users).await;
 
     if !roblox_conversion_errors.is_empty() {
         ctx.channel_id()
@@ -46,15 +50,16 @@ pub async fn getinfo(
         let user_details = ctx
             .data()
             .rbx_client
-            .user_details(id.parse::<u64>().expect("Invalid user ID"))
-            .await?;
-        let created_at: DateTime<Local> =
-            DateTime::from_str(&user_details.created_at).expect("Invalid date");
+            .user_details(id.parse::<u64>()?)
+            .await
+            .map_err(BotError::roblox)?;
+        let created_at: DateTime<Local> = DateTime::from_str(&user_details.created_at)?;
         let avatar_image =
             helper::get_roblox_avatar_bust(&ctx.data().reqwest_client, user_details.id.to_string())
-                .await;
+                .await?;
         let associated_discord_ids = helper::roblox_id_to_discord_ids(
             &ctx.data().reqwest_client,
+            &bloxlink,
             user_details.id.to_string(),
         );
 
@@ -70,7 +75,7 @@ pub async fn getinfo(
         let mut embed = helper::new_embed_from_template(ctx.data())
             .await
             .title(format!("Extra Information - {}", user_details.display_name))
-            .color(ctx.data().bot_color)
+            .color(ctx.data().config.get().main.color)
             .thumbnail(avatar_image.as_str().to_string())
             .field(
                 "User Link",
@@ -1,4 +1,4 @@
-use super::{Context, Error, helper, UserId, serenity, FromStr, CONFIG};
+use super::{Context, Error, helper, UserId, serenity, FromStr};
 
 pub mod discord_info;
 pub mod get_info;  
\ No newline at end of file
@@ -0,0 +1,110 @@
+use futures::StreamExt;
+use poise::CreateReply;
+use serenity::all::{
+    ChannelId, CreateAttachment, Message, MessageInteractionMetadata, UserId,
+};
+
+use super::Error;
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::ModLog;
+use crate::Data;
+use crate::commands::permissions_module::is_admin;
+
+const PROGRESS_INTERVAL: usize = 500;
+
+/// Who made the log: the user who ran the command the bot posted it for, or the author if a
+/// moderator posted it by hand.
+fn log_moderator(message: &Message) -> Option<UserId> {
+    match message.interaction_metadata.as_deref() {
+        Some(MessageInteractionMetadata::Command(metadata)) => Some(metadata.user.id),
+        Some(MessageInteractionMetadata::Component(metadata)) => Some(metadata.user.id),
+        Some(MessageInteractionMetadata::ModalSubmit(metadata)) => Some(metadata.user.id),
+        Some(_) => None,
+        None if !message.author.bot => Some(message.author.id),
+        None => None,
+    }
+}
+
+#[derive(Default)]
+struct BackfillReport {
+    scanned: usize,
+    imported: usize,
+    already_recorded: usize,
+    unparseable: Vec<String>,
+}
+
+impl BackfillReport {
+    fn summary(&self, channel: ChannelId, status: &str) -> String {
+        format!(
+            "Backfill of <#{}> {}.\nScanned: {}\nImported: {}\nAlready recorded: {}\nUnparseable: {}",
+            channel,
+            status,
+            self.scanned,
+            self.imported,
+            self.already_recorded,
+            self.unparseable.len()
+        )
+    }
+}
+
+#[poise::command(slash_command, category = "Admin", check = "is_admin")]
+/// Imports the logs in a log channel's history into the infraction database.
+pub async fn backfill(
+    ctx: poise::ApplicationContext<'_, Data, Error>,
+    #[description = "The log channel to import."] channel: ChannelId,
+) -> Result<(), Error> {
+
+    let mut report = BackfillReport::default();
+    let reply = ctx.say(report.summary(channel, "in progress")).await?;
+    let logging_db = &ctx.data().logging_db;
+
+    let mut messages = channel.messages_iter(ctx.http()).boxed();
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        report.scanned += 1;
+        if report.scanned % PROGRESS_INTERVAL == 0 {
+            reply
+                .edit(
+                    poise::Context::Application(ctx),
+                    CreateReply::default().content(report.summary(channel, "in progress")),
+                )
+                .await?;
+        }
+
+        // Anything that doesn't start like a log is chatter, not a log that failed to parse.
+        if !message.content.trim_start().starts_with('[') {
+            continue;
+        }
+        if logging_db.get_by_message_id(message.id.get())?.is_some() {
+            report.already_recorded += 1;
+            continue;
+        }
+
+        match message.content.parse::<ModLog>() {
+            Ok(log) => {
+                let mut entry = LogEntry::from(&log).posted_as(&message);
+                if let Some(moderator) = log_moderator(&message) {
+                    entry = entry.moderated_by(moderator);
+                }
+                logging_db.record(entry)?;
+                report.imported += 1;
+            }
+            Err(err) => report.unparseable.push(format!(
+                "{} - {}",
+                message.id.link(channel, ctx.guild_id()),
+                err
+            )),
+        }
+    }
+
+    let mut final_reply = CreateReply::default().content(report.summary(channel, "finished"));
+    if !report.unparseable.is_empty() {
+        final_reply = final_reply.attachment(CreateAttachment::bytes(
+            report.unparseable.join("\n"),
+            "unparseable_logs.txt",
+        ));
+    }
+    ctx.send(final_reply).await?;
+
+    Ok(())
+}
@@ -1,52 +1,306 @@
-use serenity::all::{CreateEmbed, CreateEmbedAuthor, CreateMessage, UserId};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteractionCollector, CreateActionRow, CreateButton,
+    CreateEmbed, CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, EditMessage, GuildId, Message, MessageId, UserId,
+};
 
-use crate::main_modules::helper;
 use super::{Context, Error};
+use crate::main_modules::helper::{self, ResolvedUser, UserIdentity};
+use crate::main_modules::logging_database::LogEntry;
+use crate::commands::permissions_module::is_moderator;
+
+const LOGS_PER_PAGE: usize = 5;
+
+struct History {
+    name: String,
+    icon_url: String,
+    logs: Vec<LogEntry>,
+}
+
+struct PaginatedHistory {
+    message: Message,
+    pages: Vec<CreateEmbed>,
+    current_page: usize,
+}
+
+fn merge_logs(logs: &mut Vec<LogEntry>, more: Vec<LogEntry>) {
+    for entry in more {
+        if !logs.iter().any(|existing| existing.id == entry.id) {
+            logs.push(entry);
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max_chars).collect::<String>())
+    }
+}
+
+/// Logs made against the Discord account, plus any made against its Bloxlink-linked Roblox account.
+async fn discord_history(
+    ctx: Context<'_>,
+    discord_id: UserId,
+    linked_roblox_id: Option<u64>,
+) -> Result<History, Error> {
+    let data = ctx.data();
+    let user = discord_id.to_user(ctx).await?;
+    let mut logs = data.logging_db.get_by_discord_id(discord_id.get())?;
 
-#[poise::command(slash_command, prefix_command)]
-/// This is to test serialization of messages in an ingame-logging environment.
+    if let Some(roblox_id) = linked_roblox_id {
+        merge_logs(&mut logs, data.logging_db.get_by_roblox_id(roblox_id)?);
+    }
+    logs.sort_by_key(|entry| entry.timestamp);
+
+    Ok(History {
+        name: user.name.clone(),
+        icon_url: user.face(),
+        logs,
+    })
+}
+
+/// Logs made against the Roblox account, plus any made against Discord accounts linked to it.
+async fn roblox_history(ctx: Context<'_>, roblox_id: u64) -> Result<History, Error> {
+    let data = ctx.data();
+    let user_details = data.rbx_client.user_details(roblox_id).await?;
+    let mut logs = data.logging_db.get_by_roblox_id(roblox_id)?;
+
+    if let Ok(discord_ids) =
+        helper::roblox_id_to_discord_ids(&data.reqwest_client, &helper::bloxlink_for(data, ctx.guild_id()), roblox_id.to_string()).await
+    {
+        for discord_id in discord_ids {
+            if let Ok(discord_id) = discord_id.parse::<u64>() {
+                merge_logs(&mut logs, data.logging_db.get_by_discord_id(discord_id)?);
+            }
+        }
+    }
+    logs.sort_by_key(|entry| entry.timestamp);
+
+    Ok(History {
+        name: user_details.username,
+        icon_url: helper::get_roblox_avatar_bust(&data.reqwest_client, roblox_id.to_string()).await?,
+        logs,
+    })
+}
+
+fn log_field(index: usize, entry: &LogEntry, guild_id: Option<GuildId>) -> (String, String) {
+    let mut value = format!("**Reason:** {}", truncate(&entry.reason, 500));
+    if let Some(note) = &entry.note {
+        value.push_str(&format!("\n**Note:** {}", truncate(note, 300)));
+    }
+    if entry.moderator_id != 0 {
+        value.push_str(&format!("\n**Moderator:** <@{}>", entry.moderator_id));
+    }
+    value.push_str(&format!("\n<t:{}:f>", entry.timestamp));
+    if entry.channel_id != 0 && entry.message_id != 0 {
+        let link =
+            MessageId::new(entry.message_id).link(ChannelId::new(entry.channel_id), guild_id);
+        value.push_str(&format!(" - [Jump to log]({})", link));
+    }
+
+    (
+        format!("#{} - {} ({})", index + 1, entry.log_type, entry.kind),
+        value,
+    )
+}
+
+fn history_pages(
+    template: CreateEmbed,
+    history: &History,
+    guild_id: Option<GuildId>,
+) -> Vec<CreateEmbed> {
+    let template = template.author(
+        CreateEmbedAuthor::new(format!("{}'s Moderation History", history.name))
+            .icon_url(history.icon_url.clone()),
+    );
+
+    if history.logs.is_empty() {
+        return vec![template.description("No logs on record.")];
+    }
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for entry in &history.logs {
+        *counts.entry(entry.log_type.as_str()).or_insert(0) += 1;
+    }
+    let summary = format!(
+        "**{}** log(s) on record\n{}",
+        history.logs.len(),
+        counts
+            .iter()
+            .map(|(log_type, count)| format!("{}: {}", log_type, count))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    );
+
+    let page_count = history.logs.len().div_ceil(LOGS_PER_PAGE);
+    history
+        .logs
+        .chunks(LOGS_PER_PAGE)
+        .enumerate()
+        .map(|(page, chunk)| {
+            let mut embed = template.clone().description(format!(
+                "{}\n\nPage {}/{}",
+                summary,
+                page + 1,
+                page_count
+            ));
+            for (offset, entry) in chunk.iter().enumerate() {
+                let (name, value) = log_field(page * LOGS_PER_PAGE + offset, entry, guild_id);
+                embed = embed.field(name, value, false);
+            }
+            embed
+        })
+        .collect()
+}
+
+fn navigation_buttons(ctx_id: u64, index: usize) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{}:{}:prev", ctx_id, index))
+            .style(ButtonStyle::Secondary)
+            .label("Previous"),
+        CreateButton::new(format!("{}:{}:next", ctx_id, index))
+            .style(ButtonStyle::Secondary)
+            .label("Next"),
+    ])]
+}
+
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
+/// Shows the moderation history of the users inputted.
 pub async fn inf(
     ctx: Context<'_>,
-    #[description = "Users for the command, accepts Discord ids, ROBLOX users and ROBLOX ids."] users: String
+    #[description = "Users for the command, accepts Discord ids, ROBLOX users and ROBLOX ids."]
+    users: String,
 ) -> Result<(), Error> {
-    ctx.say("Getting message(s) and doing some calculation(s)...").await?;
+    ctx.say("Getting moderation history, please standby!")
+        .await?;
     let users: Vec<String> = users.split_whitespace().map(str::to_string).collect();
 
-    let (roblox_users, discord_users, errors) = helper::split_types(&ctx.data().rbx_client, users).await;
+    let resolved = helper::split_types(
+        &ctx.data().reqwest_client,
+        &ctx.data().rbx_client,
+        &helper::bloxlink_for(ctx.data(), ctx.guild_id()),
+        users,
+        true,
+    )
+    .await;
 
+    let errors: Vec<String> = resolved
+        .iter()
+        .filter_map(|result| result.as_ref().err().map(ToString::to_string))
+        .collect();
     if !errors.is_empty() {
-        ctx.channel_id().say(&ctx.http(), &errors.join("\n")).await?;
+        ctx.channel_id().say(&ctx.http(), errors.join("\n")).await?;
     }
 
-    for user in roblox_users {        
-        println!("{}", user);
-        let logs = ctx.data().logging_db.get_by_id(user).await;
-        println!("{:#?}", logs);
+    let users: Vec<ResolvedUser> = resolved.into_iter().filter_map(Result::ok).collect();
+    if users.is_empty() {
+        ctx.say("Command failed; no valid users were found. You might have inputted the users incorrectly.").await?;
+        return Ok(());
     }
 
-    for user in discord_users {
-        println!("{}", user);
-        let logs = ctx.data().logging_db.get_by_id(user).await;
-        println!("{:#?}", logs);
+    let mut histories = Vec::new();
+    for user in users {
+        let history = match user.identity {
+            UserIdentity::Discord(discord_id) => discord_history(ctx, discord_id, None).await,
+            UserIdentity::Linked {
+                discord_id,
+                roblox_id,
+            } => discord_history(ctx, discord_id, Some(roblox_id)).await,
+            UserIdentity::Roblox { id, .. } => roblox_history(ctx, id).await,
+        };
+        match history {
+            Ok(history) => histories.push(history),
+            Err(err) => {
+                ctx.say(format!(
+                    "Couldn't get the history of `{}`: {}",
+                    user.input, err
+                ))
+                .await?;
+            }
+        }
+    }
 
-        // let mut embeds: Vec<CreateEmbed> = vec![];
-        // let split_logs = logs.chunks(5);
+    let ctx_id = ctx.id();
+    let mut paginated: Vec<PaginatedHistory> = Vec::new();
+    for (index, history) in histories.iter().enumerate() {
+        let template = helper::new_embed_from_template(ctx.data()).await;
+        let pages = history_pages(template, history, ctx.guild_id());
+        let components = if pages.len() > 1 {
+            navigation_buttons(ctx_id, index)
+        } else {
+            vec![]
+        };
+        let message = ctx
+            .channel_id()
+            .send_message(
+                &ctx.http(),
+                CreateMessage::new()
+                    .embed(pages[0].clone())
+                    .components(components),
+            )
+            .await?;
+        paginated.push(PaginatedHistory {
+            message,
+            pages,
+            current_page: 0,
+        });
+    }
+
+    if paginated.iter().all(|history| history.pages.len() <= 1) {
+        return Ok(());
+    }
 
-        // let discord_user = ctx.http().get_user(UserId::new(user)).await?;
-        
-        // for chunk in split_logs {
-        //     let mut embed = helper::new_embed_from_template(ctx.data()).await;
-        //     embed = embed.author(CreateEmbedAuthor::new(format!("{}'s Moderation History", discord_user.name.clone())).icon_url(discord_user.avatar_url().unwrap()));
-        //     for log in chunk {
-        //         let log_msg = ctx.http().get_message(log.channel_id.into(), log.message_id.into()).await.unwrap();
-        //         embed = embed.field(format!("[Log #{}]{} |", log.message_id, log_msg.link()), format!("{}", log.log_type), false)
-        //     }
-        //     embeds.push(embed);
-        // }
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&format!("{}:", ctx_id)))
+        .timeout(Duration::from_secs(600))
+        .await
+    {
+        let mut parts = press.data.custom_id.split(':').skip(1);
+        let (Some(index), Some(direction)) = (
+            parts.next().and_then(|index| index.parse::<usize>().ok()),
+            parts.next(),
+        ) else {
+            continue;
+        };
+        let Some(history) = paginated.get_mut(index) else {
+            continue;
+        };
 
-        // for embed in embeds {
-        //     ctx.channel_id().send_message(&ctx.http(), CreateMessage::new().add_embed(embed.clone())).await?;
-        // }
+        let page_count = history.pages.len();
+        history.current_page = match direction {
+            "next" => (history.current_page + 1) % page_count,
+            "prev" => history
+                .current_page
+                .checked_sub(1)
+                .unwrap_or(page_count - 1),
+            _ => continue,
+        };
+
+        press
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(history.pages[history.current_page].clone()),
+                ),
+            )
+            .await?;
+    }
+
+    for history in paginated
+        .iter_mut()
+        .filter(|history| history.pages.len() > 1)
+    {
+        history
+            .message
+            .edit(&ctx.http(), EditMessage::new().components(vec![]))
+            .await?;
     }
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,2 +1,3 @@
 use super::{Context, Error};
-pub mod infractions;
\ No newline at end of file
+pub mod infractions;
+pub mod backfill;
\ No newline at end of file
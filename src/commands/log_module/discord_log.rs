@@ -1,10 +1,14 @@
 // Command for making discord-side logs
-use poise::ChoiceParameter;
 use serenity::User;
 
-use super::{Context, Error, helper, UserId, Mentionable, serenity, FromStr};
+use crate::commands::probation_module::flag_escalations;
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::{LogUser, ModLog, RobloxUser};
+use super::{Context, Error, helper, UserId, serenity, FromStr};
+use crate::commands::permissions_module::is_moderator;
+use crate::main_modules::error::BotError;
 
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum DiscordInfTypes {
     Ban,
     #[name = "Temporary Ban"]
@@ -14,7 +18,7 @@ pub enum DiscordInfTypes {
     Warn
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
 /// Makes a Discord infraction log based on the Discord IDs inputted.
 pub async fn discordlog(
     ctx: Context<'_>,
@@ -34,10 +38,12 @@ pub async fn discordlog(
     let users = purified_users.split(' ');
     let reasons = reason.split('|').map(str::to_string).collect::<Vec<String>>();
     let notes = note.unwrap_or_default().split('|').map(str::to_string).collect::<Vec<String>>();
-    let mut users_string = String::new();
-    let mut user_string_vec: Vec<String> = Vec::new();
+    let mut log_users: Vec<LogUser> = Vec::new();
     for snowflake in users {
-        let userid: UserId = UserId::from_str(snowflake).expect("something went wrong.");
+        let Ok(userid) = UserId::from_str(snowflake) else {
+            ctx.say(format!("`{}` isn't a valid Discord id, skipping it.", snowflake)).await?;
+            continue
+        };
         let user: User = match userid.to_user(ctx).await {
             Ok(user) => user,
             Err(_) => {
@@ -45,30 +51,30 @@ pub async fn discordlog(
                 continue
             }
         };
-        let mut user_string = String::new();
-        user_string.push_str(format!("[{}:{}", user.mention(), user.id).as_str());
-        let roblox_id = if infraction_type.name() == "Ban" { match helper::discord_id_to_roblox_id(&ctx.data().reqwest_client, user.id).await {Ok(id) => id, Err(err) => {
-            ctx.say(err).await?;
-            "null".to_string()
-        }}} else { "null".to_string() };
-        let roblox_user = if roblox_id != *"null".to_string() {ctx.data().rbx_client.user_details(roblox_id.parse::<u64>().expect("err")).await?.username} else { "null".to_string() };
-        if infraction_type.name() == "Ban" { user_string.push_str(format!(" - {}:{}]\n", roblox_user, roblox_id).as_str()) } else { user_string.push_str("]\n") }
-        if !multimessage {users_string.push_str(user_string.as_str())} else {user_string_vec.push(user_string)}
+        if infraction_type == DiscordInfTypes::Ban {
+            let roblox = match helper::discord_id_to_roblox_id(&ctx.data().reqwest_client, &helper::bloxlink_for(ctx.data(), ctx.guild_id()), user.id).await {Ok(id) => {
+                Some(RobloxUser { username: ctx.data().rbx_client.user_details(id).await.map_err(BotError::roblox)?.username, id })
+            }, Err(err) => {
+                ctx.say(err.to_string()).await?;
+                None
+            }};
+            log_users.push(LogUser::Linked { discord_id: user.id, roblox });
+        } else { log_users.push(LogUser::Discord(user.id)) }
     }
-    let type_string = format!("[{}]\n", infraction_type.name());
+    let note = |index: usize| Some(notes[index].clone()).filter(|note| !note.is_empty());
     if !multimessage {
-        let reason_string = format!("[{}]", reasons[0]);
-        let note_string = if !notes[0].is_empty() {format!("\nNote: {}", notes[0])} else {String::new()};
-        let response = format!("{}{}{}{}", type_string, users_string, reason_string, note_string);
-        ctx.say(response).await?;
+        let log = ModLog::Discord { infraction: infraction_type, users: log_users, reason: reasons[0].clone(), note: note(0) };
+        let message = ctx.say(log.to_string()).await?.into_message().await?;
+        ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
+        flag_escalations(ctx, &log).await?;
     } else {
         let mut reason_number = 0;
         let mut note_number = 0;
-        for user_string in user_string_vec {
-            let reason_string = format!("[{}]", reasons[reason_number]);
-            let note_string = if !notes[note_number].is_empty() {format!("\nNote: {}", notes[note_number])} else {String::new()};
-            let response = format!("{}{}{}{}", type_string, user_string, reason_string, note_string);
-            ctx.say(response).await?;
+        for user in log_users {
+            let log = ModLog::Discord { infraction: infraction_type, users: vec![user], reason: reasons[reason_number].clone(), note: note(note_number) };
+            let message = ctx.say(log.to_string()).await?.into_message().await?;
+            ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
+            flag_escalations(ctx, &log).await?;
             if reasons.get(reason_number + 1).is_some() { reason_number += 1 }
             if notes.get(note_number + 1 ).is_some() { note_number += 1 }
         }
@@ -1,9 +1,10 @@
-use poise::ChoiceParameter;
-use serenity::all::Mentionable;
-
+use crate::main_modules::helper::{self, Bloxlink, UserIdentity};
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::{LogModerator, LogUser, ModLog, RobloxUser};
 use super::{Context, Error, UserId, FromStr};
+use crate::commands::permissions_module::is_moderator;
 
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum FalseInfTypes {
     #[name = "Discord, Temporary Ban"]
     Ban,
@@ -27,34 +28,22 @@ pub enum FalseInfTypes {
     GameWarn
 }
 
-async fn do_affected_id(rbx_client: &roboat::Client, user: &str) -> (String, Vec<String>) {
+async fn do_affected_id(reqwest_client: &reqwest::Client, rbx_client: &roboat::Client, bloxlink: &Bloxlink, user: &str) -> (Vec<LogUser>, Vec<String>) {
     let mut errors_vector = vec![];
-    let mut response_edit = String::new();
-    if user.len() >= 17 && user.chars().all(|c| c.is_ascii_digit()) {
-        let discord_id = match UserId::from_str(user) {Ok(id) => id, Err(err) => {
-            errors_vector.push(format!("Couldn't find turn discord id string into actual discord id for {}, details:\n{}", user, err));
-            return (response_edit, errors_vector)
-        }};
-        response_edit.push_str(format!("\n[{}:{}]", discord_id.mention(), discord_id).as_str())
-    } else if user.len() < 17 && user.chars().all(|c| c.is_ascii_digit()) {
-        let details = match rbx_client.user_details(user.parse::<u64>().unwrap()).await {Ok(id) => id, Err(err) => {
-            errors_vector.push(format!("Couldn't find turn discord id into roblox id for {}, details:\n{}", user, err));
-            return (response_edit, errors_vector)
-        }};
-        response_edit.push_str(format!("\n[{}:{}]", details.username, details.id).as_str())
-    } else if !user.chars().all(|c| c.is_ascii_digit()) {
-        let user_search = match rbx_client.username_user_details(vec![user.to_string()], false).await {Ok(id) => id, Err(err) => {
-            errors_vector.push(format!("Couldn't find user details for {}, details:\n{}", user, err));
-            return (response_edit, errors_vector)
-        }};
-        for details in user_search {
-            response_edit.push_str(format!("\n[{}:{}]", details.username, details.id).as_str())
+    let mut affected = vec![];
+    for result in helper::split_types(reqwest_client, rbx_client, bloxlink, vec![user.to_string()], false).await {
+        match result {
+            Ok(resolved) => affected.push(match resolved.identity {
+                UserIdentity::Discord(discord_id) | UserIdentity::Linked { discord_id, .. } => LogUser::Discord(discord_id),
+                UserIdentity::Roblox { id, username } => LogUser::Roblox(RobloxUser { username, id }),
+            }),
+            Err(err) => errors_vector.push(err.to_string()),
         }
     }
-    (response_edit, errors_vector)
+    (affected, errors_vector)
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
 /// Makes a Discord infraction log based on the Discord IDs inputted.
 pub async fn false_infraction(
     ctx: Context<'_>,
@@ -64,7 +53,7 @@ pub async fn false_infraction(
     #[description = "Reason for infraction, why were the affected user(s) affected?"] reason1: String,
     #[description = "Reason for invalidation."] reason2: String,
 ) -> Result<(), Error> {
-    ctx.reply("Making log(s), please standby!").await.unwrap();
+    ctx.reply("Making log(s), please standby!").await?;
 
     let mod_ids: Vec<UserId> = mod_users
         .split_whitespace()
@@ -84,28 +73,27 @@ pub async fn false_infraction(
 
     for (index, mod_id) in mod_ids.iter().enumerate() {
         if index + 1 == mod_ids.len() {
-            let mut response = format!("[{}]\n[{}:{}]", infraction_type.name(), mod_id.to_user(&ctx.http()).await.unwrap().mention(), mod_id);
+            let mut affected = vec![];
             for affected_id in &affected_ids {
-                let result = do_affected_id(&ctx.data().rbx_client, affected_id).await;
+                let result = do_affected_id(&ctx.data().reqwest_client, &ctx.data().rbx_client, &helper::bloxlink_for(ctx.data(), ctx.guild_id()), affected_id).await;
                 for err in result.1 {
-                    ctx.say(err).await.unwrap();
+                    ctx.say(err).await?;
                 }
-                response.push_str(result.0.as_str());
+                affected.extend(result.0);
             }
-            response.push_str(format!("\n[{}]", reason1).as_str());
-            response.push_str(format!("\n[{}]", reason2).as_str());
-            ctx.say(response).await?;
+            let log = ModLog::FalseInfraction { infraction: infraction_type, moderator: LogModerator::Mention(*mod_id), affected, reason: reason1.clone(), invalidation_reason: reason2.clone() };
+            let message = ctx.say(log.to_string()).await?.into_message().await?;
+            ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
         } else {
-            let mut response = format!("[{}]\n[{}:{}]", infraction_type.name(), mod_id.to_user(&ctx.http()).await.unwrap().name, mod_id);
+            let moderator = LogModerator::Named { name: mod_id.to_user(&ctx.http()).await?.name, id: *mod_id };
             let affected_id = affected_ids[affected_iter];
-            let result = do_affected_id(&ctx.data().rbx_client, affected_id).await;
+            let result = do_affected_id(&ctx.data().reqwest_client, &ctx.data().rbx_client, &helper::bloxlink_for(ctx.data(), ctx.guild_id()), affected_id).await;
             for err in result.1 {
-                ctx.say(err).await.unwrap();
+                ctx.say(err).await?;
             }
-            response.push_str(result.0.as_str());
-            response.push_str(format!("\n[{}]", reason1).as_str());
-            response.push_str(format!("\n[{}]", reason2).as_str());
-            ctx.say(response).await?;
+            let log = ModLog::FalseInfraction { infraction: infraction_type, moderator, affected: result.0, reason: reason1.clone(), invalidation_reason: reason2.clone() };
+            let message = ctx.say(log.to_string()).await?.into_message().await?;
+            ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
             if affected_ids.get(affected_iter + 1).is_some() {affected_ids.remove(affected_iter); affected_iter += 1};
         }
     }
@@ -1,4 +1,4 @@
-use super::{Context, Error, UserId, helper, Mentionable, serenity, FromStr};
+use super::{Context, Error, UserId, helper, serenity, FromStr};
 
 pub mod false_infraction;
 pub mod discord_log;
@@ -1,9 +1,15 @@
 use poise::ChoiceParameter;
 use serenity::User;
 
-use super::{Context, Error, helper, UserId, Mentionable, serenity, FromStr};
+use crate::commands::probation_module::track_probation;
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::{LogUser, ModLog, RobloxUser};
+use crate::main_modules::probation::Probation;
+use super::{Context, Error, helper, UserId, serenity, FromStr};
+use crate::commands::permissions_module::is_moderator;
+use crate::main_modules::error::BotError;
 
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum ProbationTypes {
     #[name = "Roblox Ban"]
     RobloxBan,
@@ -11,7 +17,7 @@ pub enum ProbationTypes {
     DiscordBan
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
 /// Makes a probation log based on the Discord IDs inputted.
 pub async fn probationlog(
     ctx: Context<'_>,
@@ -28,7 +34,6 @@ pub async fn probationlog(
     }
     let users = purified_users.split(' ');
     let reasons = reason.split('|').map(str::to_string).collect::<Vec<String>>();
-    let type_string = format!("[{}]\n\n", infraction_type.name());
 
     let mut duration_errors = Vec::new();
     let raw_durations = duration.split('|').map(str::to_string).collect::<Vec<String>>();
@@ -38,11 +43,11 @@ pub async fn probationlog(
             let (current_time, unix_timestamp, timestamp_string) = match helper::duration_conversion(duration).await {
                 Ok((current_time, unix_timestamp, timestamp_string)) => (current_time, unix_timestamp, timestamp_string),
                 Err(err) => {
-                    duration_errors.push(err);
+                    duration_errors.push(err.to_string());
                     continue
                 },
             };
-            durations.push(format!("[{} (<t:{}:D> - <t:{}:D>)]", timestamp_string, current_time, unix_timestamp))
+            durations.push((format!("{} (<t:{}:D> - <t:{}:D>)", timestamp_string, current_time, unix_timestamp), current_time, unix_timestamp))
         }
         (durations, duration_errors)
     });
@@ -50,18 +55,25 @@ pub async fn probationlog(
     let mut reason_number = 0;
     let mut response_vec = Vec::new();
     for snowflake in users {
-        let userid: UserId = UserId::from_str(snowflake).expect("something went wrong.");
+        let Ok(userid) = UserId::from_str(snowflake) else {
+            ctx.say(format!("`{}` isn't a valid Discord id, skipping it.", snowflake)).await?;
+            continue
+        };
         let reqwest_client = ctx.data().reqwest_client.clone();
         let rbx_client = ctx.data().rbx_client.clone();
+        let bloxlink = helper::bloxlink_for(ctx.data(), ctx.guild_id());
         let roblox_handler = tokio::spawn(async move {
             let mut roblox_errors = Vec::new();
-            let roblox_id = match helper::discord_id_to_roblox_id(&reqwest_client, userid).await {
-                Ok(roblox_id) => roblox_id,
-                Err(_) => {roblox_errors.push(format!("A error occured on Bloxlink's end when getting {}'s Roblox id. The user may be not verified with Bloxlink or Bloxlink is down.", userid));
-                "null".to_string()}
+            let roblox = match helper::discord_id_to_roblox_id(&reqwest_client, &bloxlink, userid).await {
+                Ok(id) => match rbx_client.user_details(id).await {
+                    Ok(details) => Some(RobloxUser { username: details.username, id }),
+                    Err(err) => {roblox_errors.push(BotError::roblox(err).to_string());
+                    None}
+                },
+                Err(err) => {roblox_errors.push(err.to_string());
+                None}
             };
-            let roblox_user = if roblox_id != *"null".to_string() {rbx_client.user_details(roblox_id.parse::<u64>().expect("err")).await.expect("err").username} else { "null".to_string() };
-            (roblox_id, roblox_user, roblox_errors)
+            (roblox, roblox_errors)
         });
         let user: User = match userid.to_user(ctx).await {
             Ok(user) => user,
@@ -70,20 +82,33 @@ pub async fn probationlog(
                 continue
             }
         };
-        let (roblox_user, roblox_id, roblox_errors) = roblox_handler.await.unwrap();
+        let (roblox, roblox_errors) = roblox_handler.await?;
         for error in roblox_errors {ctx.say(error).await?;}
-        response_vec.push(format!("{}[{}:{} - {}:{}]\n\n[{}]\n\n", type_string, user.mention(), user.id, roblox_user, roblox_id, reasons[reason_number]));
+        response_vec.push((LogUser::Linked { discord_id: user.id, roblox }, reasons[reason_number].clone()));
         if reasons.get(reason_number + 1).is_some() { reason_number += 1 }
     }
 
-    let (durations, duration_errors) = duration_handler.await.unwrap();
+    let (durations, duration_errors) = duration_handler.await?;
     for error in duration_errors {
         ctx.say(error).await?;
     }
     let mut duration_number = 0;
-    for response in response_vec {
-        let response = format!("{}{}", response, match durations.get(duration_number) { Some(dur) => dur, None => continue });
-        ctx.say(response).await?;
+    for (user, reason) in response_vec {
+        let (duration, start, end) = match durations.get(duration_number) { Some(dur) => dur.clone(), None => continue };
+        let subject = user.subject();
+        let log = ModLog::Probation { infraction: infraction_type, user, reason, duration };
+        let message = ctx.say(log.to_string()).await?.into_message().await?;
+        ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
+        track_probation(ctx.data(), Probation {
+            id: 0,
+            discord_id: subject.discord_id.unwrap_or_default(),
+            roblox_id: subject.roblox_id,
+            probation_type: infraction_type.name().to_string(),
+            reason: log.reason().to_string(),
+            moderator_id: ctx.author().id.get(),
+            start,
+            end,
+        }).await?;
         if durations.get(duration_number + 1 ).is_some() { duration_number += 1 }
     }
     Ok(())
@@ -1,9 +1,15 @@
-use poise::ChoiceParameter;
 use serenity::all::CreateMessage;
 
 use super::{Context, Error, helper};
+use crate::commands::game_module::{schedule_ban_lift, temp_ban_note};
+use crate::commands::probation_module::flag_escalations;
+use crate::main_modules::game_sanctions::{GameSanction, SanctionKind, unix_now};
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::{ModLog, RobloxUser};
+use crate::commands::permissions_module::is_moderator;
+use crate::main_modules::error::BotError;
 
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum RobloxInfTypes {
     #[name = "Game Ban"]
     Ban,
@@ -13,9 +19,28 @@ pub enum RobloxInfTypes {
     ServerBan,
     Kick,
     Warn,
+    #[name = "Game Unban"]
+    Unban,
 }
 
-#[poise::command(slash_command, prefix_command)]
+/// Tracks a logged temporary game ban so it is lifted automatically when it ends.
+async fn schedule_temp_bans(
+    ctx: Context<'_>,
+    users: &[RobloxUser],
+    reason: &str,
+    expires_at: u64,
+) -> Result<(), Error> {
+    for user in users {
+        let mut sanction =
+            GameSanction::new(SanctionKind::Ban, user.id, ctx.author().id, reason, unix_now())
+                .expires_at(expires_at);
+        sanction.id = ctx.data().game_sanctions.add(sanction.clone())?;
+        schedule_ban_lift(ctx.data(), &sanction, ctx.channel_id()).await?;
+    }
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
 /// Creates a log for a ingame infraction. **Do not input Discord IDs as a test, please.**
 #[warn(clippy::too_many_arguments)]
 pub async fn robloxlog(
@@ -29,9 +54,33 @@ pub async fn robloxlog(
     #[description = "Note for the infraction, split with |."] note: Option<String>,
     #[description = "Multimessage mode allows creation of multiple logs from 1 command."]
     multimessage: Option<bool>,
+    #[description = "Duration of a temporary game ban (e.g., '1h', '2d', '1w'), lifted when it ends."]
+    duration: Option<String>,
 ) -> Result<(), Error> {
     ctx.reply("Making logs...").await?;
     let multimessage = multimessage.unwrap_or_default();
+    let expiry = match (infraction_type, duration) {
+        (RobloxInfTypes::TempBan, Some(duration)) => {
+            match helper::duration_conversion(duration).await {
+                Ok((_, unix_timestamp, timestamp_string)) => Some((unix_timestamp, timestamp_string)),
+                Err(err) => {
+                    ctx.say(err.to_string()).await?;
+                    return Ok(());
+                }
+            }
+        }
+        (RobloxInfTypes::TempBan, None) => {
+            ctx.say("No duration was given, this temporary game ban won't be lifted automatically.")
+                .await?;
+            None
+        }
+        (_, Some(_)) => {
+            ctx.say("Durations are only used for temporary game bans, ignoring it.")
+                .await?;
+            None
+        }
+        (_, None) => None,
+    };
     let users: Vec<String> = users
         .split(' ')
         .map(str::to_string)
@@ -39,7 +88,13 @@ pub async fn robloxlog(
     let roblox_conversion_errors;
     let roblox_ids;
     (roblox_ids, roblox_conversion_errors) =
-        helper::merge_types(&ctx.data().reqwest_client, &ctx.data().rbx_client, users).await;
+        helper::merge_types(
+            &ctx.data().reqwest_client,
+            &ctx.data().rbx_client,
+            &helper::bloxlink_for(ctx.data(), ctx.guild_id()),
+            users,
+        )
+        .await;
     if roblox_ids.is_empty() {
         ctx.channel_id().say(ctx, "Command failed; every user was converted and no valid users were found, meaning you might have inputted the users incorrectly...").await?;
         return Ok(());
@@ -59,8 +114,7 @@ pub async fn robloxlog(
         .map(str::to_string)
         .collect::<Vec<String>>();
 
-    let mut users_string = String::new();
-    let mut user_string_vec: Vec<String> = Vec::new();
+    let mut log_users: Vec<RobloxUser> = Vec::new();
     for id in roblox_ids {
         if id.is_empty() {
             continue;
@@ -68,24 +122,24 @@ pub async fn robloxlog(
         let user_details = ctx
             .data()
             .rbx_client
-            .user_details(id.parse::<u64>().expect("err"))
-            .await?;
-        let value = format!("[{}:{}]\n", user_details.username, user_details.id);
-        if !multimessage {
-            users_string.push_str(value.as_str())
-        } else {
-            user_string_vec.push(value)
-        };
+            .user_details(id.parse::<u64>()?)
+            .await
+            .map_err(BotError::roblox)?;
+        log_users.push(RobloxUser {
+            username: user_details.username,
+            id: user_details.id,
+        });
         let serenity_ctx = ctx.serenity_context().clone();
         let channel_id = ctx.channel_id();
         let reqwest_client = ctx.data().reqwest_client.clone();
+        let bloxlink = helper::bloxlink_for(ctx.data(), ctx.guild_id());
         let data = ctx.data().clone();
         tokio::spawn(async move {
             let embed = helper::new_embed_from_template(&data)
                 .await
                 .title("Additional Information")
-                .color(data.bot_color);
-            if let Ok(associated_ids) = helper::roblox_id_to_discord_ids(&reqwest_client, id).await
+                .color(data.config.get().main.color);
+            if let Ok(associated_ids) = helper::roblox_id_to_discord_ids(&reqwest_client, &bloxlink, id).await
                 && let Err(err) = channel_id
                     .send_message(
                         &serenity_ctx.http,
@@ -103,39 +157,63 @@ pub async fn robloxlog(
                     )
                     .await
             {
-                eprintln!("Failed to send message: {err:?}");
+                tracing::error!(error = %err, "Couldn't send the log message");
             }
         });
     }
 
-    let type_string = format!("[{}]\n", infraction_type.name());
+    let note = |index: usize| {
+        let note = Some(notes[index].clone()).filter(|note| !note.is_empty());
+        match &expiry {
+            Some((unix_timestamp, timestamp_string)) => {
+                Some(temp_ban_note(note, timestamp_string, *unix_timestamp))
+            }
+            None => note,
+        }
+    };
     if !multimessage {
-        let reason_string = format!("[{}]", reasons[0]);
-        let note_string = if !notes[0].is_empty() {
-            format!("\nNote: {}", notes[0])
-        } else {
-            String::new()
+        if let Some((unix_timestamp, _)) = &expiry {
+            schedule_temp_bans(ctx, &log_users, &reasons[0], *unix_timestamp).await?;
+        }
+        let log = ModLog::Roblox {
+            infraction: infraction_type,
+            users: log_users,
+            reason: reasons[0].clone(),
+            note: note(0),
         };
-        let response = format!(
-            "{}{}{}{}",
-            type_string, users_string, reason_string, note_string
-        );
-        ctx.say(response).await?;
+        let message = ctx.say(log.to_string()).await?.into_message().await?;
+        ctx.data().logging_db.record(
+            LogEntry::from(&log)
+                .moderated_by(ctx.author().id)
+                .posted_as(&message),
+        )?;
+        flag_escalations(ctx, &log).await?;
     } else {
         let mut reason_number = 0;
         let mut note_number = 0;
-        for user_string in user_string_vec {
-            let reason_string = format!("[{}]", reasons[reason_number]);
-            let note_string = if !notes[note_number].is_empty() {
-                format!("\nNote: {}", notes[note_number])
-            } else {
-                String::new()
+        for user in log_users {
+            if let Some((unix_timestamp, _)) = &expiry {
+                schedule_temp_bans(
+                    ctx,
+                    std::slice::from_ref(&user),
+                    &reasons[reason_number],
+                    *unix_timestamp,
+                )
+                .await?;
+            }
+            let log = ModLog::Roblox {
+                infraction: infraction_type,
+                users: vec![user],
+                reason: reasons[reason_number].clone(),
+                note: note(note_number),
             };
-            let response = format!(
-                "{}{}{}{}",
-                type_string, user_string, reason_string, note_string
-            );
-            ctx.say(response).await?;
+            let message = ctx.say(log.to_string()).await?.into_message().await?;
+            ctx.data().logging_db.record(
+                LogEntry::from(&log)
+                    .moderated_by(ctx.author().id)
+                    .posted_as(&message),
+            )?;
+            flag_escalations(ctx, &log).await?;
             if reasons.get(reason_number + 1).is_some() {
                 reason_number += 1
             }
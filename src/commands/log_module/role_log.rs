@@ -1,10 +1,13 @@
 // Command for making discord-side logs
-use poise::ChoiceParameter;
 use serenity::User;
 
-use super::{Context, Error, helper, UserId, Mentionable, serenity, FromStr};
+use crate::main_modules::logging_database::LogEntry;
+use crate::main_modules::mod_log::{LogUser, ModLog, RobloxUser};
+use super::{Context, Error, helper, UserId, serenity, FromStr};
+use crate::commands::permissions_module::is_moderator;
+use crate::main_modules::error::BotError;
 
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum RoleEnums {
     #[name = "Dedicated Player"]
     DedicatedPlayer,
@@ -52,13 +55,13 @@ pub enum RoleEnums {
     #[name = "Content Creator"]
     ContentCreator,
 }
-#[derive(Debug, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum LogType {
     Addition,
     Removal,
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Moderator", check = "is_moderator")]
 /// Makes a role addition or deletion log based on the Discord IDs inputted.
 pub async fn rolelog(
     ctx: Context<'_>,
@@ -78,18 +81,25 @@ pub async fn rolelog(
     let users = purified_users.split(' ');
     let reason = reason.unwrap_or_default();
     for snowflake in users {
-        let userid: UserId = UserId::from_str(snowflake).expect("something went wrong.");
+        let Ok(userid) = UserId::from_str(snowflake) else {
+            ctx.say(format!("`{}` isn't a valid Discord id, skipping it.", snowflake)).await?;
+            continue
+        };
         let reqwest_client = ctx.data().reqwest_client.clone();
         let rbx_client = ctx.data().rbx_client.clone();
+        let bloxlink = helper::bloxlink_for(ctx.data(), ctx.guild_id());
         let roblox_handler = tokio::spawn(async move {
             let mut roblox_errors = Vec::new();
-            let roblox_id = match helper::discord_id_to_roblox_id(&reqwest_client, userid).await {
-                Ok(roblox_id) => roblox_id,
-                Err(_) => {roblox_errors.push(format!("A error occured on Bloxlink's end when getting {}'s Roblox id. The user may be not verified with Bloxlink or Bloxlink is down.", userid));
-                "null".to_string()}
+            let roblox = match helper::discord_id_to_roblox_id(&reqwest_client, &bloxlink, userid).await {
+                Ok(id) => match rbx_client.user_details(id).await {
+                    Ok(details) => Some(RobloxUser { username: details.username, id }),
+                    Err(err) => {roblox_errors.push(BotError::roblox(err).to_string());
+                    None}
+                },
+                Err(err) => {roblox_errors.push(err.to_string());
+                None}
             };
-            let roblox_user = if roblox_id != *"null".to_string() {rbx_client.user_details(roblox_id.parse::<u64>().expect("err")).await.expect("err").username} else { "null".to_string() };
-            (roblox_id, roblox_user, roblox_errors)
+            (roblox, roblox_errors)
         });
         let user: User = match userid.to_user(ctx).await {
             Ok(user) => user,
@@ -98,11 +108,11 @@ pub async fn rolelog(
                 continue
             }
         };
-        let (roblox_user, roblox_id, roblox_errors) = roblox_handler.await.unwrap();
+        let (roblox, roblox_errors) = roblox_handler.await?;
         for error in roblox_errors {ctx.say(error).await?;}
-        let mut response = format!("[{}]\n[{}]\n[{}:{} - {}:{}]", infraction_type.name(), role.name(), user.mention(), user.id, roblox_user, roblox_id);
-        if !reason.is_empty() {response.push_str(format!("\n[{}]", reason).as_str())}
-        ctx.say(response).await?;
+        let log = ModLog::Role { change: infraction_type, role, user: LogUser::Linked { discord_id: user.id, roblox }, reason: Some(reason.clone()).filter(|reason| !reason.is_empty()) };
+        let message = ctx.say(log.to_string()).await?.into_message().await?;
+        ctx.data().logging_db.record(LogEntry::from(&log).moderated_by(ctx.author().id).posted_as(&message))?;
     }
     Ok(())
 }
\ No newline at end of file
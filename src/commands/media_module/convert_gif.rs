@@ -1,27 +1,27 @@
 use std::io::Write;
+use std::path::Path;
 use ::serenity::all::Attachment;
-use serenity::all::CreateMessage;
+use serenity::all::{CreateMessage, EditMessage};
 use uuid::Uuid;
 
-use super::{Context, Error, video_format_changer, image_to_png_converter, png_to_gif_converter, video_to_gif_converter, QualityPreset};
+use super::{Context, Error, video_format_changer, image_to_png_converter, png_to_gif_converter, video_to_gif_converter, fit_output, QualityPreset};
+use crate::commands::permissions_module::is_staff;
+use crate::main_modules::error::BotError;
+use crate::main_modules::media_format::{MediaFormat, MediaKind, sniff};
+use crate::main_modules::media_queue::{Progress, StatusMessage};
+use crate::main_modules::upload_size::guild_upload_limit;
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// Command for converting any video/display format to a gif, dynamically, for free.
 pub async fn gif(
     ctx: Context<'_>,
     #[description = "Attachment for command."] attachment: Attachment,
     #[description = "Quality Preset for the command."] quality_preset: Option<QualityPreset>
 ) -> Result<(), Error> {
-    ctx.reply("Converting attachment into gif, this may take a while!").await.unwrap();
+    let label = "Converting attachment into gif, this may take a while!";
+    let reply = ctx.reply(label).await?;
     let quality_preset = quality_preset.unwrap_or(QualityPreset::HighQuality);
-    
-    let content_type = match attachment.content_type {
-        Some(ct) => ct,
-        None => {
-            ctx.say("No content type found.").await?;
-            return Ok(());
-        }
-    };
+    let upload_limit = guild_upload_limit(&ctx.serenity_context().cache, ctx.guild_id());
 
     let main_input_filename = format!("./.tmp/main_input_{}..tmp", Uuid::new_v4());
     let response = ctx.data().reqwest_client.get(&attachment.url).send().await?;
@@ -29,98 +29,86 @@ pub async fn gif(
     let mut file = std::fs::File::create(&main_input_filename)?;
     file.write_all(&bytes)?;
 
-    let result = if content_type.contains("video/") {
-        convert_video(&content_type, main_input_filename, quality_preset).await
-    } else if content_type.contains("image") {
-        convert_image(&content_type, main_input_filename, quality_preset).await
-    } else {
-        std::fs::remove_file(&main_input_filename)?;
-        Err(Error::from("Unsupported file type"))
+    let queued = async {
+        let format = sniff(Path::new(&main_input_filename), &attachment.filename).await?;
+        let kind = if format.kind() == MediaKind::Image { "image_to_gif" } else { "video_to_gif" };
+        Ok::<_, BotError>((ctx.data().media_queue.enqueue(ctx.author().id, kind)?, format))
+    }.await;
+    let (job, format) = match queued {
+        Ok(queued) => queued,
+        Err(err) => {
+            std::fs::remove_file(&main_input_filename)?;
+            return Err(err.into());
+        }
     };
+    let msg = reply.message().await?;
+    let status = StatusMessage::new(ctx.serenity_context().http.clone(), msg.channel_id, msg.id, label);
 
-    match result {
-        Ok(output_filename) => {
-            let send_result = async {
-                let file = serenity::all::CreateAttachment::path(&output_filename).await?;
-                let builder = CreateMessage::new().content("Done!");
-                ctx.channel_id().send_files(&ctx.http(), vec![file], builder).await?;
-                Ok::<_, Error>(())
-            }.await;
-
-            std::fs::remove_file(output_filename).unwrap();
-
-            if let Err(e) = send_result {
-                ctx.say(format!("Failed to send the converted file: {}", e)).await?;
-            }
-        },
-        Err(e) => {
-            ctx.say(format!("Failed to convert the file: {}", e)).await?;
-        }
-    }
+    let result = job.run(&status, |progress| async move {
+        let output_filename = if format.kind() == MediaKind::Image {
+            convert_image(format, main_input_filename, quality_preset).await?
+        } else {
+            convert_video(format, main_input_filename, quality_preset, &progress).await?
+        };
+        let fitted = fit_output(&output_filename, upload_limit, &progress).await?;
+        Ok((output_filename, fitted))
+    }).await;
+    let (output_filename, fitted) = match result {
+        Ok(output) => output,
+        Err(err) => return Ok(status.fail(err).await?),
+    };
 
-    Ok(())
+    let send_result = async {
+        let file = serenity::all::CreateAttachment::path(&output_filename).await?;
+        let builder = CreateMessage::new().content(format!("Done! {}", fitted));
+        ctx.channel_id().send_files(&ctx.http(), vec![file], builder).await?;
+        status.finish(EditMessage::new().content(format!("{}\nDone!", label))).await?;
+        Ok::<_, Error>(())
+    }.await;
+    std::fs::remove_file(output_filename)?;
+    send_result
 }
 
-async fn convert_video(content_type: &str, input: String, quality_preset: QualityPreset) -> Result<String, Error> {
+async fn convert_video(format: MediaFormat, input: String, quality_preset: QualityPreset, progress: &Progress) -> Result<String, BotError> {
     let output_filename = format!("./.tmp/output_{}.gif", Uuid::new_v4());
-    
-    let result = if content_type != "video/mp4" {
+
+    let result = if format != MediaFormat::Mp4 {
         let mp4_output_filename = format!("./.tmp/output_{}.mp4", Uuid::new_v4());
-        let output = video_format_changer(&input, &mp4_output_filename);
-        std::fs::remove_file(&input).ok();
-        
-        if output.status.success() {
-            let gif_output = video_to_gif_converter(&mp4_output_filename, &output_filename, quality_preset);
-            std::fs::remove_file(&mp4_output_filename).ok();
-            handle_command_output(gif_output, output_filename.clone())
-        } else {
-            print!("{:#?}", output);
-            std::fs::remove_file(&mp4_output_filename).ok();
-            Err(Error::from("Failed to convert video format"))
+        let mut result = video_format_changer(&input, &mp4_output_filename, progress).await;
+        if result.is_ok() {
+            result = video_to_gif_converter(&mp4_output_filename, &output_filename, quality_preset, progress).await;
         }
+        std::fs::remove_file(&mp4_output_filename).ok();
+        result
     } else {
-        let output = video_to_gif_converter(&input, &output_filename, quality_preset);
-        std::fs::remove_file(&input).ok();
-        handle_command_output(output, output_filename.clone())
+        video_to_gif_converter(&input, &output_filename, quality_preset, progress).await
     };
+    std::fs::remove_file(&input).ok();
 
     if result.is_err() {
         std::fs::remove_file(&output_filename).ok();
     }
-    
-    result
+    result.map(|()| output_filename)
 }
 
-async fn convert_image(content_type: &str, input: String, quality_preset: QualityPreset) -> Result<String, Error> {
+async fn convert_image(format: MediaFormat, input: String, quality_preset: QualityPreset) -> Result<String, BotError> {
     let output_filename = format!("./.tmp/output_{}.gif", Uuid::new_v4());
-    
-    if content_type != "image/png" {
+
+    let result = if format != MediaFormat::Png {
         let png_output_filename = format!("./.tmp/output_{}.png", Uuid::new_v4());
-        let output = image_to_png_converter(&input, &png_output_filename);
-        std::fs::remove_file(&input).unwrap();
-        
-        if output.status.success() {
-            let gif_output: Result<(), std::io::Error> = png_to_gif_converter(&png_output_filename, &output_filename, quality_preset);
-            std::fs::remove_file(&png_output_filename).unwrap();
-            handle_command_output(gif_output, output_filename.clone())
-        } else {
-            std::fs::remove_file(&png_output_filename).unwrap();
-            Err(Error::from("Failed to convert image to PNG"))
+        let mut result = image_to_png_converter(&input, &png_output_filename).await;
+        if result.is_ok() {
+            result = png_to_gif_converter(&png_output_filename, &output_filename, quality_preset).await;
         }
+        std::fs::remove_file(&png_output_filename).ok();
+        result
     } else {
-        let output = png_to_gif_converter(&input, &output_filename, quality_preset);
-        std::fs::remove_file(&input).unwrap();
-        handle_command_output(output, output_filename.clone())
-    }
-}
+        png_to_gif_converter(&input, &output_filename, quality_preset).await
+    };
+    std::fs::remove_file(&input).ok();
 
-fn handle_command_output(output: Result<(), std::io::Error>, output_filename: String) -> Result<String, Error> {
-    if output.is_ok() {
-        Ok(output_filename)
-    } else {
-        Err(Error::from(format!(
-            "Conversion failed: {}",
-            &output.err().unwrap().to_string()
-        )))
+    if result.is_err() {
+        std::fs::remove_file(&output_filename).ok();
     }
-}
\ No newline at end of file
+    result.map(|()| output_filename)
+}
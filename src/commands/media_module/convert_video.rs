@@ -3,8 +3,9 @@ use serenity::all::MessageId;
 use super::{Context, Error, video_convert};
 use poise::serenity_prelude as serenity;
 use std::sync::Arc;
+use crate::commands::permissions_module::is_staff;
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// Command for activating the video convertor on specific messages.
 pub async fn convert_video(
     ctx: Context<'_>,
@@ -58,19 +59,22 @@ async fn process_message(
         }
     }
 
-    let message = match message {
+    let mut message = match message {
         Some(msg) => msg,
         None => {
-            println!("Couldn't find message in guild, sorry!");
+            tracing::warn!(%message_id, "Couldn't find the message to convert in the guild");
             return;
         }
     };
 
+    // Messages fetched over HTTP don't say which guild they're in, which the upload limit needs.
+    message.guild_id = message.guild_id.or(ctx.guild_id());
+
     let futures = message.attachments.iter().map(|attachment| {
         let message = message.clone();
         let attachment = attachment.clone();
         async move {
-            video_convert(message, ctx.serenity_context().clone(), ctx.data().reqwest_client.clone(), attachment).await;
+            video_convert(message, ctx.serenity_context().clone(), ctx.data().clone(), attachment, ctx.author().id).await;
         }
     });
 
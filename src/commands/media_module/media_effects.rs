@@ -1,83 +1,140 @@
-use std::{fmt, io::Write};
 use serenity::all::{Attachment, EditMessage};
-use uuid::Uuid;
+use image::ImageFormat;
 
-use super::{Context, Error, apply_mask};
-
-#[derive(Debug, poise::ChoiceParameter)]
-pub enum SpeechBubbleOverlays {
-    #[name = "esm Bot Style"]
-    EsmBotStyle,
-    #[name = "RON Bot Style"]
-    RONBotStyle
-}
-
-impl fmt::Display for SpeechBubbleOverlays {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SpeechBubbleOverlays::EsmBotStyle => write!(f, "EsmBotStyle"),
-            SpeechBubbleOverlays::RONBotStyle => write!(f, "RONBotStyle"),
-        }
-    }
-}
+use super::{Context, Error, apply_mask, fit_output};
+use crate::commands::permissions_module::{is_admin, is_staff};
+use crate::main_modules::error::BotError;
+use crate::main_modules::mask_library::{DEFAULT_MASK, Mask};
+use crate::main_modules::media_format::{MediaFormat, sniff};
+use crate::main_modules::media_queue::StatusMessage;
+use crate::main_modules::upload_size::guild_upload_limit;
 
 use std::{fs, path::Path};
+use tempfile::tempdir_in;
+
+/// Largest overlay PNG `/media mask add` takes.
+const MAX_MASK_SIZE: u32 = 5 * 1024 * 1024;
+/// Longest mask name, well under Discord's limit for autocomplete choices.
+const MAX_MASK_NAME_LEN: usize = 50;
 
-#[poise::command(slash_command, prefix_command, subcommand_required, subcommands("speechbubble"))]
+#[poise::command(slash_command, prefix_command, subcommand_required, subcommands("speechbubble", "mask"), category = "Staff", check = "is_staff")]
 pub async fn media(_: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+async fn autocomplete_mask(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    match ctx.data().mask_library.names_matching(partial) {
+        Ok(mut names) => {
+            names.truncate(25);
+            names
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, "Couldn't list speech bubble masks");
+            vec![]
+        }
+    }
+}
+
 #[poise::command(prefix_command, slash_command)]
 pub async fn speechbubble(
     ctx: Context<'_>,
     #[description = "Attachment for command."] attachment: Attachment,
-    #[description = "Overlay type, defaults to esm bot style."] style: Option<SpeechBubbleOverlays>,
-    #[description = "A bit technical, but what should the height of the overlay be divided by? In 0.0-1.0."] height_float: Option<f32>,
-    #[description = "Should the speech bubble be flipped horizontally?"] flip: Option<bool>,
+    #[description = "Overlay to use, defaults to esm bot style."] #[autocomplete = "autocomplete_mask"] style: Option<String>,
+    #[description = "A bit technical, but what should the height of the overlay be divided by? In 0.0-1.0. Defaults to the mask's own."] height_float: Option<f32>,
+    #[description = "Should the speech bubble be flipped horizontally? Defaults to the mask's own preference."] flip: Option<bool>,
     #[description = "Should the speech bubble be transparent? By default set to true if image."] transparent: Option<bool>,
     #[description = "Should the speech bubble be, if its an image, be converted to gif? False for yes, true for no."] no_force_gif: Option<bool>,
 ) -> Result<(), Error> {
-    let msg = ctx.say("Adding speechbubble...").await?;
-    let style = style.unwrap_or(SpeechBubbleOverlays::EsmBotStyle);
+    let style = style.unwrap_or_else(|| DEFAULT_MASK.to_string());
+    let Some((mask, overlay)) = ctx.data().mask_library.get(&style)? else {
+        return Err(BotError::InvalidInput(format!("There's no speech bubble mask called `{}`.", style)).into());
+    };
+
+    let label = "Adding speechbubble...";
+    let reply = ctx.say(label).await?;
 
     let response = ctx.data().reqwest_client.get(&attachment.url).send().await?;
     let bytes = response.bytes().await?;
 
-    let input_extension = Path::new(&attachment.filename)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .ok_or("Invalid file extension")?
-        .to_lowercase();
+    // Holds the input and overlay, removed however the command returns.
+    let work_dir = tempdir_in("./.tmp")?;
+    let input_path = work_dir.path().join("input").to_string_lossy().into_owned();
+    fs::write(&input_path, &bytes)?;
+    let input_format = sniff(Path::new(&input_path), &attachment.filename).await?;
 
-    let input_path = match input_extension.as_str() {
-        "png" | "jpg" | "jpeg" | "mp4" | "mov" => format!("./.tmp/input_{}.{}", Uuid::new_v4(), input_extension),
-        _ => return Err("Unsupported file format".into()),
+    let overlay_path = work_dir.path().join("mask.png").to_string_lossy().into_owned();
+    fs::write(&overlay_path, &overlay)?;
+
+    let upload_limit = guild_upload_limit(&ctx.serenity_context().cache, ctx.guild_id());
+    let job = ctx.data().media_queue.enqueue(ctx.author().id, "speech_bubble");
+    let msg = reply.into_message().await?;
+    let status = StatusMessage::new(ctx.serenity_context().http.clone(), msg.channel_id, msg.id, label);
+    let (mask_input, mask_overlay) = (input_path, overlay_path.as_str());
+    let output = match job {
+        Ok(job) => job.run(&status, |progress| async move {
+            let output_path = apply_mask(mask_input, input_format, mask_overlay, flip.unwrap_or(mask.flip), height_float.unwrap_or(mask.height_ratio), transparent.unwrap_or(true), no_force_gif.unwrap_or(false), &progress).await?;
+            let fitted = fit_output(&output_path, upload_limit, &progress).await?;
+            Ok((output_path, fitted))
+        }).await,
+        Err(err) => Err(err),
+    };
+    let (output_path, fitted) = match output {
+        Ok(output) => output,
+        Err(err) => return Ok(status.fail(err).await?),
     };
 
-    let mut file = fs::File::create(&input_path)?;
-    file.write_all(&bytes)?;
-    
-    let overlay_path = format!("./.default_masks/{}.png", style);
+    let sent = match serenity::all::CreateAttachment::path(&output_path).await {
+        Ok(file) => status.finish(EditMessage::new().new_attachment(file).content(format!("Done! {}", fitted))).await,
+        Err(err) => Err(err.into()),
+    };
+    let _ = fs::remove_file(&output_path);
 
-    if !Path::new(&overlay_path).exists() {
-        return Err(format!("Overlay file not found: {}", overlay_path).into());
-    }
+    Ok(sent?)
+}
 
-    let output_path = match apply_mask(input_path.clone(), &overlay_path, flip.unwrap_or(false), height_float.unwrap_or(0.2), transparent.unwrap_or(true), no_force_gif.unwrap_or(false)) {
-        Ok(str) => {
-            str
-        },
-        Err(err) => {
-            return Err(err.into());
-        }
-    };
+#[poise::command(prefix_command, slash_command, subcommand_required, subcommands("mask_add"))]
+pub async fn mask(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
 
-    let file = serenity::all::CreateAttachment::path(&output_path).await?;
-    msg.into_message().await?.edit(ctx.http(), EditMessage::new().new_attachment(file).content("Done!")).await?;
+#[poise::command(prefix_command, slash_command, rename = "add", category = "Admin", check = "is_admin")]
+/// Add a speech bubble overlay for `/media speechbubble` to pick.
+pub async fn mask_add(
+    ctx: Context<'_>,
+    #[description = "Name to pick the mask by."] name: String,
+    #[description = "PNG of the speech bubble, transparent where the input should show through."] overlay: Attachment,
+    #[description = "How much of the input's height it covers by default, in 0.0-1.0. Defaults to 0.2."] height_ratio: Option<f32>,
+    #[description = "Should it be flipped horizontally by default?"] flip: Option<bool>,
+) -> Result<(), Error> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_MASK_NAME_LEN {
+        return Err(BotError::InvalidInput(format!("Mask names have to be 1 to {} characters long.", MAX_MASK_NAME_LEN)).into());
+    }
+    let height_ratio = height_ratio.unwrap_or(0.2);
+    if !(height_ratio > 0.0 && height_ratio <= 1.0) {
+        return Err(BotError::InvalidInput("The height ratio has to be above 0 and at most 1.".to_string()).into());
+    }
+    if overlay.size > MAX_MASK_SIZE {
+        return Err(BotError::InvalidInput(format!("Overlays can be at most {} MiB.", MAX_MASK_SIZE / 1024 / 1024)).into());
+    }
 
-    fs::remove_file(&input_path)?;
-    fs::remove_file(&output_path)?;
+    ctx.defer().await?;
+    let response = ctx.data().reqwest_client.get(&overlay.url).send().await?;
+    let png = response.bytes().await?;
+    if MediaFormat::from_magic(&png) != Some(MediaFormat::Png) {
+        return Err(BotError::InvalidInput(format!("`{}` isn't a PNG.", overlay.filename)).into());
+    }
+    image::load_from_memory_with_format(&png, ImageFormat::Png).map_err(BotError::from)?;
 
+    let mask = Mask {
+        name: name.to_string(),
+        height_ratio,
+        flip: flip.unwrap_or(false),
+        added_by: ctx.author().id.get(),
+    };
+    if !ctx.data().mask_library.add(&mask, &png)? {
+        return Err(BotError::InvalidInput(format!("There's already a mask called `{}`.", name)).into());
+    }
+    ctx.say(format!("Added the `{}` speech bubble mask.", name)).await?;
     Ok(())
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use super::{Context, Error, QualityPreset, video_format_changer, video_convert, image_to_png_converter, png_to_gif_converter, video_to_gif_converter, apply_mask};
+use super::{Context, Error, QualityPreset, video_format_changer, video_convert, image_to_png_converter, png_to_gif_converter, video_to_gif_converter, apply_mask, fit_output};
 
 pub mod convert_video;
 pub mod convert_gif;
@@ -1,6 +1,7 @@
-use super::{Context, Error, helper, QualityPreset, UserId, Mentionable, serenity, FromStr, CONFIG, video_format_changer, video_convert, image_to_png_converter, video_to_gif_converter, png_to_gif_converter, apply_mask};
+use super::{Context, Error, helper, QualityPreset, UserId, Mentionable, serenity, FromStr, video_format_changer, video_convert, image_to_png_converter, video_to_gif_converter, png_to_gif_converter, apply_mask, fit_output};
 
 pub mod update;
+pub mod status;
 pub mod log_module;
 pub mod media_module;
 pub mod time_module;
@@ -8,4 +9,9 @@ pub mod info_module;
 pub mod game_module;
 pub mod policy_module;
 pub mod playground;
-pub mod guide_module;
\ No newline at end of file
+pub mod guide_module;
+pub mod log_db;
+pub mod probation_module;
+pub mod config_module;
+pub mod permissions_module;
+pub mod audit_module;
\ No newline at end of file
@@ -0,0 +1,81 @@
+use poise::CreateReply;
+
+use super::{Context, Error};
+use crate::main_modules::permissions::{GuildAdmin, PermissionTier};
+
+pub mod permissions;
+
+/// The highest tier the author has, going by their roles here and, outside the main guild, their
+/// roles in it.
+pub async fn author_tier(ctx: Context<'_>) -> Result<Option<PermissionTier>, Error> {
+    let config = ctx.data().config.get();
+
+    let mut roles = Vec::new();
+    if let Some(member) = ctx.author_member().await {
+        roles.extend(member.roles.iter().copied());
+    }
+    if ctx.guild_id() != Some(config.main.guild_id)
+        && let Ok(member) = config.main.guild_id.member(ctx, ctx.author().id).await
+    {
+        roles.extend(member.roles);
+    }
+
+    Ok(PermissionTier::highest(&config, &roles))
+}
+
+async fn deny(ctx: Context<'_>, requirement: &str) -> Result<bool, Error> {
+    ctx.send(
+        CreateReply::default()
+            .content(format!("You must be {} to use this command.", requirement))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+async fn require(ctx: Context<'_>, tier: PermissionTier) -> Result<bool, Error> {
+    if author_tier(ctx)
+        .await?
+        .is_some_and(|author_tier| author_tier >= tier)
+    {
+        return Ok(true);
+    }
+
+    deny(ctx, &format!("{} or above in Rise of Nations", tier)).await
+}
+
+pub async fn is_staff(ctx: Context<'_>) -> Result<bool, Error> {
+    require(ctx, PermissionTier::Staff).await
+}
+
+pub async fn is_moderator(ctx: Context<'_>) -> Result<bool, Error> {
+    require(ctx, PermissionTier::Moderator).await
+}
+
+pub async fn is_admin(ctx: Context<'_>) -> Result<bool, Error> {
+    require(ctx, PermissionTier::Admin).await
+}
+
+/// Passes Admins and anyone with one of this guild's own admin roles, for commands that only
+/// manage this guild.
+pub async fn is_guild_admin(ctx: Context<'_>) -> Result<bool, Error> {
+    if author_tier(ctx)
+        .await?
+        .is_some_and(|author_tier| author_tier >= PermissionTier::Admin)
+    {
+        return Ok(true);
+    }
+    if let Some(guild_id) = ctx.guild_id()
+        && let Some(member) = ctx.author_member().await
+    {
+        let settings = ctx
+            .data()
+            .guild_settings
+            .get(guild_id, &ctx.data().config.get())?;
+        if GuildAdmin::holds(&settings, &member.roles) {
+            return Ok(true);
+        }
+    }
+
+    deny(ctx, "an admin of this server or Admin in Rise of Nations").await
+}
@@ -0,0 +1,88 @@
+use poise::CreateReply;
+use serenity::all::RoleId;
+use std::collections::BTreeMap;
+
+use super::{Context, Error, author_tier};
+use crate::Data;
+use crate::main_modules::helper;
+use crate::main_modules::permissions::PermissionTier;
+
+/// Collects the commands needing each tier, a command's subcommands inheriting its tier.
+fn commands_by_tier(
+    commands: &[poise::Command<Data, Error>],
+    inherited: Option<PermissionTier>,
+    by_tier: &mut BTreeMap<Option<PermissionTier>, Vec<String>>,
+) {
+    for command in commands {
+        let tier = command
+            .category
+            .as_deref()
+            .and_then(|category| category.parse().ok())
+            .or(inherited);
+        if command.subcommands.is_empty() {
+            by_tier
+                .entry(tier)
+                .or_default()
+                .push(format!("`/{}`", command.qualified_name));
+        } else {
+            commands_by_tier(&command.subcommands, tier, by_tier);
+        }
+    }
+}
+
+fn mention_roles(roles: &[RoleId]) -> String {
+    if roles.is_empty() {
+        return "No roles".to_string();
+    }
+    roles
+        .iter()
+        .map(|role| format!("<@&{}>", role))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[poise::command(slash_command, prefix_command)]
+/// Shows which roles can run which commands.
+pub async fn permissions(ctx: Context<'_>) -> Result<(), Error> {
+    let config = ctx.data().config.get();
+    let mut by_tier = BTreeMap::new();
+    commands_by_tier(&ctx.framework().options().commands, None, &mut by_tier);
+
+    let mut embed = helper::new_embed_from_template(ctx.data())
+        .await
+        .title("Command Permissions")
+        .description(match author_tier(ctx).await? {
+            Some(tier) => format!("You are {}.", tier),
+            None => "You don't have a permission tier.".to_string(),
+        });
+    if let Some(commands) = by_tier.get(&None) {
+        embed = embed.field("Everyone", commands.join(", "), false);
+    }
+    for tier in PermissionTier::ALL {
+        let roles = mention_roles(&tier.role_ids(&config));
+        let commands = by_tier
+            .get(&Some(tier))
+            .map_or("No commands".to_string(), |commands| commands.join(", "));
+        embed = embed.field(
+            format!("{} and above", tier),
+            format!("{}\n{}", roles, commands),
+            false,
+        );
+    }
+
+    if let Some(guild_id) = ctx.guild_id() {
+        let settings = ctx.data().guild_settings.get(guild_id, &config)?;
+        embed = embed.field(
+            "Admins of this server",
+            format!(
+                "{}\n`/settings` here, except `/settings add_guild`",
+                mention_roles(&settings.admin_role_ids)
+            ),
+            false,
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -3,6 +3,7 @@ use poise::CreateReply;
 use serenity::User;
 
 use super::{Context, Error, UserId, serenity, FromStr};
+use crate::commands::permissions_module::is_staff;
 
 #[derive(Debug, poise::ChoiceParameter)]
 pub enum DiscordInfTypes {
@@ -14,7 +15,7 @@ pub enum DiscordInfTypes {
     Warn
 }
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// Makes a ephermal message with all the inputted user ids in mention form.
 pub async fn id_to_mention(
     ctx: Context<'_>,
@@ -32,7 +33,10 @@ pub async fn id_to_mention(
     let users = purified_users.split(' ');
     let mut users_string = String::new();
     for snowflake in users {
-        let userid: UserId = UserId::from_str(snowflake).expect("something went wrong.");
+        let Ok(userid) = UserId::from_str(snowflake) else {
+            ctx.say(format!("`{}` isn't a valid Discord id, skipping it.", snowflake)).await?;
+            continue
+        };
         let user: User = match userid.to_user(ctx).await {
             Ok(user) => user,
             Err(_) => {
@@ -2,8 +2,9 @@ use serenity::all::{GetMessages, ReactionType};
 use serenity::model::id::EmojiId;
 use crate::main_modules::helper;
 use super::{Context, Error, serenity, FromStr};
+use crate::commands::permissions_module::is_staff;
 
-#[poise::command(slash_command, prefix_command)]
+#[poise::command(slash_command, prefix_command, category = "Staff", check = "is_staff")]
 /// All this does is literally just react with all the emojis in the last message that had emojis.
 pub async fn gamenight_helper(
     ctx: Context<'_>,
@@ -27,7 +28,7 @@ pub async fn gamenight_helper(
         for emoji in unicode_emojis {
             if let Ok(reaction_type) = ReactionType::from_str(&emoji) {
                 if let Err(e) = message.react(ctx.http(), reaction_type).await {
-                    eprintln!("Failed to react with unicode emoji: {}", e);
+                    tracing::warn!(error = %e, "Couldn't react with unicode emoji");
                 }
             }
         }
@@ -40,7 +41,7 @@ pub async fn gamenight_helper(
             };
             
             if let Err(e) = message.react(ctx.http(), reaction_type).await {
-                eprintln!("Failed to react with custom emoji: {}", e);
+                tracing::warn!(error = %e, "Couldn't react with custom emoji");
             }
         }
     }
@@ -1,11 +1,11 @@
 use crate::Data;
-use crate::CONFIG;
 use super::{Context, Error};
 use poise::Modal;
+use crate::commands::permissions_module::is_admin;
 
 #[poise::command(slash_command, prefix_command, 
     subcommands("edit", "delete", "publish", "list", "clear_all"),
-    subcommand_required)]
+    subcommand_required, category = "Admin", check = "is_admin")]
 /// Command for managing policies
 pub async fn policy(_: Context<'_>) -> Result<(), Error> {
     Ok(())
@@ -19,26 +19,12 @@ struct EditModal {
     content: String,
 }
 
-async fn has_required_role(ctx: &poise::ApplicationContext<'_, Data, Error>, author: &User) -> bool {
-    let role_list = CONFIG.main.admin_role_ids;
-    let mut has_role = false;
-    for role in role_list {
-        if author.has_role(ctx.http(), GuildId::new(CONFIG.main.guild_id.parse().unwrap()), RoleId::new(role.try_into().unwrap())).await.unwrap() {has_role = true}
-    }
-
-    has_role
-}
-
 #[poise::command(slash_command)]
 /// Edit an existing policy
 pub async fn edit(
     ctx: poise::ApplicationContext<'_, Data, Error>,
     #[description = "Policy internal name"] internal_name: String,
 ) -> Result<(), Error> {
-    if !(has_required_role(&ctx, ctx.author()).await) {
-        ctx.say("You must be an administrator in Rise of Nations to use this command.").await?;
-        return Ok(())
-    }
     let policy_system = &ctx.data().policy_system;
 
     let data = EditModal::execute(ctx).await?;
@@ -57,10 +43,6 @@ pub async fn delete(
     ctx: poise::ApplicationContext<'_, Data, Error>,
     #[description = "Policy internal name"] internal_name: String,
 ) -> Result<(), Error> {
-    if !(has_required_role(&ctx, ctx.author()).await) {
-        ctx.say("You must be an administrator in Rise of Nations to use this command.").await?;
-        return Ok(())
-    }
     let policy_system = &ctx.data().policy_system;
     policy_system.remove(&internal_name).unwrap();
     
@@ -73,13 +55,9 @@ pub async fn delete(
 pub async fn publish(
     ctx: poise::ApplicationContext<'_, Data, Error>
 ) -> Result<(), Error> {
-    if !(has_required_role(&ctx, ctx.author()).await) {
-        ctx.say("You must be an administrator in Rise of Nations to use this command.").await?;
-        return Ok(())
-    }
     let policy_system = &ctx.data().policy_system;
     ctx.say("Policy cached changes applying.".to_string()).await?;
-    policy_system.update_policy(&ctx.serenity_context().clone()).await.unwrap();
+    policy_system.update_policy(&ctx.serenity_context().clone(), &ctx.data().config.get().modules.policy).await.unwrap();
     Ok(())
 }
 
@@ -88,10 +66,6 @@ pub async fn publish(
 pub async fn list(
     ctx: poise::ApplicationContext<'_, Data, Error>
 ) -> Result<(), Error> {
-    if !(has_required_role(&ctx, ctx.author()).await) {
-        ctx.say("You must be an administrator in Rise of Nations to use this command.").await?;
-        return Ok(())
-    }
     let policy_system = &ctx.data().policy_system;
     let policies = policy_system.list_policies_internal_names().unwrap();
     let mut policy_list_string = String::from("Current Policy Internal Names:");
@@ -106,19 +80,12 @@ pub async fn list(
 }
 
 use poise::serenity_prelude as serenity;
-use ::serenity::all::GuildId;
-use ::serenity::all::RoleId;
-use ::serenity::all::User;
 
 #[poise::command(slash_command)]
 /// Clear all policies
 pub async fn clear_all(
     ctx: poise::ApplicationContext<'_, Data, Error>
 ) -> Result<(), Error> {
-    if !(has_required_role(&ctx, ctx.author()).await) {
-        ctx.say("You must be an administrator in Rise of Nations to use this command.").await?;
-        return Ok(())
-    }
     let policy_system = &ctx.data().policy_system;
     let uuid_yes = ctx.id();
     let uuid_no = uuid::Uuid::new_v4();
@@ -0,0 +1,51 @@
+use serenity::all::Http;
+
+use super::{Context, Error, helper};
+use crate::main_modules::game_sanctions::unix_now;
+use crate::main_modules::mod_log::ModLog;
+use crate::main_modules::probation::{PROBATION_EXPIRY, Probation};
+use crate::main_modules::timer::Action;
+use crate::Data;
+
+pub mod probation;
+
+/// Flags every subject of a new infraction log who is still on probation.
+pub async fn flag_escalations(ctx: Context<'_>, log: &ModLog) -> Result<(), Error> {
+    for probation in ctx.data().probations.escalations(log, unix_now())? {
+        ctx.say(probation.escalation_notice()).await?;
+    }
+    Ok(())
+}
+
+/// Stores a probation and schedules its expiry notification.
+pub async fn track_probation(data: &Data, probation: Probation) -> Result<(), Error> {
+    let discord_id = probation.discord_id;
+    let end = probation.end;
+    let probation_id = data.probations.add(probation)?;
+    data.timer_system
+        .schedule(
+            discord_id.to_string(),
+            Action::Custom {
+                key: PROBATION_EXPIRY.to_string(),
+                payload: probation_id.to_string(),
+            },
+            end.saturating_sub(unix_now()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Tells moderators a probation is over, run by the probation's expiry timer.
+pub async fn notify_expiry(
+    http: &Http,
+    data: &Data,
+    discord_id: u64,
+    probation_id: u64,
+) -> Result<(), Error> {
+    let Some(probation) = data.probations.get(discord_id, probation_id)? else {
+        return Ok(());
+    };
+    let channel = data.config.get().modules.probation.notification_channel_id;
+    channel.say(http, probation.expiry_notice()).await?;
+    Ok(())
+}
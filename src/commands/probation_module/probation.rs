@@ -0,0 +1,73 @@
+use poise::CreateReply;
+use serenity::all::UserId;
+
+use super::{Context, Error, helper};
+use crate::main_modules::game_sanctions::unix_now;
+use crate::main_modules::probation::Probation;
+use crate::commands::permissions_module::is_moderator;
+
+fn probation_field(probation: &Probation, now: u64) -> (String, String) {
+    let status = if probation.is_active(now) {
+        "Active"
+    } else if now < probation.start {
+        "Upcoming"
+    } else {
+        "Ended"
+    };
+    let mut value = format!("**Reason:** {}", probation.reason);
+    value.push_str(&format!("\n**Moderator:** <@{}>", probation.moderator_id));
+    value.push_str(&format!(
+        "\n**Window:** <t:{}:D> - <t:{}:D> (ends <t:{}:R>)",
+        probation.start, probation.end, probation.end
+    ));
+
+    (format!("{} - {}", probation.probation_type, status), value)
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("status"),
+    subcommand_required,
+    category = "Moderator",
+    check = "is_moderator"
+)]
+/// Commands for tracking probations.
+pub async fn probation(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command)]
+/// Shows the probations a user is on or has been on.
+pub async fn status(
+    ctx: Context<'_>,
+    #[description = "The user to check."] user: UserId,
+) -> Result<(), Error> {
+    let now = unix_now();
+    let probations = ctx.data().probations.get_by_discord_id(user.get())?;
+    let mut embed = helper::new_embed_from_template(ctx.data())
+        .await
+        .title(format!("Probations for {}", user));
+
+    if probations.is_empty() {
+        embed = embed.description(format!("No probations are recorded for <@{}>.", user));
+    } else {
+        let active = probations
+            .iter()
+            .filter(|probation| probation.is_active(now))
+            .count();
+        embed = embed.description(format!(
+            "**{}** active out of **{}** probation(s)",
+            active,
+            probations.len()
+        ));
+        // Embeds are limited to 25 fields, show the most recent ones.
+        for probation in probations.iter().rev().take(25) {
+            let (name, value) = probation_field(probation, now);
+            embed = embed.field(name, value, false);
+        }
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use poise::CreateReply;
+use reqwest::StatusCode;
+use std::path::Path;
+use std::time::Duration;
+
+use super::{Context, Error, helper};
+use crate::commands::permissions_module::is_admin;
+use crate::main_modules::diagnostics::{self, Probe, format_bytes};
+
+fn describe_size(size: sled::Result<u64>) -> String {
+    match size {
+        Ok(size) => format_bytes(size),
+        Err(err) => format!("unknown, {}", err),
+    }
+}
+
+/// `healthy` being whether the status means the dependency is working, whatever was asked of it.
+fn describe_probe(probe: &Probe, healthy: impl Fn(StatusCode) -> bool) -> String {
+    match &probe.status {
+        Ok(status) if healthy(*status) => {
+            format!("✅ {} in {}ms", status, probe.latency.as_millis())
+        }
+        Ok(status) => format!("⚠️ {} in {}ms", status, probe.latency.as_millis()),
+        Err(err) => format!("❌ {}", err),
+    }
+}
+
+#[poise::command(slash_command, prefix_command, category = "Admin", check = "is_admin")]
+/// Show the bot's uptime, storage, queues and whether what it depends on is reachable.
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let data = ctx.data();
+    let reqwest_client = &data.reqwest_client;
+
+    // Looking the bot itself up in Bloxlink tests the key too, a 404 just means it isn't linked.
+    let bloxlink_request = helper::bloxlink_for(data, ctx.guild_id()).request(
+        reqwest_client,
+        "discord-to-roblox",
+        ctx.framework().bot_id,
+    )?;
+    let (roblox, bloxlink, ffmpeg, latency) = tokio::join!(
+        diagnostics::probe(reqwest_client.get("https://users.roblox.com/v1/users/1")),
+        diagnostics::probe(bloxlink_request),
+        diagnostics::ffmpeg_version(),
+        ctx.ping(),
+    );
+
+    let latency = if latency == Duration::ZERO {
+        "Not measured yet".to_string()
+    } else {
+        format!("{}ms", latency.as_millis())
+    };
+    let attachment_db_size = data.attachment_db.lock().unwrap().size_on_disk();
+    let databases = [
+        ("attachment_logs", attachment_db_size),
+        ("timer_system", data.timer_system.size_on_disk()),
+        ("policy_system", data.policy_system.size_on_disk()),
+        ("guide_system", data.guide_system.size_on_disk()),
+        ("mask_library", data.mask_library.size_on_disk()),
+    ]
+    .into_iter()
+    .map(|(name, size)| format!("`{}`: {}", name, describe_size(size)))
+    .collect::<Vec<_>>()
+    .join("\n");
+    let queued_logs = data.queued_logs.lock().unwrap().len();
+    let (running_jobs, waiting_jobs) = data.media_queue.counts();
+    let (active_timers, paused_timers) = data.timer_system.counts().await;
+    let tmp_usage = match diagnostics::dir_size(Path::new("./.tmp")) {
+        Ok(size) => format_bytes(size),
+        Err(err) => format!("unknown, {}", err),
+    };
+    let ffmpeg = match ffmpeg {
+        Ok(version) => format!("✅ {}", version),
+        Err(err) => format!("❌ {}", err),
+    };
+
+    let embed = helper::new_embed_from_template(data)
+        .await
+        .title(format!("Status of v{}", env!("CARGO_PKG_VERSION")))
+        .field(
+            "Uptime",
+            helper::format_duration(data.started_at.elapsed().as_secs()),
+            true,
+        )
+        .field("Gateway latency", latency, true)
+        .field("Databases", databases, false)
+        .field("Queued deletion logs", queued_logs.to_string(), true)
+        .field(
+            "Timers",
+            format!("{} active, {} paused", active_timers, paused_timers),
+            true,
+        )
+        .field(
+            "Media jobs",
+            format!("{} running, {} waiting", running_jobs, waiting_jobs),
+            true,
+        )
+        .field("`.tmp` usage", tmp_usage, true)
+        .field("ffmpeg", ffmpeg, false)
+        .field(
+            "Roblox",
+            describe_probe(&roblox, |status| status.is_success()),
+            true,
+        )
+        .field(
+            "Bloxlink",
+            describe_probe(&bloxlink, |status| {
+                status.is_success() || status == StatusCode::NOT_FOUND
+            }),
+            true,
+        );
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
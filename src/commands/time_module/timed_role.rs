@@ -2,10 +2,12 @@ use poise::CreateReply;
 use ::serenity::all::CreateMessage;
 
 use super::{Context, Error, helper, UserId, Mentionable, serenity, FromStr};
+use crate::main_modules::timer::Action;
+use crate::commands::permissions_module::is_moderator;
 
 #[poise::command(slash_command, prefix_command, 
     subcommands("add", "delete", "toggle_pause", "list"), 
-    subcommand_required)]
+    subcommand_required, category = "Moderator", check = "is_moderator")]
 pub async fn timed_role(
     _: Context<'_>,
 ) -> Result<(), Error> {
@@ -47,7 +49,7 @@ pub async fn add(
     let duration_secs = unix_timestamp - current_time;
 
     for user_id in users {
-        let timer_id = match ctx.data().timer_system.add_timer(user_id.to_string(), role.id.to_string(), duration_secs, false, None, delete_on_ban).await {
+        let timer_id = match ctx.data().timer_system.add_timer(user_id.to_string(), Action::RemoveRole { role_id: role.id.get() }, duration_secs, false, None, delete_on_ban).await {
             Ok(id) => id,
             Err(err) => {
                 ctx.say(format!("Failed to add timer for user {}: {}", user_id, err)).await?;
@@ -121,7 +123,7 @@ pub async fn list(
     for timer in timers {
         let embed= helper::new_embed_from_template(ctx.data()).await
             .title(format!("Timer ID - {}", timer.timer_id))
-            .field("Role ID", timer.role_id, true)
+            .field("Action", timer.action.to_string(), true)
             .field("End Timestamp", format!("<t:{}:D>", timer.end_timestamp), true)
             .field("Is Paused", format!("{}", timer.is_paused), true)
             .field("Paused Duration (only calculated afer unpause)", helper::format_duration(timer.paused_duration), true);
@@ -1,23 +1,145 @@
-use std::process::{Command, Stdio};
+use poise::{CreateReply, ReplyHandle};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 use super::{Context, Error};
+use crate::commands::permissions_module::is_admin;
+use crate::main_modules::config::UpdateConfig;
+use crate::main_modules::shutdown;
+use crate::main_modules::updater::{self, PendingRestart};
 
-#[poise::command(slash_command, prefix_command)]
+const OUTPUT_LINES: usize = 15;
+const EDIT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[poise::command(slash_command, prefix_command,
+    subcommands("run", "rollback"),
+    subcommand_required, category = "Admin", check = "is_admin")]
 /// Command for updating the bot.
-pub async fn update(
-    ctx: Context<'_>,
-) -> Result<(), Error> {
-    ctx.reply("Updating!").await?;
-    let child = Command::new("sh")
-        .arg("-c")
-        .arg("/root/rabby-stuff/ron-assista-bot/update.sh")
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?;
-
-    println!("Script started with PID: {}", child.id());
-    
-    // Immediately detach by not calling .wait()
+pub async fn update(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// The step list with each step's state, and the latest output under it.
+fn progress(steps: &[String], output: &VecDeque<String>) -> String {
+    let mut message = format!("**Updating from v{}**\n{}", env!("CARGO_PKG_VERSION"), steps.join("\n"));
+    if !output.is_empty() {
+        let mut tail = output.iter().cloned().collect::<Vec<_>>().join("\n").replace("```", "'''");
+        while tail.chars().count() > 1500 {
+            tail = tail.split_once('\n').map_or(String::new(), |(_, rest)| rest.to_string());
+        }
+        message.push_str(&format!("\n```\n{}\n```", tail));
+    }
+    message
+}
+
+async fn run_steps(ctx: Context<'_>, reply: &ReplyHandle<'_>, update: &UpdateConfig) -> Result<(), Error> {
+    let mut steps: Vec<String> = update.steps.iter().map(|step| format!("⬜ {}", step.name)).collect();
+    let mut output = VecDeque::new();
+
+    for (index, step) in update.steps.iter().enumerate() {
+        steps[index] = format!("⏳ {}", step.name);
+        output.clear();
+        reply.edit(ctx, CreateReply::default().content(progress(&steps, &output))).await?;
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let step_task = {
+            let step = step.clone();
+            let working_directory = update.working_directory.clone();
+            tokio::spawn(async move { updater::run_step(&step, &working_directory, sender).await })
+        };
+        let mut last_edit = Instant::now();
+        while let Some(line) = receiver.recv().await {
+            output.push_back(line);
+            if output.len() > OUTPUT_LINES {
+                output.pop_front();
+            }
+            if last_edit.elapsed() >= EDIT_INTERVAL {
+                reply.edit(ctx, CreateReply::default().content(progress(&steps, &output))).await?;
+                last_edit = Instant::now();
+            }
+        }
+
+        let result = step_task.await?;
+        steps[index] = match &result {
+            Ok(()) => format!("✅ {}", step.name),
+            Err(err) => format!("❌ {}: {}", step.name, err),
+        };
+        reply.edit(ctx, CreateReply::default().content(progress(&steps, &output))).await?;
+        result?;
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Records what to report once the bot is back, shuts down and restarts into `binary`, only
+/// returning if the restart failed.
+async fn restart(ctx: Context<'_>, binary: &Path, rollback: bool) -> Result<(), Error> {
+    ctx.data().updater.set_pending(&PendingRestart {
+        from_version: env!("CARGO_PKG_VERSION").to_string(),
+        channel_id: ctx.channel_id().get(),
+        requested_by: ctx.author().id.get(),
+        rollback,
+    })?;
+    ctx.say("Restarting...").await?;
+    let reason = if rollback { "rolling back" } else { "restarting for an update" };
+    if !shutdown::shut_down(ctx.serenity_context(), ctx.data(), reason).await {
+        ctx.data().updater.take_pending()?;
+        return Err("The bot is already shutting down.".into());
+    }
+    let err = updater::restart(binary);
+    ctx.data().updater.take_pending()?;
+    let err = format!("Couldn't restart the bot: {}", err);
+    shutdown::resume(ctx.http(), ctx.data(), &err).await;
+    Err(err.into())
+}
+
+#[poise::command(slash_command, prefix_command)]
+/// Fetch, build and restart into the latest version of the bot.
+pub async fn run(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(update) = ctx.data().config.get().modules.update.clone() else {
+        ctx.say("Updating isn't set up, add a `modules.update` section to the config.").await?;
+        return Ok(())
+    };
+    let updater = &ctx.data().updater;
+    if !updater.try_start() {
+        ctx.say("An update is already running.").await?;
+        return Ok(())
+    }
+
+    let result = async {
+        let reply = ctx.say("Starting update...").await?;
+        run_steps(ctx, &reply, &update).await?;
+
+        let binary = std::env::current_exe()?;
+        updater::swap_binary(&update.working_directory.join(&update.built_binary), &binary)?;
+        restart(ctx, &binary, false).await
+    }
+    .await;
+    updater.finish();
+    result
+}
+
+#[poise::command(slash_command, prefix_command)]
+/// Go back to the version running before the last update.
+pub async fn rollback(ctx: Context<'_>) -> Result<(), Error> {
+    let updater = &ctx.data().updater;
+    if !updater.try_start() {
+        ctx.say("An update is already running.").await?;
+        return Ok(())
+    }
+
+    let result = async {
+        let binary = std::env::current_exe()?;
+        updater::rollback_binary(&binary)?;
+        match updater.previous_version()? {
+            Some(version) => ctx.say(format!("Rolling back to v{}.", version)).await?,
+            None => ctx.say("Rolling back to the previous version.").await?,
+        };
+        restart(ctx, &binary, true).await
+    }
+    .await;
+    updater.finish();
+    result
+}
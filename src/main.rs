@@ -1,7 +1,7 @@
 use ::serenity::all::{
-    ChannelId, Color, CreateAttachment, CreateMessage, GuildId, MessageId, ReactionType, RoleId,
+    ChannelId, CreateAttachment, CreateMessage, EditMember, GuildId, MessageId,
+    ReactionType, RoleId,
 };
-use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
 use regex::Regex;
 use reqwest::Client;
@@ -10,40 +10,57 @@ use serenity::{ActivityData, OnlineStatus};
 use serenity::{UserId, prelude::*};
 use std::{
     env,
-    io::Write,
     str::FromStr,
     sync::{Arc, Mutex},
     vec,
 };
+use tracing::{debug, error, info, warn};
 
 mod main_modules;
 use main_modules::{
+    command_audit::{CommandAuditDB, CommandOutcome},
+    error::{self, BotError},
+    config::SharedConfig,
     deleted_attachments::{self, AttachmentStore, AttachmentStoreDB},
+    game_sanctions::GameSanctionDB,
+    guild_settings::{GuildModule, GuildSettingsDB},
     guide_updater::GuideSystem,
     helper, log_interactions,
+    logging_database::LoggingDB,
+    metrics,
+    open_cloud::OpenCloudClient,
+    probation::{PROBATION_EXPIRY, ProbationDB},
+    shutdown::{self, Shutdown},
+    telemetry::{self, Traced},
     media::{
-        QualityPreset, apply_mask, image_to_png_converter, png_to_gif_converter, video_convert,
-        video_format_changer, video_to_gif_converter,
+        QualityPreset, apply_mask, fit_output, image_to_png_converter, png_to_gif_converter,
+        video_convert, video_format_changer, video_to_gif_converter,
     },
+    mask_library::MaskLibrary,
+    media_format::{MediaFormat, MediaKind},
+    media_queue::{self, MediaQueue},
     policy_updater::PolicySystem,
-    timer::TimerSystem,
+    timer::{Action, TimerSystem},
+    updater::UpdateSystem,
 };
 mod commands;
 use commands::{
+    audit_module::{self, audit},
+    config_module::{config, settings},
+    game_module::{self, game_ban, game_infractions, game_warn},
     guide_module::guide,
     info_module::{discord_info, get_info},
+    log_db::{backfill, infractions},
     log_module::{discord_log, false_infraction, probation_log, roblox_log, role_log},
     media_module::{convert_gif, convert_video, media_effects},
     playground::{auror, gamenight_helper},
     policy_module::policy,
+    permissions_module::permissions,
+    probation_module::{self, probation},
     time_module::timed_role,
-    update,
+    status, update,
 };
 
-static_toml::static_toml! {
-    static CONFIG = include_toml!("config.toml");
-}
-
 #[derive(Clone)]
 pub struct Data {
     pub rbx_client: Arc<roboat::Client>,
@@ -54,7 +71,18 @@ pub struct Data {
     pub queued_logs: Arc<Mutex<Vec<LoggingQueue>>>,
     pub policy_system: PolicySystem,
     pub guide_system: GuideSystem,
-    pub bot_color: Color,
+    pub logging_db: LoggingDB,
+    pub game_sanctions: GameSanctionDB,
+    pub probations: ProbationDB,
+    pub open_cloud: OpenCloudClient,
+    pub config: SharedConfig,
+    pub guild_settings: GuildSettingsDB,
+    pub command_audit: CommandAuditDB,
+    pub updater: UpdateSystem,
+    pub shutdown: Shutdown,
+    pub media_queue: MediaQueue,
+    pub mask_library: MaskLibrary,
+    pub started_at: Instant,
     pub bot_avatar: String,
 }
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -83,29 +111,19 @@ async fn do_image_logging(
         let reqwest_client = framework_data.reqwest_client.clone();
         let data = framework_data.clone();
         let ctx = ctx.clone();
-        tokio::spawn(async move {
-            if guild_id.is_some() && guild_id.unwrap().to_string() == *CONFIG.main.guild_id {
-                let log_channel_id = ChannelId::new(
-                    CONFIG
-                        .modules
-                        .logging
-                        .attachment_logging_channel_id
-                        .parse::<u64>()
-                        .unwrap(),
-                );
-                let output_filename = format!("./.tmp/{}", attachment.filename);
-                let response = reqwest_client.get(&attachment.url).send().await.unwrap();
-                let bytes = response.bytes().await.unwrap();
-                let mut file =
-                    std::fs::File::create(&output_filename).expect("Failed to create input file");
-                file.write_all(&bytes).expect("Failed to write input file");
-                drop(file);
-                let attachment = CreateAttachment::file(
-                    &tokio::fs::File::open(&output_filename).await.unwrap(),
-                    &attachment.filename,
-                )
-                .await
-                .unwrap();
+        framework_data.shutdown.spawn(async move {
+            let log_channel_id = guild_id.and_then(|guild_id| {
+                data.guild_settings
+                    .get(guild_id, &data.config.get())
+                    .ok()?
+                    .log_channel(GuildModule::AttachmentLogging)
+            });
+            let Some(log_channel_id) = log_channel_id else {
+                return;
+            };
+            let logged = async {
+                let bytes = download_attachment(&reqwest_client, &attachment.url).await?;
+                let file = CreateAttachment::bytes(bytes, attachment.filename.clone());
                 let embed = helper::new_embed_from_template(&data)
                     .await
                     .title("Attachment Log")
@@ -125,14 +143,13 @@ async fn do_image_logging(
                         false,
                     );
                 log_channel_id
-                    .send_message(
-                        &ctx.http,
-                        CreateMessage::new().add_embed(embed).add_file(attachment),
-                    )
-                    .await
-                    .unwrap();
-                std::fs::remove_file(output_filename).unwrap();
+                    .send_message(&ctx.http, CreateMessage::new().add_embed(embed).add_file(file))
+                    .await?;
+                Ok::<_, BotError>(())
             };
+            if let Err(err) = logged.await {
+                error::report(&format!("logging deleted attachment {}", attachment.url), &err);
+            }
         });
     }
 
@@ -144,32 +161,41 @@ async fn do_image_logging(
         .unwrap();
 }
 
+/// Downloads an attachment from Discord's CDN.
+async fn download_attachment(reqwest_client: &Client, url: &str) -> Result<Vec<u8>, BotError> {
+    let cdn_error = |err: reqwest::Error| BotError::Api {
+        service: "Discord CDN",
+        details: err.to_string(),
+    };
+    let response = reqwest_client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(cdn_error)?;
+    Ok(response.bytes().await.map_err(cdn_error)?.into())
+}
+
 #[derive(Debug, Clone)]
 pub struct LoggingQueue {
     pub message_id: MessageId,
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
 }
 
 impl LoggingQueue {
-    pub async fn do_image_logging(
-        &self,
-        ctx: &serenity::Context,
-        framework_data: Data,
-        deleting_message: serenity::all::MessageId,
-        guild_id: Option<GuildId>,
-        channel_id: ChannelId,
-    ) {
-        do_image_logging(ctx, framework_data, deleting_message, guild_id, channel_id).await;
+    pub async fn do_image_logging(&self, ctx: &serenity::Context, framework_data: Data) {
+        do_image_logging(
+            ctx,
+            framework_data,
+            self.message_id,
+            self.guild_id,
+            self.channel_id,
+        )
+        .await;
     }
 }
 
-static DODGED_FILE_FORMATS: Lazy<Vec<String>> = Lazy::new(|| {
-    vec![
-        "video/mp4".to_string(),
-        "video/webm".to_string(),
-        "video/quicktime".to_string(),
-    ]
-});
-
 struct ReactionInfo {
     channel_id: ChannelId,
     message_id: MessageId,
@@ -184,15 +210,6 @@ async fn reaction_logging(
     event_type: &str,
     reaction_info: ReactionInfo,
 ) {
-    let log_channel_id = ChannelId::new(
-        CONFIG
-            .modules
-            .logging
-            .reaction_logging_channel_id
-            .parse()
-            .unwrap(),
-    );
-    let mut embed_builder = helper::new_embed_from_template(&framework_data).await;
     let (channel_id, message_id, user_id, guild_id, emoji) = (
         reaction_info.channel_id,
         reaction_info.message_id,
@@ -200,6 +217,20 @@ async fn reaction_logging(
         reaction_info.guild_id,
         reaction_info.emoji,
     );
+    let config = framework_data.config.get();
+    let log_channel_id = match framework_data
+        .guild_settings
+        .get_or_main(guild_id, &config)
+        .map(|settings| settings.log_channel(GuildModule::ReactionLogging))
+    {
+        Ok(Some(log_channel_id)) => log_channel_id,
+        Ok(None) => return,
+        Err(err) => {
+            error!(error = %err, "Couldn't get guild settings for the reaction log");
+            return;
+        }
+    };
+    let mut embed_builder = helper::new_embed_from_template(&framework_data).await;
 
     let emoji_url = match emoji {
         Some(ReactionType::Custom { animated, id, .. }) => {
@@ -244,7 +275,136 @@ async fn reaction_logging(
         .send_message(&ctx.http, CreateMessage::new().add_embed(embed_builder))
         .await
     {
-        eprintln!("Error sending log message: {:?}", why);
+        error!(error = %why, "Couldn't send the reaction log");
+    }
+}
+
+/// Carries out what an expired timer was set up to do.
+async fn run_timer_action(ctx: &serenity::Context, data: &Data, user_id: String, action: Action) {
+    // Discord actions are keyed by the user's id, game bans by `roblox-<id>` instead, so a bad
+    // id only fails the actions that need one.
+    let discord_id = UserId::from_str(&user_id)
+        .map_err(|_| Error::from(format!("`{}` isn't a Discord user ID", user_id)));
+    if let Err(err) = perform_timer_action(ctx, data, discord_id, action).await {
+        error!(user_id, error = %err, "Couldn't run timer action");
+    }
+}
+
+async fn perform_timer_action(
+    ctx: &serenity::Context,
+    data: &Data,
+    discord_id: Result<UserId, Error>,
+    action: Action,
+) -> Result<(), Error> {
+    match action {
+        Action::RemoveRole { role_id } | Action::AddRole { role_id } => {
+            let user_id = discord_id?;
+            let role_id = RoleId::new(role_id);
+            let adding = matches!(action, Action::AddRole { .. });
+
+            for guild_id in ctx.cache.guilds() {
+                if let Ok(guild) = guild_id.to_partial_guild(ctx).await
+                    && let Ok(member) = guild.member(&ctx.http, user_id).await
+                {
+                    let result = if adding {
+                        member.add_role(&ctx.http, role_id).await
+                    } else {
+                        member.remove_role(&ctx.http, role_id).await
+                    };
+                    if let Err(err) = result {
+                        warn!(%guild_id, error = %err, "Couldn't update the user's role");
+                    }
+                }
+            }
+            Ok(())
+        }
+        Action::LiftGameBan {
+            roblox_id,
+            sanction_id,
+            log_channel_id,
+        } => {
+            let bot_id = ctx.cache.current_user().id;
+            game_module::lift_game_ban(
+                &ctx.http,
+                data,
+                bot_id,
+                roblox_id,
+                sanction_id,
+                ChannelId::new(log_channel_id),
+            )
+            .await
+        }
+        Action::Unban { guild_id } => GuildId::new(guild_id)
+            .unban(&ctx.http, discord_id?)
+            .await
+            .map_err(Error::from),
+        Action::Unmute { guild_id } => GuildId::new(guild_id)
+            .edit_member(
+                &ctx.http,
+                discord_id?,
+                EditMember::new().enable_communication(),
+            )
+            .await
+            .map(|_| ())
+            .map_err(Error::from),
+        Action::PostMessage {
+            channel_id,
+            content,
+        } => ChannelId::new(channel_id)
+            .say(&ctx.http, content)
+            .await
+            .map(|_| ())
+            .map_err(Error::from),
+        // Custom actions are run by their registered callbacks instead.
+        Action::Custom { .. } => Ok(()),
+    }
+}
+
+/// Turns commands away while the bot is shutting down.
+async fn command_check(ctx: Context<'_>) -> Result<bool, Error> {
+    if !ctx.data().shutdown.is_shutting_down() {
+        return Ok(true);
+    }
+    ctx.send(
+        poise::CreateReply::default()
+            .content("The bot is restarting, try again in a moment.")
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(false)
+}
+
+/// Logs a command's error in full and shows the user what went wrong, with an id to quote so the
+/// log can be found.
+async fn report_command_error(ctx: Context<'_>, err: &Error) {
+    let id = error::report(&format!("/{}", ctx.command().qualified_name), err.as_ref());
+    audit_module::record(ctx, CommandOutcome::Failed(format!("{} (error {})", err, id))).await;
+
+    let embed = error::error_embed(helper::new_embed_from_template(ctx.data()).await, err, &id);
+    if let Err(err) = ctx.send(poise::CreateReply::default().embed(embed)).await {
+        warn!(error = %err, "Couldn't send the error embed");
+    }
+}
+
+/// Reports commands that failed and audits every other invocation that didn't finish, handling
+/// anything else as usual.
+async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
+    match &error {
+        poise::FrameworkError::Command { error, ctx, .. } => {
+            report_command_error(*ctx, error).await;
+            return;
+        }
+        poise::FrameworkError::CommandCheckFailed { ctx, .. } => {
+            audit_module::record(*ctx, CommandOutcome::Denied).await
+        }
+        _ => {
+            if let Some(ctx) = error.ctx() {
+                audit_module::record(ctx, CommandOutcome::Failed(error.to_string())).await
+            }
+        }
+    }
+    if let Err(err) = poise::builtins::on_error(error).await {
+        error!(error = %err, "Error while handling error");
     }
 }
 
@@ -256,40 +416,58 @@ async fn event_handler(
 ) -> Result<(), Error> {
     match event {
         serenity::FullEvent::Ready { data_about_bot, .. } => {
-            println!("{} is connected!", data_about_bot.user.name);
+            info!(user = data_about_bot.user.name, "Connected to Discord");
+            match data.updater.take_pending() {
+                Ok(Some(pending)) => {
+                    let report = pending.report(env!("CARGO_PKG_VERSION"));
+                    if let Err(err) = ChannelId::new(pending.channel_id).say(&ctx.http, report).await {
+                        warn!(error = %err, "Couldn't report the update");
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => error!(error = %err, "Couldn't read the pending update"),
+            }
+
+            let expiry_ctx = ctx.clone();
+            let expiry_data = data.clone();
+            data.timer_system
+                .register_custom(PROBATION_EXPIRY, move |user_id: String, payload: String| {
+                    let ctx = expiry_ctx.clone();
+                    let data = expiry_data.clone();
+                    async move {
+                        let (Ok(discord_id), Ok(probation_id)) = (user_id.parse(), payload.parse())
+                        else {
+                            warn!(user_id, payload, "Invalid probation expiry timer");
+                            return;
+                        };
+                        if let Err(err) =
+                            probation_module::notify_expiry(&ctx.http, &data, discord_id, probation_id)
+                                .await
+                        {
+                            error!(user_id, error = %err, "Couldn't post probation expiry");
+                        }
+                    }
+                })
+                .await;
             let ctx = ctx.clone();
+            let timer_data = data.clone();
             data.timer_system
-                .set_event_handler(move |user_id: String, role_id: String| {
+                .set_event_handler(move |user_id: String, action: Action| {
                     let ctx = ctx.clone();
-                    Box::pin(async move {
-                        let user_id = UserId::from_str(user_id.as_str()).expect("Invalid user ID");
-                        let role_id = RoleId::from_str(role_id.as_str()).expect("Invalid role ID");
-
-                        let guilds = ctx.cache.guilds();
-
-                        for guild_id in guilds {
-                            if let Ok(guild) = guild_id.to_partial_guild(&ctx).await {
-                                if let Ok(member) = guild.member(&ctx.http, user_id).await {
-                                    match member.remove_role(&ctx.http, role_id).await {
-                                        Ok(()) => (),
-                                        Err(err) => println!(
-                                            "Couldn't remove role from user in {}, {}",
-                                            guild_id, err
-                                        ),
-                                    };
-                                }
-                            }
-                        }
-                    })
+                    let data = timer_data.clone();
+                    Box::pin(async move { run_timer_action(&ctx, &data, user_id, action).await })
                 })
                 .await;
             data.timer_system.start_timer_thread();
         }
 
         serenity::FullEvent::Message { new_message } => {
-            if new_message.channel_id.to_string() == *CONFIG.modules.logging.cdn_channel_id
-                || new_message.channel_id.to_string()
-                    == *CONFIG.modules.logging.attachment_logging_channel_id
+            let config = data.config.get();
+            let settings = data
+                .guild_settings
+                .get_or_main(new_message.guild_id, &config)?;
+            if new_message.channel_id == config.modules.logging.cdn_channel_id
+                || Some(new_message.channel_id) == settings.attachment_logging_channel_id
             {
                 return Ok(());
             }
@@ -299,84 +477,76 @@ async fn event_handler(
 
             let message = CreateMessage::new();
             let mut files = vec![];
+            let mut formats = vec![];
             for attachment in &new_message.attachments {
-                let output_filename = format!("./.tmp/{}", attachment.filename);
-                let response = data
-                    .reqwest_client
-                    .get(&attachment.url)
-                    .send()
-                    .await
-                    .unwrap();
-                let bytes = response.bytes().await.unwrap();
-                let mut file =
-                    std::fs::File::create(&output_filename).expect("Failed to create input file");
-                file.write_all(&bytes).expect("Failed to write input file");
-                drop(file);
-                files.push(
-                    CreateAttachment::file(
-                        &tokio::fs::File::open(&output_filename).await.unwrap(),
-                        &attachment.filename,
-                    )
-                    .await
-                    .unwrap(),
-                );
-                std::fs::remove_file(&output_filename).unwrap();
+                match download_attachment(&data.reqwest_client, &attachment.url).await {
+                    Ok(bytes) => {
+                        formats.push(MediaFormat::from_magic(&bytes));
+                        files.push(CreateAttachment::bytes(bytes, attachment.filename.clone()));
+                    }
+                    Err(err) => {
+                        error::report(&format!("caching attachment {}", attachment.url), &err);
+                        formats.push(None);
+                    }
+                }
             }
-            let log_channel_id = ChannelId::new(
-                CONFIG
+            let store = if files.is_empty() {
+                None
+            } else {
+                match config
                     .modules
                     .logging
                     .cdn_channel_id
-                    .parse::<u64>()
-                    .unwrap(),
-            );
-            let final_msg = log_channel_id
-                .send_message(&ctx.http, message.add_files(files))
-                .await
-                .unwrap();
-            let user_id = new_message.author.id;
-            let attachments = final_msg.attachments;
-            let created_at = new_message.id.created_at();
-            let message_id = new_message.id;
-            let store = AttachmentStore {
-                message_id,
-                attachments,
-                created_at,
-                user_id,
+                    .send_message(&ctx.http, message.add_files(files))
+                    .await
+                {
+                    Ok(final_msg) => Some(AttachmentStore {
+                        message_id: new_message.id,
+                        attachments: final_msg.attachments,
+                        created_at: new_message.id.created_at(),
+                        user_id: new_message.author.id,
+                    }),
+                    Err(err) => {
+                        error!(message_id = %new_message.id, error = %err, "Couldn't cache attachments in the CDN channel");
+                        None
+                    }
+                }
             };
 
-            for attachment in &new_message.attachments {
-                let Some(content_type) = &attachment.content_type else {
+            for (attachment, format) in new_message.attachments.iter().zip(formats) {
+                let Some(format) = format else {
                     continue;
                 };
-                if !content_type.contains("video/") || DODGED_FILE_FORMATS.contains(content_type) {
+                if data.shutdown.is_shutting_down()
+                    || !settings.is_enabled(GuildModule::VideoConversion)
+                    || format.kind() != MediaKind::Video
+                    || format.plays_in_discord()
+                {
                     continue;
                 }
 
                 let new_message = new_message.clone();
                 let attachment = attachment.clone();
                 let ctx = ctx.clone();
-                let reqwest_client = data.reqwest_client.clone();
-                tokio::spawn(async move {
-                    video_convert(new_message, ctx, reqwest_client, attachment).await;
+                let conversion_data = data.clone();
+                data.shutdown.spawn(async move {
+                    let author = new_message.author.id;
+                    video_convert(new_message, ctx, conversion_data, attachment, author).await;
                 });
             }
 
-            data.attachment_db.lock().unwrap().save(&store).unwrap();
+            if let Some(store) = store
+                && let Err(err) = data.attachment_db.lock().unwrap().save(&store)
+            {
+                error!(message_id = %new_message.id, error = %err, "Couldn't save cached attachments");
+            }
 
             let message_id = new_message.id;
             let mut i = 0;
             while i < data.queued_logs.lock().unwrap().len() {
                 let log = data.queued_logs.lock().unwrap().get(i).unwrap().clone();
                 if log.message_id == message_id {
-                    log.do_image_logging(
-                        ctx,
-                        framework.user_data.clone(),
-                        message_id,
-                        new_message.guild_id,
-                        new_message.channel_id,
-                    )
-                    .await;
+                    log.do_image_logging(ctx, framework.user_data.clone()).await;
                     data.queued_logs.lock().unwrap().remove(i);
                 }
                 i += 1
@@ -388,7 +558,7 @@ async fn event_handler(
             deleted_message_id,
             guild_id,
         } => {
-            if channel_id.to_string() == *CONFIG.modules.logging.cdn_channel_id {
+            if *channel_id == data.config.get().modules.logging.cdn_channel_id {
                 return Ok(());
             }
             match data
@@ -401,6 +571,8 @@ async fn event_handler(
                 None => {
                     data.queued_logs.lock().unwrap().push(LoggingQueue {
                         message_id: *deleted_message_id,
+                        guild_id: *guild_id,
+                        channel_id: *channel_id,
                     });
                     return Ok(());
                 }
@@ -418,17 +590,17 @@ async fn event_handler(
         serenity::FullEvent::GuildMemberAddition { new_member } => {
             let user_id = new_member.user.id.to_string();
             let timers = data.timer_system.list_user_timers(&user_id).await;
-            for timer in timers {
-                if let Ok(role_id) = data
+            for timer in timers
+                .into_iter()
+                .filter(|timer| timer.is_paused && timer.action.needs_member())
+            {
+                if let Ok(Some(Action::RemoveRole { role_id })) = data
                     .timer_system
                     .toggle_timer(&user_id, &timer.timer_id)
                     .await
                 {
                     new_member
-                        .add_role(
-                            &ctx.http,
-                            RoleId::new(role_id.unwrap().parse::<u64>().unwrap()),
-                        )
+                        .add_role(&ctx.http, RoleId::new(role_id))
                         .await
                         .unwrap();
                 };
@@ -438,7 +610,10 @@ async fn event_handler(
         serenity::FullEvent::GuildMemberRemoval { user, .. } => {
             let user_id = user.id.to_string();
             let timers = data.timer_system.list_user_timers(&user_id).await;
-            for timer in timers {
+            for timer in timers
+                .into_iter()
+                .filter(|timer| !timer.is_paused && timer.action.needs_member())
+            {
                 data.timer_system
                     .toggle_timer(&user_id, &timer.timer_id)
                     .await?;
@@ -498,6 +673,12 @@ async fn event_handler(
             channel_id,
             removed_from_message_id,
         } => {
+            let guild_id = channel_id
+                .to_channel(ctx)
+                .await
+                .ok()
+                .and_then(|channel| channel.guild())
+                .map(|channel| channel.guild_id);
             reaction_logging(
                 ctx,
                 framework.user_data.clone(),
@@ -506,7 +687,7 @@ async fn event_handler(
                     channel_id: *channel_id,
                     message_id: *removed_from_message_id,
                     user_id: None,
-                    guild_id: None,
+                    guild_id,
                     emoji: None,
                 },
             )
@@ -546,7 +727,7 @@ async fn event_handler(
                             log_interactions::handle_create_log_button(ctx, component_interaction)
                                 .await
                     {
-                        eprintln!("Error handling create log button: {:?}", err);
+                        error!(error = %err, "Couldn't handle the create log button");
                     }
                     if component_interaction
                         .data
@@ -556,7 +737,20 @@ async fn event_handler(
                             log_interactions::handle_select_log_type(ctx, component_interaction)
                                 .await
                     {
-                        eprintln!("Error handling create log button: {:?}", err);
+                        error!(error = %err, "Couldn't handle the log type selection");
+                    }
+                    if component_interaction
+                        .data
+                        .custom_id
+                        .starts_with("cancel_media_job:")
+                        && let Err(err) = media_queue::handle_cancel_button(
+                            ctx,
+                            component_interaction,
+                            &data.media_queue,
+                        )
+                        .await
+                    {
+                        error!(error = %err, "Couldn't handle the media job cancel button");
                     }
                 }
                 serenity::Interaction::Modal(modal_interaction) => {
@@ -566,9 +760,10 @@ async fn event_handler(
                         .custom_id
                         .starts_with("roblox_log_modal:")
                         && let Err(err) =
-                            log_interactions::handle_log_modal_submit(ctx, modal_interaction).await
+                            log_interactions::handle_log_modal_submit(ctx, modal_interaction, data)
+                                .await
                     {
-                        eprintln!("Error handling log modal submit: {:?}", err);
+                        error!(error = %err, "Couldn't handle the log modal submission");
                     }
                 }
                 _ => {}
@@ -582,7 +777,7 @@ async fn event_handler(
 
 use std::fs;
 use std::path::Path;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::time::sleep;
 
 async fn remove_old_files() {
@@ -601,19 +796,19 @@ async fn remove_old_files() {
                     if let Ok(modified_time) = metadata.modified() {
                         if let Ok(age) = now.duration_since(modified_time) {
                             if age >= threshold {
-                                println!("Deleting file: {:?}", path);
+                                debug!(?path, "Deleting old temporary file");
                                 if let Err(err) = fs::remove_file(&path) {
-                                    eprintln!("Failed to delete {:?}: {}", path, err);
+                                    warn!(?path, error = %err, "Couldn't delete temporary file");
                                 }
                             }
                         }
                     }
                 }
-                Err(err) => eprintln!("Failed to get metadata for {:?}: {}", path, err),
+                Err(err) => warn!(?path, error = %err, "Couldn't get temporary file metadata"),
             }
         }
     } else {
-        eprintln!("Failed to read .tmp directory");
+        warn!("Couldn't read the .tmp directory");
     }
 }
 
@@ -626,10 +821,27 @@ async fn periodic_cleanup() {
 
 #[tokio::main]
 async fn main() {
+    let started_at = Instant::now();
+    let config = match SharedConfig::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Couldn't load config: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let _log_guard = match telemetry::init(&config.get().tracing) {
+        Ok(guard) => guard,
+        Err(err) => {
+            eprintln!("Couldn't start logging: {}", err);
+            std::process::exit(1);
+        }
+    };
     deleted_attachments::start_attachment_db();
     std::fs::create_dir_all("./.tmp").unwrap();
     tokio::spawn(periodic_cleanup());
-    let discord_api_key = &CONFIG.main.discord_api_key;
+    #[cfg(unix)]
+    tokio::spawn(main_modules::config::reload_on_hangup(config.clone()));
+    let discord_api_key = config.get().main.discord_api_key.clone();
     let intents = GatewayIntents::GUILDS
         | GatewayIntents::GUILD_PRESENCES
         | GatewayIntents::GUILD_MEMBERS
@@ -647,6 +859,7 @@ async fn main() {
         role_log::rolelog(),
         get_info::getinfo(),
         update::update(),
+        status::status(),
         discord_info::discordinfo(),
         timed_role::timed_role(),
         false_infraction::false_infraction(),
@@ -657,24 +870,30 @@ async fn main() {
         auror::id_to_mention(),
         gamenight_helper::gamenight_helper(),
         guide::guide(),
+        infractions::inf(),
+        backfill::backfill(),
+        game_ban::game_ban(),
+        game_warn::game_warn(),
+        game_infractions::game_infractions(),
+        probation::probation(),
+        config::config(),
+        settings::settings(),
+        permissions::permissions(),
+        audit::audit(),
     ];
 
     let empty_commands: Vec<poise::Command<Data, Error>> = vec![];
 
-    let color_string = CONFIG.main.color;
-    let colors: Vec<u8> = color_string
-        .split(',')
-        .map(|s| u8::from_str(s.trim()).expect("Failed to parse color component"))
-        .collect();
-
-    let (r, g, b) = (colors[0], colors[1], colors[2]);
-
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands,
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
             },
+            pre_command: |ctx| Box::pin(audit_module::pre_command(ctx)),
+            post_command: |ctx| Box::pin(audit_module::post_command(ctx)),
+            on_error: |error| Box::pin(on_error(error)),
+            command_check: Some(|ctx| Box::pin(command_check(ctx))),
             ..Default::default()
         })
         .setup(move |ctx, ready, framework| {
@@ -685,13 +904,26 @@ async fn main() {
             ctx.set_presence(Some(activity), status);
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &empty_commands).await?;
-                poise::builtins::register_in_guild(
-                    ctx,
-                    &framework.options().commands,
-                    GuildId::new(u64::from_str(CONFIG.main.guild_id).unwrap()),
-                )
-                .await?;
-                Ok(Data {
+                let guild_settings = GuildSettingsDB::init("./dbs/guild_settings").unwrap();
+                let mut guilds = guild_settings.guilds()?;
+                guilds.retain(|guild_id| *guild_id != config.get().main.guild_id);
+                guilds.insert(0, config.get().main.guild_id);
+                for guild_id in guilds {
+                    poise::builtins::register_in_guild(
+                        ctx,
+                        &framework.options().commands,
+                        guild_id,
+                    )
+                    .await?;
+                }
+                let media = config.get().modules.media.clone();
+                let media_queue =
+                    MediaQueue::new(media.max_concurrent_jobs, media.max_jobs_per_user);
+                let mask_library = MaskLibrary::init("./dbs/mask_library").unwrap();
+                if let Err(err) = mask_library.add_defaults(Path::new("./.default_masks")) {
+                    warn!(error = %err, "Couldn't add the default speech bubble masks");
+                }
+                let data = Data {
                     rbx_client: Arc::new(ClientBuilder::new().build()),
                     reqwest_client: Arc::new(Client::new()),
                     number_regex: Arc::new(Regex::new(r"[^\d\s]").expect("Failed to create regex")),
@@ -700,18 +932,42 @@ async fn main() {
                     queued_logs: Arc::new(Mutex::new(vec![])),
                     policy_system: PolicySystem::init("./dbs/policy_system").unwrap(),
                     guide_system: GuideSystem::init("./dbs/guide_system").unwrap(),
-                    bot_color: Color::from_rgb(r, g, b),
+                    logging_db: LoggingDB::init("./dbs/logging_db").unwrap(),
+                    game_sanctions: GameSanctionDB::init("./dbs/game_sanctions").unwrap(),
+                    probations: ProbationDB::init("./dbs/probations").unwrap(),
+                    open_cloud: OpenCloudClient::from_config(
+                        Client::new(),
+                        &config.get().modules.game,
+                    ),
+                    config,
+                    guild_settings,
+                    command_audit: CommandAuditDB::init("./dbs/command_audit").unwrap(),
+                    updater: UpdateSystem::init("./dbs/updater").unwrap(),
+                    shutdown: Shutdown::default(),
+                    media_queue,
+                    mask_library,
+                    started_at,
                     bot_avatar: ready
                         .user
                         .avatar_url()
                         .unwrap_or_else(|| ready.user.default_avatar_url()),
-                })
+                };
+                shutdown::announce_up(&ctx.http, &data).await;
+                tokio::spawn(shutdown::shut_down_on_signal(
+                    ctx.clone(),
+                    data.clone(),
+                    framework.shard_manager().clone(),
+                ));
+                if let Some(metrics) = &data.config.get().metrics {
+                    tokio::spawn(metrics::serve(metrics.listen_address, data.clone()));
+                }
+                Ok(data)
             })
         })
         .build();
 
-    let mut client = serenity::ClientBuilder::new(discord_api_key, intents)
-        .framework(framework)
+    let mut client = serenity::ClientBuilder::new(&discord_api_key, intents)
+        .framework(Traced(framework))
         .await
         .expect("client start err");
 
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{CommandDataOption, CommandDataOptionValue};
+use sled::{Db, Tree};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longer than any command runs, so a start this old was never finished and can be dropped.
+const STALE_START: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommandOutcome {
+    Succeeded,
+    Failed(String),
+    /// A permission check stopped the command before it ran.
+    Denied,
+}
+
+impl CommandOutcome {
+    /// The outcome without its details, for grouping invocations by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CommandOutcome::Succeeded => "succeeded",
+            CommandOutcome::Failed(_) => "failed",
+            CommandOutcome::Denied => "denied",
+        }
+    }
+}
+
+impl fmt::Display for CommandOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandOutcome::Succeeded => write!(f, "Succeeded"),
+            CommandOutcome::Failed(err) => write!(f, "Failed: {}", err),
+            CommandOutcome::Denied => write!(f, "Denied"),
+        }
+    }
+}
+
+/// A single run of a bot command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandInvocation {
+    pub id: u64,
+    pub user_id: u64,
+    /// The full command path, e.g. `timed_role add`.
+    pub command: String,
+    pub arguments: String,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub invoked_at: u64,
+    pub duration_ms: u64,
+    pub outcome: CommandOutcome,
+}
+
+/// What `/audit` narrows invocations down by, `None` matching everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub user_id: Option<u64>,
+    /// Matches the command and its subcommands.
+    pub command: Option<String>,
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+}
+
+impl AuditFilter {
+    fn matches(&self, invocation: &CommandInvocation) -> bool {
+        self.user_id
+            .is_none_or(|user_id| invocation.user_id == user_id)
+            && self.command.as_deref().is_none_or(|command| {
+                invocation.command == command
+                    || invocation.command.starts_with(&format!("{} ", command))
+            })
+    }
+}
+
+/// Formats a slash command's arguments as `name: value` pairs, flattening subcommands.
+pub fn describe_options(options: &[CommandDataOption]) -> Vec<String> {
+    options
+        .iter()
+        .flat_map(|option| match &option.value {
+            CommandDataOptionValue::SubCommand(options)
+            | CommandDataOptionValue::SubCommandGroup(options) => describe_options(options),
+            value => {
+                let value = match value {
+                    CommandDataOptionValue::String(value) => value.clone(),
+                    CommandDataOptionValue::Integer(value) => value.to_string(),
+                    CommandDataOptionValue::Number(value) => value.to_string(),
+                    CommandDataOptionValue::Boolean(value) => value.to_string(),
+                    CommandDataOptionValue::User(id) => format!("<@{}>", id),
+                    CommandDataOptionValue::Channel(id) => format!("<#{}>", id),
+                    CommandDataOptionValue::Role(id) => format!("<@&{}>", id),
+                    CommandDataOptionValue::Mentionable(id) => id.to_string(),
+                    CommandDataOptionValue::Attachment(id) => format!("attachment {}", id),
+                    _ => "unknown".to_string(),
+                };
+                vec![format!("{}: {}", option.name, value)]
+            }
+        })
+        .collect()
+}
+
+/// Command invocations keyed by `invoked at ++ invocation id`, so date ranges are a range scan.
+#[derive(Clone)]
+pub struct CommandAuditDB {
+    db: Arc<Db>,
+    invocations: Tree,
+    /// When each running command started, as a timer and a unix timestamp.
+    started: Arc<Mutex<HashMap<u64, (Instant, u64)>>>,
+}
+
+fn invocation_key(invoked_at: u64, invocation_id: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&invoked_at.to_be_bytes());
+    key[8..].copy_from_slice(&invocation_id.to_be_bytes());
+    key
+}
+
+impl CommandAuditDB {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = CommandAuditDB {
+            invocations: db.open_tree("invocations")?,
+            db: Arc::clone(&db),
+            started: Arc::new(Mutex::new(HashMap::new())),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Notes that a command started running at `invoked_at`, keyed by its context id.
+    /// Starts older than `STALE_START` are dropped on the way.
+    pub fn start(&self, context_id: u64, invoked_at: u64) {
+        let mut started = self.started.lock().unwrap();
+        started.retain(|_, (started, _)| started.elapsed() < STALE_START);
+        started.insert(context_id, (Instant::now(), invoked_at));
+    }
+
+    /// When the command started and how long it has been running, `None` if it never started.
+    pub fn finish(&self, context_id: u64) -> Option<(u64, Duration)> {
+        self.started
+            .lock()
+            .unwrap()
+            .remove(&context_id)
+            .map(|(started, invoked_at)| (invoked_at, started.elapsed()))
+    }
+
+    pub fn record(&self, mut invocation: CommandInvocation) -> sled::Result<u64> {
+        invocation.id = self.db.generate_id()?;
+        let serialized = bincode::serialize(&invocation)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+        self.invocations.insert(
+            invocation_key(invocation.invoked_at, invocation.id),
+            serialized,
+        )?;
+        Ok(invocation.id)
+    }
+
+    /// The newest invocations matching the filter, at most `limit` of them.
+    pub fn query(
+        &self,
+        filter: &AuditFilter,
+        limit: usize,
+    ) -> sled::Result<Vec<CommandInvocation>> {
+        let start = invocation_key(filter.since.unwrap_or(0), 0);
+        let end = invocation_key(filter.until.unwrap_or(u64::MAX), u64::MAX);
+        let mut invocations = Vec::new();
+
+        for result in self.invocations.range(start..=end).rev() {
+            let (_, value) = result?;
+            let invocation: CommandInvocation = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            if filter.matches(&invocation) {
+                invocations.push(invocation);
+                if invocations.len() >= limit {
+                    break;
+                }
+            }
+        }
+
+        Ok(invocations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invocation(user_id: u64, command: &str, invoked_at: u64) -> CommandInvocation {
+        CommandInvocation {
+            id: 0,
+            user_id,
+            command: command.to_string(),
+            arguments: String::new(),
+            guild_id: Some(1),
+            channel_id: 2,
+            invoked_at,
+            duration_ms: 5,
+            outcome: CommandOutcome::Succeeded,
+        }
+    }
+
+    #[test]
+    fn test_query_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = CommandAuditDB::init(dir.path().to_str().unwrap()).unwrap();
+        db.record(invocation(10, "timed_role add", 100)).unwrap();
+        db.record(invocation(10, "policy clear_all", 200)).unwrap();
+        db.record(invocation(11, "timed_role", 300)).unwrap();
+        db.record(invocation(11, "timed_roles", 400)).unwrap();
+
+        let commands = |filter: AuditFilter, limit| -> Vec<String> {
+            db.query(&filter, limit)
+                .unwrap()
+                .into_iter()
+                .map(|invocation| invocation.command)
+                .collect()
+        };
+        assert_eq!(
+            commands(AuditFilter::default(), 2),
+            vec!["timed_roles", "timed_role"]
+        );
+        let timed_role = AuditFilter {
+            command: Some("timed_role".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            commands(timed_role.clone(), 10),
+            vec!["timed_role", "timed_role add"]
+        );
+        let by_user = AuditFilter {
+            user_id: Some(10),
+            until: Some(199),
+            ..timed_role
+        };
+        assert_eq!(commands(by_user, 10), vec!["timed_role add"]);
+        let since = AuditFilter {
+            since: Some(200),
+            until: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(commands(since, 10), vec!["timed_role", "policy clear_all"]);
+    }
+
+    #[test]
+    fn test_start_time_is_kept_until_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = CommandAuditDB::init(dir.path().to_str().unwrap()).unwrap();
+        db.start(1, 100);
+        let (invoked_at, _) = db.finish(1).unwrap();
+        assert_eq!(invoked_at, 100);
+        assert_eq!(db.finish(1), None);
+        assert_eq!(db.finish(2), None);
+    }
+
+    #[test]
+    fn test_unfinished_start_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = CommandAuditDB::init(dir.path().to_str().unwrap()).unwrap();
+        let long_ago = Instant::now().checked_sub(STALE_START * 2).unwrap();
+        db.started.lock().unwrap().insert(1, (long_ago, 100));
+        db.start(2, 200);
+        assert_eq!(db.finish(1), None);
+        assert_eq!(db.finish(2).unwrap().0, 200);
+    }
+}
@@ -0,0 +1,473 @@
+use serde::{Deserialize, Deserializer};
+use serenity::all::{ChannelId, Color, GuildId, RoleId};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::{env, fs};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// Environment variable holding the config file's path, `config.toml` is used when it's unset.
+pub const CONFIG_PATH_VAR: &str = "RON_CONFIG";
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let components: Vec<u8> = value
+        .split(',')
+        .map(|component| component.trim().parse::<u8>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("`{}` isn't an `r,g,b` color", value))?;
+    match components[..] {
+        [r, g, b] => Ok(Color::from_rgb(r, g, b)),
+        _ => Err(format!("`{}` isn't an `r,g,b` color", value)),
+    }
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_color(&value).map_err(serde::de::Error::custom)
+}
+
+// Channel, guild and role ids accept both strings and numbers, and fail to load unless they are
+// valid snowflakes.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct MainConfig {
+    pub discord_api_key: String,
+    pub guild_id: GuildId,
+    pub bloxlink_local_api_key: String,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: Color,
+    pub admin_role_ids: Vec<RoleId>,
+    pub default_badge_iterations: i64,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct LoggingConfig {
+    pub cdn_channel_id: ChannelId,
+    pub attachment_logging_channel_id: ChannelId,
+    pub reaction_logging_channel_id: ChannelId,
+    /// Where the main guild's command audit log is mirrored, if anywhere.
+    #[serde(default)]
+    pub command_audit_channel_id: Option<ChannelId>,
+    /// Where the bot announces going down and coming back up, if anywhere.
+    #[serde(default)]
+    pub ops_channel_id: Option<ChannelId>,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct PolicyConfig {
+    pub policy_channel_id: ChannelId,
+    pub policy_changes_channel_id: ChannelId,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct GuideConfig {
+    pub guide_channel_id: ChannelId,
+    pub guide_changes_channel_id: ChannelId,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct GameConfig {
+    pub open_cloud_base_url: String,
+    pub open_cloud_api_key: String,
+    pub universe_id: String,
+    pub ban_datastore_name: String,
+    pub moderation_topic: String,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct ProbationConfig {
+    pub notification_channel_id: ChannelId,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct UpdateStep {
+    pub name: String,
+    /// Run with `sh -c` in the working directory.
+    pub command: String,
+}
+
+/// How `/update` rebuilds the bot: the steps run in order, then `built_binary` replaces the
+/// running one.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct UpdateConfig {
+    pub working_directory: PathBuf,
+    pub steps: Vec<UpdateStep>,
+    /// Relative to the working directory.
+    pub built_binary: PathBuf,
+}
+
+/// How many media conversions run at once, and how many each user can have queued or running.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct MediaConfig {
+    pub max_concurrent_jobs: usize,
+    pub max_jobs_per_user: usize,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        MediaConfig {
+            max_concurrent_jobs: 2,
+            max_jobs_per_user: 2,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct ModulesConfig {
+    pub logging: LoggingConfig,
+    pub policy: PolicyConfig,
+    pub guide: GuideConfig,
+    pub game: GameConfig,
+    pub probation: ProbationConfig,
+    #[serde(default)]
+    pub update: Option<UpdateConfig>,
+    #[serde(default)]
+    pub media: MediaConfig,
+}
+
+/// Roles for the permission tiers below admin, which is `main.admin_role_ids`. Roles from any
+/// guild can be listed.
+#[derive(Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct PermissionsConfig {
+    pub staff_role_ids: Vec<RoleId>,
+    pub moderator_role_ids: Vec<RoleId>,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Where log files are written, a new one being started every `rotation`.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct LogFileConfig {
+    pub directory: PathBuf,
+    #[serde(default = "default_log_file_prefix")]
+    pub prefix: String,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    /// Older files past this many are deleted, all of them are kept when unset.
+    #[serde(default)]
+    pub max_files: Option<usize>,
+}
+
+fn default_log_file_prefix() -> String {
+    "ron-assistant.log".to_string()
+}
+
+/// How the bot logs. `level` takes `RUST_LOG` style directives, which `RUST_LOG` itself
+/// overrides when set.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TracingConfig {
+    pub level: String,
+    /// Logs a JSON object per line instead of human readable text.
+    pub json: bool,
+    pub file: Option<LogFileConfig>,
+}
+
+impl Default for TracingConfig {
+    fn default() -> Self {
+        TracingConfig {
+            level: "info".to_string(),
+            json: false,
+            file: None,
+        }
+    }
+}
+
+/// Where Prometheus metrics are served from, under `/metrics`. Anyone who can reach it can read
+/// them, so it's best kept on localhost.
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct MetricsConfig {
+    pub listen_address: SocketAddr,
+}
+
+#[derive(Clone, PartialEq, Deserialize)]
+pub struct Config {
+    pub main: MainConfig,
+    pub modules: ModulesConfig,
+    #[serde(default)]
+    pub permissions: PermissionsConfig,
+    #[serde(default)]
+    pub tracing: TracingConfig,
+    #[serde(default)]
+    pub metrics: Option<MetricsConfig>,
+}
+
+impl Config {
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(contents).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn read(path: &Path) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("couldn't read {}: {}", path.display(), err))?;
+        Config::parse(&contents)
+    }
+
+    /// Checks what deserializing can't, listing every problem at once.
+    fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.main.discord_api_key.trim().is_empty() {
+            problems.push("main.discord_api_key is empty".to_string());
+        }
+        if self.main.admin_role_ids.is_empty() {
+            problems.push("main.admin_role_ids needs at least one role".to_string());
+        }
+        if self.main.default_badge_iterations < 1 {
+            problems.push("main.default_badge_iterations must be at least 1".to_string());
+        }
+        if !self.modules.game.open_cloud_base_url.starts_with("http") {
+            problems.push("modules.game.open_cloud_base_url must be an http(s) url".to_string());
+        }
+        if let Some(update) = &self.modules.update
+            && update.steps.is_empty()
+        {
+            problems.push("modules.update.steps needs at least one step".to_string());
+        }
+        if self.modules.media.max_concurrent_jobs < 1 || self.modules.media.max_jobs_per_user < 1 {
+            problems.push("modules.media limits must be at least 1".to_string());
+        }
+        if let Err(err) = EnvFilter::try_new(&self.tracing.level) {
+            problems.push(format!("tracing.level isn't a valid filter: {}", err));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join(", "))
+        }
+    }
+
+    /// The settings that differ in `new` but are only read when the bot starts.
+    pub fn restart_required(&self, new: &Config) -> Vec<&'static str> {
+        let mut settings = Vec::new();
+        if self.main.discord_api_key != new.main.discord_api_key {
+            settings.push("main.discord_api_key");
+        }
+        // Commands are registered in this guild when the bot starts.
+        if self.main.guild_id != new.main.guild_id {
+            settings.push("main.guild_id");
+        }
+        if self.modules.game != new.modules.game {
+            settings.push("modules.game");
+        }
+        if self.modules.media != new.modules.media {
+            settings.push("modules.media");
+        }
+        if self.tracing != new.tracing {
+            settings.push("tracing");
+        }
+        if self.metrics != new.metrics {
+            settings.push("metrics");
+        }
+        settings
+    }
+}
+
+/// The live config, shared by everything in `Data` and swapped out in place when reloaded.
+#[derive(Clone)]
+pub struct SharedConfig {
+    path: PathBuf,
+    current: Arc<RwLock<Arc<Config>>>,
+}
+
+impl SharedConfig {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let config = Config::read(&path)?;
+        Ok(SharedConfig {
+            path,
+            current: Arc::new(RwLock::new(Arc::new(config))),
+        })
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        SharedConfig::load(env::var(CONFIG_PATH_VAR).unwrap_or_else(|_| "config.toml".to_string()))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    /// Re-reads the config file, keeping the current config if the new one doesn't load.
+    /// Returns the changed settings that still need a restart.
+    pub fn reload(&self) -> Result<Vec<&'static str>, String> {
+        let config = Config::read(&self.path)?;
+        let mut current = self.current.write().unwrap();
+        let restart_required = current.restart_required(&config);
+        *current = Arc::new(config);
+        Ok(restart_required)
+    }
+
+    /// Reloads the config, describing the outcome for whoever asked for it.
+    pub fn reload_and_describe(&self) -> String {
+        match self.reload() {
+            Ok(restart_required) if restart_required.is_empty() => {
+                format!("Reloaded config from `{}`.", self.path.display())
+            }
+            Ok(restart_required) => format!(
+                "Reloaded config from `{}`, these changes only apply after a restart: {}",
+                self.path.display(),
+                restart_required.join(", ")
+            ),
+            Err(err) => format!("Couldn't reload config, keeping the current one: {}", err),
+        }
+    }
+}
+
+/// Reloads the config whenever the process receives SIGHUP.
+#[cfg(unix)]
+pub async fn reload_on_hangup(config: SharedConfig) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(err) => {
+            warn!(error = %err, "Couldn't listen for SIGHUP, config can only be reloaded by command");
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        info!("{}", config.reload_and_describe());
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    pub(crate) const VALID: &str = r#"
+[main]
+discord_api_key = "token"
+guild_id = "1000"
+bloxlink_local_api_key = "key"
+color = "255, 128, 0"
+admin_role_ids = [2000, "2001"]
+default_badge_iterations = 5
+
+[modules.logging]
+cdn_channel_id = "3000"
+attachment_logging_channel_id = "3001"
+reaction_logging_channel_id = "3002"
+
+[modules.policy]
+policy_channel_id = "3003"
+policy_changes_channel_id = "3004"
+
+[modules.guide]
+guide_channel_id = "3005"
+guide_changes_channel_id = "3006"
+
+[modules.game]
+open_cloud_base_url = "https://apis.roblox.com"
+open_cloud_api_key = "key"
+universe_id = "42"
+ban_datastore_name = "Bans"
+moderation_topic = "Moderation"
+
+[modules.probation]
+notification_channel_id = "3007"
+
+[modules.update]
+working_directory = "/srv/ron-assistant"
+built_binary = "target/release/ron-assista-bot"
+steps = [
+    { name = "Fetch", command = "git pull" },
+    { name = "Build", command = "cargo build --release" },
+]
+
+[modules.media]
+max_concurrent_jobs = 4
+
+[permissions]
+staff_role_ids = ["2100"]
+moderator_role_ids = ["2200", "2201"]
+
+[tracing]
+level = "info,serenity=warn"
+json = true
+
+[tracing.file]
+directory = "logs"
+rotation = "hourly"
+
+[metrics]
+listen_address = "127.0.0.1:9090"
+"#;
+
+    #[test]
+    fn test_parse_and_validate() {
+        let config = Config::parse(VALID).ok().unwrap();
+        assert_eq!(config.main.guild_id, GuildId::new(1000));
+        assert_eq!(
+            config.main.admin_role_ids,
+            vec![RoleId::new(2000), RoleId::new(2001)]
+        );
+        assert_eq!(config.main.color, Color::from_rgb(255, 128, 0));
+        assert_eq!(config.permissions.staff_role_ids, vec![RoleId::new(2100)]);
+        assert_eq!(config.modules.update.as_ref().unwrap().steps[1].name, "Build");
+        assert_eq!(config.modules.media.max_concurrent_jobs, 4);
+        assert_eq!(config.modules.media.max_jobs_per_user, 2);
+        let log_file = config.tracing.file.as_ref().unwrap();
+        assert!(log_file.rotation == LogRotation::Hourly);
+        assert_eq!(log_file.prefix, "ron-assistant.log");
+        assert_eq!(
+            config.metrics.as_ref().unwrap().listen_address,
+            "127.0.0.1:9090".parse().unwrap()
+        );
+        let no_permissions = VALID.split("[permissions]").next().unwrap();
+        let defaults = Config::parse(no_permissions).ok().unwrap();
+        assert!(defaults.permissions.moderator_role_ids.is_empty());
+        assert!(defaults.tracing == TracingConfig::default());
+        assert!(defaults.metrics.is_none());
+
+        let bad_color = VALID.replace("255, 128, 0", "255, 128");
+        assert!(Config::parse(&bad_color).err().unwrap().contains("r,g,b"));
+        let bad_channel = VALID.replace("\"3001\"", "\"general\"");
+        assert!(Config::parse(&bad_channel).is_err());
+        let no_workers = VALID.replace("max_concurrent_jobs = 4", "max_concurrent_jobs = 0");
+        assert!(Config::parse(&no_workers).err().unwrap().contains("modules.media"));
+        let bad_level = VALID.replace("serenity=warn", "serenity=loud");
+        assert!(Config::parse(&bad_level).err().unwrap().contains("tracing.level"));
+        let no_admins = VALID.replace("[2000, \"2001\"]", "[]");
+        assert!(
+            Config::parse(&no_admins)
+                .err()
+                .unwrap()
+                .contains("admin_role_ids")
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_config_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, VALID).unwrap();
+        let config = SharedConfig::load(&path).ok().unwrap();
+
+        fs::write(&path, VALID.replace("\"3007\"", "\"3008\"")).unwrap();
+        assert_eq!(config.reload(), Ok(vec![]));
+        assert_eq!(
+            config.get().modules.probation.notification_channel_id,
+            ChannelId::new(3008)
+        );
+
+        fs::write(&path, VALID.replace("\"1000\"", "\"1001\"")).unwrap();
+        assert_eq!(config.reload(), Ok(vec!["main.guild_id"]));
+
+        fs::write(&path, "not toml").unwrap();
+        assert!(config.reload().is_err());
+        assert_eq!(config.get().main.guild_id, GuildId::new(1001));
+    }
+}
@@ -50,6 +50,19 @@ impl AttachmentStoreDB {
         })
     }
 
+    pub fn entry_count(&self) -> usize {
+        self.db.len()
+    }
+
+    pub fn size_on_disk(&self) -> Result<u64, SledError> {
+        self.db.size_on_disk()
+    }
+
+    pub fn flush(&self) -> Result<(), SledError> {
+        self.db.flush()?;
+        Ok(())
+    }
+
     pub fn delete(&self, message_id: &str) -> Result<(), SledError> {
         let key = message_id.as_bytes();
         self.db.remove(key)?;
@@ -85,7 +98,7 @@ pub fn start_attachment_db() {
         loop {
             std::thread::sleep(Duration::from_secs(1800)); // 30 minutes
             if let Err(e) = db.lock().unwrap().delete_old_entries() {
-                eprintln!("Error deleting old attachment entries: {}", e);
+                tracing::error!(error = %e, "Couldn't delete old attachment entries");
             }
         }
     });
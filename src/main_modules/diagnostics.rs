@@ -0,0 +1,93 @@
+use reqwest::{RequestBuilder, StatusCode};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// How long a dependency gets to answer a probe before it counts as down.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a dependency answered a probe with, and how long it took to.
+pub struct Probe {
+    pub latency: Duration,
+    pub status: Result<StatusCode, reqwest::Error>,
+}
+
+pub async fn probe(request: RequestBuilder) -> Probe {
+    let started = Instant::now();
+    let status = request
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .map(|response| response.status());
+    Probe {
+        latency: started.elapsed(),
+        status,
+    }
+}
+
+/// The first line of `ffmpeg -version`, which has the version and build.
+pub async fn ffmpeg_version() -> Result<String, String> {
+    let output = Command::new("ffmpeg")
+        .arg("-version")
+        .output()
+        .await
+        .map_err(|err| format!("couldn't run ffmpeg: {}", err))?;
+    if !output.status.success() {
+        return Err(format!("`ffmpeg -version` exited with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// The total size of the files under `path`.
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), [0; 100]).unwrap();
+        fs::create_dir(dir.path().join("frames")).unwrap();
+        fs::write(dir.path().join("frames").join("b"), [0; 50]).unwrap();
+        assert_eq!(dir_size(dir.path()).unwrap(), 150);
+        assert!(dir_size(&dir.path().join("missing")).is_err());
+    }
+}
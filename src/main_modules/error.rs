@@ -0,0 +1,184 @@
+use serenity::all::{Colour, CreateEmbed, UserId};
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use uuid::Uuid;
+
+use super::diagnostics::format_bytes;
+
+/// What can go wrong talking to outside services, converting media and using the bot's own
+/// storage. `Display` is written for users, `Debug` keeps the details for maintainers.
+#[derive(Debug)]
+pub enum BotError {
+    /// An outside API couldn't be reached, or answered with an error or something unreadable.
+    Api {
+        service: &'static str,
+        details: String,
+    },
+    /// The Discord user has no Roblox account linked through Bloxlink.
+    NotVerified(UserId),
+    /// Something the user typed in couldn't be understood.
+    InvalidInput(String),
+    /// A file the media commands can't work with, by its name.
+    UnsupportedMedia(String),
+    /// ffmpeg or ffprobe exited unsuccessfully, or ffprobe's output couldn't be read.
+    Ffmpeg {
+        stderr: String,
+    },
+    /// An output that's still over the upload limit after shrinking it as far as it goes.
+    TooLarge {
+        size: u64,
+        limit: u64,
+    },
+    /// The user cancelled the job before it finished.
+    Cancelled,
+    Image(image::ImageError),
+    Io(io::Error),
+    Discord(Box<serenity::Error>),
+    Database(sled::Error),
+}
+
+impl BotError {
+    /// What only maintainers need to see, like the API response or ffmpeg output.
+    pub fn details(&self) -> Option<&str> {
+        match self {
+            BotError::Api { details, .. } => Some(details),
+            BotError::Ffmpeg { stderr } => Some(stderr),
+            _ => None,
+        }
+    }
+
+    pub fn bloxlink(details: impl fmt::Display) -> Self {
+        BotError::Api {
+            service: "Bloxlink",
+            details: details.to_string(),
+        }
+    }
+
+    pub fn roblox(details: impl fmt::Display) -> Self {
+        BotError::Api {
+            service: "Roblox",
+            details: details.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Api { service, .. } => write!(
+                f,
+                "{} couldn't be reached or sent back something unexpected, try again later.",
+                service
+            ),
+            BotError::NotVerified(discord_id) => write!(
+                f,
+                "Couldn't find a Roblox account for `{}`, they might not be verified with Bloxlink.",
+                discord_id
+            ),
+            BotError::InvalidInput(details) => write!(f, "{}", details),
+            BotError::UnsupportedMedia(name) => write!(
+                f,
+                "`{}` isn't an image or video the media commands can work with.",
+                name
+            ),
+            BotError::Ffmpeg { .. } => write!(f, "ffmpeg couldn't convert the file."),
+            BotError::TooLarge { size, limit } => write!(
+                f,
+                "The result is {} even at the lowest quality, over the {} upload limit here.",
+                format_bytes(*size),
+                format_bytes(*limit)
+            ),
+            BotError::Cancelled => write!(f, "The job was cancelled."),
+            BotError::Image(err) => write!(f, "Couldn't process the image: {}", err),
+            BotError::Io(_) => write!(f, "Couldn't read or write a temporary file."),
+            BotError::Discord(err) => write!(f, "Discord sent back an error: {}", err),
+            BotError::Database(_) => write!(f, "Couldn't access the bot's database."),
+        }
+    }
+}
+
+impl StdError for BotError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            BotError::Image(err) => Some(err),
+            BotError::Io(err) => Some(err),
+            BotError::Discord(err) => Some(err.as_ref()),
+            BotError::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for BotError {
+    fn from(err: image::ImageError) -> Self {
+        BotError::Image(err)
+    }
+}
+
+impl From<io::Error> for BotError {
+    fn from(err: io::Error) -> Self {
+        BotError::Io(err)
+    }
+}
+
+impl From<serenity::Error> for BotError {
+    fn from(err: serenity::Error) -> Self {
+        BotError::Discord(Box::new(err))
+    }
+}
+
+impl From<sled::Error> for BotError {
+    fn from(err: sled::Error) -> Self {
+        BotError::Database(err)
+    }
+}
+
+/// Logs an error in full under a new id, returning the id for users to quote when reporting it.
+pub fn report(context: &str, error: &(dyn StdError + 'static)) -> String {
+    let id = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let mut details = match error.downcast_ref::<BotError>() {
+        Some(err) => match err.details() {
+            Some(extra) => format!("{}\n  details: {}", err, extra),
+            None => err.to_string(),
+        },
+        None => format!("{:?}", error),
+    };
+    let mut source = error.source();
+    while let Some(cause) = source {
+        details.push_str(&format!("\n  caused by: {}", cause));
+        source = cause.source();
+    }
+    tracing::error!(error_id = id, context, "{}", details);
+    id
+}
+
+/// Fills in `embed` to tell the user what went wrong.
+pub fn error_embed(embed: CreateEmbed, error: &dyn fmt::Display, id: &str) -> CreateEmbed {
+    embed
+        .title("Something went wrong")
+        .description(error.to_string())
+        .colour(Colour::RED)
+        .field("Error ID", format!("`{}`", id), false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_hide_details() {
+        let err = BotError::bloxlink("status 500 Internal Server Error");
+        assert!(!err.to_string().contains("500"));
+        assert_eq!(err.details(), Some("status 500 Internal Server Error"));
+
+        let err = BotError::Ffmpeg {
+            stderr: "Invalid data found when processing input".to_string(),
+        };
+        assert_eq!(err.to_string(), "ffmpeg couldn't convert the file.");
+
+        let err = BotError::from(io::Error::other("disk full"));
+        assert_eq!(err.source().unwrap().to_string(), "disk full");
+        assert_eq!(report("test", &err).len(), 8);
+    }
+}
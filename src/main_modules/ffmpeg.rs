@@ -0,0 +1,369 @@
+use serde::Deserialize;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::debug;
+
+use super::error::BotError;
+use super::media_queue::Progress;
+
+/// An ffmpeg command, built up in the order its arguments are passed. Overwrites its output
+/// without asking, and is killed if the future running it is dropped, like when its job is
+/// cancelled.
+#[derive(Debug, Clone)]
+pub struct Ffmpeg {
+    args: Vec<OsString>,
+}
+
+impl Ffmpeg {
+    pub fn new() -> Self {
+        Ffmpeg {
+            args: vec!["-hide_banner".into(), "-y".into()],
+        }
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(mut self, args: I) -> Self {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    /// Options for an input go before it, like `-f concat` or `-framerate 25`.
+    pub fn input(self, path: impl AsRef<OsStr>) -> Self {
+        self.arg("-i").arg(path)
+    }
+
+    pub fn video_codec(self, codec: &str) -> Self {
+        self.args(["-c:v", codec])
+    }
+
+    pub fn audio_codec(self, codec: &str) -> Self {
+        self.args(["-c:a", codec])
+    }
+
+    /// Copies every stream as it is instead of re-encoding it.
+    pub fn copy_codecs(self) -> Self {
+        self.args(["-c", "copy"])
+    }
+
+    pub fn crf(self, crf: u8) -> Self {
+        self.arg("-crf").arg(crf.to_string())
+    }
+
+    pub fn pixel_format(self, format: &str) -> Self {
+        self.args(["-pix_fmt", format])
+    }
+
+    pub fn video_filter(self, filter: &str) -> Self {
+        self.args(["-vf", filter])
+    }
+
+    pub fn filter_complex(self, filter: &str) -> Self {
+        self.args(["-filter_complex", filter])
+    }
+
+    /// Forces the format of the next input or output, rather than guessing it from the name.
+    pub fn format(self, format: &str) -> Self {
+        self.args(["-f", format])
+    }
+
+    pub fn video_bitrate(self, kbps: u64) -> Self {
+        self.arg("-b:v").arg(format!("{}k", kbps))
+    }
+
+    pub fn audio_bitrate(self, kbps: u64) -> Self {
+        self.arg("-b:a").arg(format!("{}k", kbps))
+    }
+
+    /// Which pass of a two-pass encode this is, with both passes sharing the stats in
+    /// `log_prefix`.
+    pub fn pass(self, pass: u8, log_prefix: &Path) -> Self {
+        self.arg("-pass")
+            .arg(pass.to_string())
+            .arg("-passlogfile")
+            .arg(log_prefix)
+    }
+
+    pub fn output(self, path: impl AsRef<OsStr>) -> Self {
+        self.arg(path)
+    }
+
+    fn command(&self, with_progress: bool) -> Command {
+        let mut command = Command::new("ffmpeg");
+        if with_progress {
+            // Global options, so they have to come before any input.
+            command.args(["-progress", "pipe:1", "-nostats"]);
+        }
+        command
+            .args(&self.args)
+            .kill_on_drop(true)
+            .stdin(Stdio::null());
+        command
+    }
+
+    fn log_finished(&self, status: &std::process::ExitStatus, started: Instant) {
+        debug!(args = ?self.args, %status, elapsed = ?started.elapsed(), "ffmpeg finished");
+    }
+
+    /// Runs the command, turning an unsuccessful exit into an error carrying its stderr.
+    pub async fn run(self) -> Result<Output, BotError> {
+        let started = Instant::now();
+        let output = self.command(false).output().await?;
+        self.log_finished(&output.status, started);
+        if !output.status.success() {
+            return Err(BotError::Ffmpeg {
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(output)
+    }
+
+    /// Like `run`, setting `progress` to how much of the `duration` long input has been
+    /// converted.
+    pub async fn run_with_progress(
+        self,
+        duration: Option<Duration>,
+        progress: &Progress,
+    ) -> Result<(), BotError> {
+        let started = Instant::now();
+        let mut child = self
+            .command(true)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let mut stderr = BufReader::new(child.stderr.take().expect("stderr is piped")).lines();
+
+        let duration = duration.filter(|duration| !duration.is_zero());
+        let mut logged = String::new();
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        while stdout_open || stderr_open {
+            tokio::select! {
+                line = stdout.next_line(), if stdout_open => match line? {
+                    Some(line) => {
+                        if let (Some(duration), Some(done)) = (duration, parse_progress_line(&line)) {
+                            progress.set(done.as_secs_f32() / duration.as_secs_f32());
+                        }
+                    }
+                    None => stdout_open = false,
+                },
+                line = stderr.next_line(), if stderr_open => match line? {
+                    Some(line) => {
+                        logged.push_str(&line);
+                        logged.push('\n');
+                    }
+                    None => stderr_open = false,
+                },
+            }
+        }
+
+        let status = child.wait().await?;
+        self.log_finished(&status, started);
+        if !status.success() {
+            return Err(BotError::Ffmpeg { stderr: logged });
+        }
+        Ok(())
+    }
+}
+
+/// How far into the input ffmpeg is, from a `-progress` line.
+fn parse_progress_line(line: &str) -> Option<Duration> {
+    let micros = line.strip_prefix("out_time_us=")?.parse::<u64>().ok()?;
+    Some(Duration::from_micros(micros))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoStream {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    /// `None` when the container doesn't say, like for still images.
+    pub fps: Option<f64>,
+}
+
+/// What ffprobe found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaInfo {
+    /// Can list several names for one format, like `mov,mp4,m4a,3gp,3g2,mj2`.
+    pub format_name: String,
+    pub duration: Option<Duration>,
+    /// The first video stream, which images count as too.
+    pub video: Option<VideoStream>,
+    /// The codec of the first audio stream.
+    pub audio_codec: Option<String>,
+}
+
+impl MediaInfo {
+    pub fn has_audio(&self) -> bool {
+        self.audio_codec.is_some()
+    }
+
+    fn from_json(json: &[u8]) -> Result<MediaInfo, serde_json::Error> {
+        let probed: ProbeOutput = serde_json::from_slice(json)?;
+        let video = probed
+            .streams
+            .iter()
+            .find(|stream| stream.codec_type == "video")
+            .and_then(|stream| {
+                Some(VideoStream {
+                    codec: stream.codec_name.clone()?,
+                    width: stream.width?,
+                    height: stream.height?,
+                    fps: stream.avg_frame_rate.as_deref().and_then(parse_frame_rate),
+                })
+            });
+        let audio_codec = probed
+            .streams
+            .iter()
+            .find(|stream| stream.codec_type == "audio")
+            .and_then(|stream| stream.codec_name.clone());
+        let duration = probed
+            .format
+            .duration
+            .as_deref()
+            .and_then(|duration| duration.parse::<f64>().ok())
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+        Ok(MediaInfo {
+            format_name: probed.format.format_name,
+            duration,
+            video,
+            audio_codec,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: ProbeFormat,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_type: String,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+    duration: Option<String>,
+}
+
+/// ffprobe gives frame rates as fractions like `30000/1001`, and `0/0` when there's none.
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let (numerator, denominator) = (
+        numerator.parse::<f64>().ok()?,
+        denominator.parse::<f64>().ok()?,
+    );
+    (numerator > 0.0 && denominator > 0.0).then(|| numerator / denominator)
+}
+
+/// Reads the format and streams of the file at `path` with ffprobe.
+pub async fn probe(path: &Path) -> Result<MediaInfo, BotError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .kill_on_drop(true)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(BotError::Ffmpeg {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+    MediaInfo::from_json(&output.stdout).map_err(|err| BotError::Ffmpeg {
+        stderr: format!("couldn't read ffprobe's output: {}", err),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_parse() {
+        let command = Ffmpeg::new()
+            .args(["-f", "concat"])
+            .input("list.txt")
+            .video_codec("libx264")
+            .video_bitrate(1200)
+            .pass(2, Path::new("pass"))
+            .output("out.mp4");
+        assert_eq!(
+            command.args,
+            [
+                "-hide_banner",
+                "-y",
+                "-f",
+                "concat",
+                "-i",
+                "list.txt",
+                "-c:v",
+                "libx264",
+                "-b:v",
+                "1200k",
+                "-pass",
+                "2",
+                "-passlogfile",
+                "pass",
+                "out.mp4"
+            ]
+        );
+
+        assert_eq!(
+            parse_progress_line("out_time_us=31250000"),
+            Some(Duration::from_millis(31250))
+        );
+        assert_eq!(parse_progress_line("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_line("progress=continue"), None);
+        assert_eq!(parse_frame_rate("30000/1001").map(f64::round), Some(30.0));
+        assert_eq!(parse_frame_rate("0/0"), None);
+    }
+
+    #[test]
+    fn test_media_info_from_probe() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720, "avg_frame_rate": "30/1"},
+                {"codec_type": "audio", "codec_name": "aac", "avg_frame_rate": "0/0"}
+            ],
+            "format": {"format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "12.500000"}
+        }"#;
+        let info = MediaInfo::from_json(json).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(12500)));
+        assert_eq!(info.video.as_ref().unwrap().width, 1280);
+        assert_eq!(info.video.as_ref().unwrap().fps, Some(30.0));
+        assert!(info.has_audio());
+
+        let image = br#"{
+            "streams": [{"codec_type": "video", "codec_name": "png", "width": 64, "height": 64, "avg_frame_rate": "0/0"}],
+            "format": {"format_name": "png_pipe"}
+        }"#;
+        let info = MediaInfo::from_json(image).unwrap();
+        assert_eq!(info.duration, None);
+        assert!(!info.has_audio());
+        assert_eq!(info.video.unwrap().fps, None);
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::UserId;
+use sled::{Db, Tree};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SanctionKind {
+    Ban,
+    Warn,
+}
+
+impl fmt::Display for SanctionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanctionKind::Ban => write!(f, "Ban"),
+            SanctionKind::Warn => write!(f, "Warn"),
+        }
+    }
+}
+
+/// An in-game ban or warn against a Roblox account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSanction {
+    pub id: u64,
+    pub roblox_id: u64,
+    pub kind: SanctionKind,
+    pub reason: String,
+    pub moderator_id: u64,
+    pub issued_at: u64,
+    /// `None` for permanent bans and warns.
+    pub expires_at: Option<u64>,
+    pub revoked: bool,
+}
+
+impl GameSanction {
+    pub fn new(
+        kind: SanctionKind,
+        roblox_id: u64,
+        moderator_id: UserId,
+        reason: &str,
+        issued_at: u64,
+    ) -> Self {
+        GameSanction {
+            id: 0,
+            roblox_id,
+            kind,
+            reason: reason.to_string(),
+            moderator_id: moderator_id.get(),
+            issued_at,
+            expires_at: None,
+            revoked: false,
+        }
+    }
+
+    pub fn expires_at(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        !self.revoked && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+/// Game-side moderation state, keyed by `roblox id ++ sanction id` so a player's sanctions are a
+/// single prefix scan.
+#[derive(Clone)]
+pub struct GameSanctionDB {
+    db: Arc<Db>,
+    sanctions: Tree,
+}
+
+fn sanction_key(roblox_id: u64, sanction_id: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&roblox_id.to_be_bytes());
+    key[8..].copy_from_slice(&sanction_id.to_be_bytes());
+    key
+}
+
+impl GameSanctionDB {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = GameSanctionDB {
+            sanctions: db.open_tree("sanctions")?,
+            db: Arc::clone(&db),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    fn insert(&self, sanction: &GameSanction) -> sled::Result<()> {
+        let serialized = bincode::serialize(sanction)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+        self.sanctions
+            .insert(sanction_key(sanction.roblox_id, sanction.id), serialized)?;
+        Ok(())
+    }
+
+    /// Stores a sanction, returning the id it was given.
+    pub fn add(&self, mut sanction: GameSanction) -> sled::Result<u64> {
+        sanction.id = self.db.generate_id()?;
+        self.insert(&sanction)?;
+        Ok(sanction.id)
+    }
+
+    /// Marks a sanction as lifted, returning it if it was still in force.
+    pub fn revoke(&self, roblox_id: u64, sanction_id: u64) -> sled::Result<Option<GameSanction>> {
+        match self.get(roblox_id, sanction_id)? {
+            Some(mut sanction) if !sanction.revoked => {
+                sanction.revoked = true;
+                self.insert(&sanction)?;
+                Ok(Some(sanction))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn get(&self, roblox_id: u64, sanction_id: u64) -> sled::Result<Option<GameSanction>> {
+        match self.sanctions.get(sanction_key(roblox_id, sanction_id))? {
+            Some(value) => {
+                let sanction: GameSanction = bincode::deserialize(&value)
+                    .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+                Ok(Some(sanction))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Every sanction ever issued against the player, oldest first.
+    pub fn get_by_roblox_id(&self, roblox_id: u64) -> sled::Result<Vec<GameSanction>> {
+        let mut sanctions = Vec::new();
+
+        for result in self.sanctions.scan_prefix(roblox_id.to_be_bytes()) {
+            let (_, value) = result?;
+            let sanction: GameSanction = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            sanctions.push(sanction);
+        }
+
+        sanctions.sort_by_key(|sanction| sanction.issued_at);
+        Ok(sanctions)
+    }
+
+    pub fn active(&self, roblox_id: u64, now: u64) -> sled::Result<Vec<GameSanction>> {
+        let mut sanctions = self.get_by_roblox_id(roblox_id)?;
+        sanctions.retain(|sanction| sanction.is_active(now));
+        Ok(sanctions)
+    }
+
+    pub fn is_banned(&self, roblox_id: u64, now: u64) -> sled::Result<bool> {
+        Ok(self
+            .active(roblox_id, now)?
+            .iter()
+            .any(|sanction| sanction.kind == SanctionKind::Ban))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_sanctions() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = GameSanctionDB::init(dir.path().to_str().unwrap()).unwrap();
+        let moderator = UserId::new(1);
+
+        db.add(GameSanction::new(
+            SanctionKind::Warn,
+            156,
+            moderator,
+            "spam",
+            10,
+        ))
+        .unwrap();
+        let temp_ban = db
+            .add(
+                GameSanction::new(SanctionKind::Ban, 156, moderator, "exploiting", 20)
+                    .expires_at(100),
+            )
+            .unwrap();
+        db.add(GameSanction::new(
+            SanctionKind::Ban,
+            1,
+            moderator,
+            "other player",
+            30,
+        ))
+        .unwrap();
+
+        assert!(db.is_banned(156, 50).unwrap());
+        assert!(!db.is_banned(156, 100).unwrap());
+        assert_eq!(db.active(156, 100).unwrap().len(), 1);
+        assert_eq!(db.get(156, temp_ban).unwrap().unwrap().reason, "exploiting");
+        assert_eq!(db.get_by_roblox_id(156).unwrap().len(), 2);
+
+        assert!(db.revoke(156, temp_ban).unwrap().is_some());
+        assert!(db.revoke(156, temp_ban).unwrap().is_none());
+        assert!(!db.is_banned(156, 50).unwrap());
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Serialize, Deserialize};
 use serenity::all::{ChannelId, Context, Message};
 use sled::Db;
 
-use super::CONFIG;
+use super::config::GuideConfig;
 use std::sync::Arc;
 
 #[derive(Serialize, Deserialize)]
@@ -36,6 +36,15 @@ impl GuideSystem {
         Ok(system)
     }
 
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn size_on_disk(&self) -> sled::Result<u64> {
+        self.db.size_on_disk()
+    }
+
     pub fn edit(&self, internal_name: &str, content: String, order: u64) -> sled::Result<()> {
         let entry = GuideEntry { content, order };
         let serialized = bincode::serialize(&entry).map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
@@ -82,7 +91,7 @@ impl GuideSystem {
         Ok(policies)
     }
 
-    pub async fn update_guide(&self, ctx: &Context) -> sled::Result<()> {
+    pub async fn update_guide(&self, ctx: &Context, channels: &GuideConfig) -> sled::Result<()> {
         let policies = self.list_policies()?;
     
         let mut file_contents = String::new();
@@ -99,7 +108,7 @@ impl GuideSystem {
         if previous_file_path.exists() {
             let previous_content = fs::read_to_string(previous_file_path).unwrap_or_default();
             if previous_content != file_contents {
-                let changes_channel_id = CONFIG.modules.guide.guide_changes_channel_id.parse::<u64>().unwrap();
+                let changes_channel_id = channels.guide_changes_channel_id.get();
                 let changes_channel = ctx.http.get_channel(changes_channel_id.into()).await.unwrap();
     
                 let diff = diff_policies(&previous_content, &file_contents);
@@ -110,7 +119,7 @@ impl GuideSystem {
         let mut file = fs::File::create(current_file_path)?;
         file.write_all(file_contents.as_bytes())?;
     
-        let guide_channel_id = CONFIG.modules.guide.guide_channel_id.parse::<u64>().unwrap();
+        let guide_channel_id = channels.guide_channel_id.get();
         let guide_channel = ctx.http.get_channel(guide_channel_id.into()).await.unwrap();
     
         let guide_actual_id = ChannelId::new(guide_channel_id);
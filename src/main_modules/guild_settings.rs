@@ -0,0 +1,331 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, GuildId, RoleId};
+use sled::{Db, Tree};
+use std::fmt;
+use std::sync::Arc;
+
+use super::config::Config;
+
+const GUILD_TREE_PREFIX: &str = "guild_";
+const ATTACHMENT_LOGGING_CHANNEL_KEY: &str = "attachment_logging_channel_id";
+const REACTION_LOGGING_CHANNEL_KEY: &str = "reaction_logging_channel_id";
+const COMMAND_AUDIT_CHANNEL_KEY: &str = "command_audit_channel_id";
+const ADMIN_ROLES_KEY: &str = "admin_role_ids";
+const DISABLED_MODULES_KEY: &str = "disabled_modules";
+const BLOXLINK_GUILD_KEY: &str = "bloxlink_guild_id";
+
+/// Features that can be switched off per guild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, poise::ChoiceParameter)]
+pub enum GuildModule {
+    #[name = "Attachment Logging"]
+    AttachmentLogging,
+    #[name = "Reaction Logging"]
+    ReactionLogging,
+    #[name = "Video Conversion"]
+    VideoConversion,
+    #[name = "Command Audit"]
+    CommandAudit,
+}
+
+impl GuildModule {
+    pub const ALL: [GuildModule; 4] = [
+        GuildModule::AttachmentLogging,
+        GuildModule::ReactionLogging,
+        GuildModule::VideoConversion,
+        GuildModule::CommandAudit,
+    ];
+
+    fn log_channel_key(&self) -> Option<&'static str> {
+        match self {
+            GuildModule::AttachmentLogging => Some(ATTACHMENT_LOGGING_CHANNEL_KEY),
+            GuildModule::ReactionLogging => Some(REACTION_LOGGING_CHANNEL_KEY),
+            GuildModule::VideoConversion => None,
+            GuildModule::CommandAudit => Some(COMMAND_AUDIT_CHANNEL_KEY),
+        }
+    }
+}
+
+impl fmt::Display for GuildModule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuildModule::AttachmentLogging => write!(f, "Attachment Logging"),
+            GuildModule::ReactionLogging => write!(f, "Reaction Logging"),
+            GuildModule::VideoConversion => write!(f, "Video Conversion"),
+            GuildModule::CommandAudit => write!(f, "Command Audit"),
+        }
+    }
+}
+
+/// A guild's settings with anything it hasn't set filled in from the config file for the main
+/// guild, or left empty for the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuildSettings {
+    pub guild_id: GuildId,
+    pub attachment_logging_channel_id: Option<ChannelId>,
+    pub reaction_logging_channel_id: Option<ChannelId>,
+    pub command_audit_channel_id: Option<ChannelId>,
+    pub admin_role_ids: Vec<RoleId>,
+    pub disabled_modules: Vec<GuildModule>,
+    pub bloxlink_guild_id: GuildId,
+}
+
+impl GuildSettings {
+    fn defaults(guild_id: GuildId, config: &Config) -> Self {
+        if guild_id != config.main.guild_id {
+            return GuildSettings {
+                guild_id,
+                attachment_logging_channel_id: None,
+                reaction_logging_channel_id: None,
+                command_audit_channel_id: None,
+                admin_role_ids: Vec::new(),
+                disabled_modules: Vec::new(),
+                bloxlink_guild_id: guild_id,
+            };
+        }
+
+        let logging = &config.modules.logging;
+        GuildSettings {
+            guild_id,
+            attachment_logging_channel_id: Some(logging.attachment_logging_channel_id),
+            reaction_logging_channel_id: Some(logging.reaction_logging_channel_id),
+            command_audit_channel_id: logging.command_audit_channel_id,
+            admin_role_ids: config.main.admin_role_ids.clone(),
+            disabled_modules: Vec::new(),
+            bloxlink_guild_id: guild_id,
+        }
+    }
+
+    pub fn is_enabled(&self, module: GuildModule) -> bool {
+        !self.disabled_modules.contains(&module)
+    }
+
+    /// Where the module logs to, if it's enabled and has a channel.
+    pub fn log_channel(&self, module: GuildModule) -> Option<ChannelId> {
+        if !self.is_enabled(module) {
+            return None;
+        }
+        match module {
+            GuildModule::AttachmentLogging => self.attachment_logging_channel_id,
+            GuildModule::ReactionLogging => self.reaction_logging_channel_id,
+            GuildModule::VideoConversion => None,
+            GuildModule::CommandAudit => self.command_audit_channel_id,
+        }
+    }
+}
+
+/// Settings for every guild the bot serves, one tree per guild holding a key per setting that
+/// was changed from its default. `guilds` lists the guilds that have been added.
+#[derive(Clone)]
+pub struct GuildSettingsDB {
+    db: Arc<Db>,
+    guilds: Tree,
+}
+
+fn serialize<T: Serialize>(value: &T) -> sled::Result<Vec<u8>> {
+    bincode::serialize(value)
+        .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))
+}
+
+fn read<T: DeserializeOwned>(tree: &Tree, key: &str) -> sled::Result<Option<T>> {
+    match tree.get(key)? {
+        Some(value) => {
+            let value = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+impl GuildSettingsDB {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = GuildSettingsDB {
+            guilds: db.open_tree("guilds")?,
+            db: Arc::clone(&db),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// The guild's settings tree, adding the guild if it's new.
+    fn tree(&self, guild_id: GuildId) -> sled::Result<Tree> {
+        self.add_guild(guild_id)?;
+        self.db
+            .open_tree(format!("{}{}", GUILD_TREE_PREFIX, guild_id))
+    }
+
+    /// Starts serving a guild with default settings.
+    pub fn add_guild(&self, guild_id: GuildId) -> sled::Result<()> {
+        self.guilds.insert(guild_id.get().to_be_bytes(), &[])?;
+        Ok(())
+    }
+
+    /// Every guild that has been added, which doesn't include the main guild unless its settings
+    /// were changed.
+    pub fn guilds(&self) -> sled::Result<Vec<GuildId>> {
+        let mut guilds = Vec::new();
+        for result in self.guilds.iter() {
+            let (key, _) = result?;
+            let guild_id = u64::from_be_bytes(
+                key.as_ref()
+                    .try_into()
+                    .map_err(|_| sled::Error::Io(std::io::Error::other("Invalid guild key")))?,
+            );
+            guilds.push(GuildId::new(guild_id));
+        }
+        Ok(guilds)
+    }
+
+    pub fn get(&self, guild_id: GuildId, config: &Config) -> sled::Result<GuildSettings> {
+        let mut settings = GuildSettings::defaults(guild_id, config);
+        if !self.guilds.contains_key(guild_id.get().to_be_bytes())? {
+            return Ok(settings);
+        }
+        let tree = self.tree(guild_id)?;
+
+        if let Some(channel_id) = read::<u64>(&tree, ATTACHMENT_LOGGING_CHANNEL_KEY)? {
+            settings.attachment_logging_channel_id = Some(ChannelId::new(channel_id));
+        }
+        if let Some(channel_id) = read::<u64>(&tree, REACTION_LOGGING_CHANNEL_KEY)? {
+            settings.reaction_logging_channel_id = Some(ChannelId::new(channel_id));
+        }
+        if let Some(channel_id) = read::<u64>(&tree, COMMAND_AUDIT_CHANNEL_KEY)? {
+            settings.command_audit_channel_id = Some(ChannelId::new(channel_id));
+        }
+        if let Some(role_ids) = read::<Vec<u64>>(&tree, ADMIN_ROLES_KEY)? {
+            settings.admin_role_ids = role_ids.into_iter().map(RoleId::new).collect();
+        }
+        if let Some(modules) = read(&tree, DISABLED_MODULES_KEY)? {
+            settings.disabled_modules = modules;
+        }
+        if let Some(bloxlink_guild_id) = read::<u64>(&tree, BLOXLINK_GUILD_KEY)? {
+            settings.bloxlink_guild_id = GuildId::new(bloxlink_guild_id);
+        }
+
+        Ok(settings)
+    }
+
+    /// Settings for wherever an event happened, using the main guild's outside of guilds.
+    pub fn get_or_main(
+        &self,
+        guild_id: Option<GuildId>,
+        config: &Config,
+    ) -> sled::Result<GuildSettings> {
+        self.get(guild_id.unwrap_or(config.main.guild_id), config)
+    }
+
+    /// Sets the module's log channel, `None` going back to the default. Returns false for modules
+    /// that don't log.
+    pub fn set_log_channel(
+        &self,
+        guild_id: GuildId,
+        module: GuildModule,
+        channel_id: Option<ChannelId>,
+    ) -> sled::Result<bool> {
+        let Some(key) = module.log_channel_key() else {
+            return Ok(false);
+        };
+        let tree = self.tree(guild_id)?;
+        match channel_id {
+            Some(channel_id) => tree.insert(key, serialize(&channel_id.get())?)?,
+            None => tree.remove(key)?,
+        };
+        Ok(true)
+    }
+
+    pub fn set_admin_roles(&self, guild_id: GuildId, role_ids: &[RoleId]) -> sled::Result<()> {
+        let role_ids: Vec<u64> = role_ids.iter().map(|role_id| role_id.get()).collect();
+        self.tree(guild_id)?
+            .insert(ADMIN_ROLES_KEY, serialize(&role_ids)?)?;
+        Ok(())
+    }
+
+    pub fn set_module_enabled(
+        &self,
+        guild_id: GuildId,
+        module: GuildModule,
+        enabled: bool,
+    ) -> sled::Result<()> {
+        let tree = self.tree(guild_id)?;
+        let mut disabled: Vec<GuildModule> = read(&tree, DISABLED_MODULES_KEY)?.unwrap_or_default();
+        disabled.retain(|disabled_module| *disabled_module != module);
+        if !enabled {
+            disabled.push(module);
+        }
+        tree.insert(DISABLED_MODULES_KEY, serialize(&disabled)?)?;
+        Ok(())
+    }
+
+    pub fn set_bloxlink_guild(
+        &self,
+        guild_id: GuildId,
+        bloxlink_guild_id: Option<GuildId>,
+    ) -> sled::Result<()> {
+        let tree = self.tree(guild_id)?;
+        match bloxlink_guild_id {
+            Some(bloxlink_guild_id) => {
+                tree.insert(BLOXLINK_GUILD_KEY, serialize(&bloxlink_guild_id.get())?)?
+            }
+            None => tree.remove(BLOXLINK_GUILD_KEY)?,
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_modules::config::tests::VALID;
+
+    #[test]
+    fn test_settings_fall_back_to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = GuildSettingsDB::init(dir.path().to_str().unwrap()).unwrap();
+        let config = Config::parse(VALID).ok().unwrap();
+        let main_guild = config.main.guild_id;
+        let appeals = GuildId::new(5000);
+
+        let main = db.get(main_guild, &config).unwrap();
+        assert_eq!(main.admin_role_ids, config.main.admin_role_ids);
+        assert_eq!(
+            main.log_channel(GuildModule::ReactionLogging),
+            Some(ChannelId::new(3002))
+        );
+        let other = db.get(appeals, &config).unwrap();
+        assert_eq!(other.log_channel(GuildModule::AttachmentLogging), None);
+        assert_eq!(other.bloxlink_guild_id, appeals);
+        assert!(db.guilds().unwrap().is_empty());
+
+        db.add_guild(appeals).unwrap();
+        db.set_log_channel(
+            appeals,
+            GuildModule::AttachmentLogging,
+            Some(ChannelId::new(6000)),
+        )
+        .unwrap();
+        db.set_admin_roles(appeals, &[RoleId::new(7000)]).unwrap();
+        db.set_bloxlink_guild(appeals, Some(main_guild)).unwrap();
+        db.set_module_enabled(main_guild, GuildModule::ReactionLogging, false)
+            .unwrap();
+
+        let other = db.get(appeals, &config).unwrap();
+        assert_eq!(
+            other.log_channel(GuildModule::AttachmentLogging),
+            Some(ChannelId::new(6000))
+        );
+        assert_eq!(other.admin_role_ids, vec![RoleId::new(7000)]);
+        assert_eq!(other.bloxlink_guild_id, main_guild);
+        let main = db.get(main_guild, &config).unwrap();
+        assert!(!main.is_enabled(GuildModule::ReactionLogging));
+        assert_eq!(main.log_channel(GuildModule::ReactionLogging), None);
+
+        assert_eq!(db.guilds().unwrap(), vec![main_guild, appeals]);
+    }
+}
@@ -1,51 +1,97 @@
 #![allow(nonstandard_style)]
 use crate::Data;
-use regex::Regex;
-use reqwest::Client;
 use reqwest::header::HeaderValue;
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde_json::Value;
-use serenity::all::{CreateEmbed, CreateEmbedFooter};
+use serenity::all::{CreateEmbed, CreateEmbedFooter, GuildId};
 use std::collections::HashMap;
-use std::str::FromStr;
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{Span, debug, field, instrument};
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::{CONFIG, UserId};
+use super::UserId;
+use super::error::BotError;
+use super::metrics::METRICS;
 use std::fmt::Write;
 
+/// The server Bloxlink links are looked up in, and the key to do it with.
+#[derive(Clone)]
+pub struct Bloxlink {
+    pub api_key: String,
+    pub guild_id: GuildId,
+}
+
+/// Bloxlink lookups for a command run in `guild_id`, going by the guild's settings.
+pub fn bloxlink_for(framework_data: &Data, guild_id: Option<GuildId>) -> Bloxlink {
+    let config = framework_data.config.get();
+    let bloxlink_guild_id = guild_id
+        .and_then(|guild_id| framework_data.guild_settings.get(guild_id, &config).ok())
+        .map_or(config.main.guild_id, |settings| settings.bloxlink_guild_id);
+    Bloxlink {
+        api_key: config.main.bloxlink_local_api_key.clone(),
+        guild_id: bloxlink_guild_id,
+    }
+}
+
+impl Bloxlink {
+    /// A `lookup` request for `id`, like `discord-to-roblox`, ready to send.
+    pub fn request(
+        &self,
+        reqwest_client: &Client,
+        lookup: &str,
+        id: impl fmt::Display,
+    ) -> Result<RequestBuilder, BotError> {
+        let api_key = self
+            .api_key
+            .parse::<HeaderValue>()
+            .map_err(|_| BotError::bloxlink("the API key isn't a valid header value"))?;
+        let url = format!(
+            "https://api.blox.link/v4/public/guilds/{}/{}/{}",
+            self.guild_id, lookup, id
+        );
+        Ok(reqwest_client.get(url).header("Authorization", api_key))
+    }
+
+    #[instrument(name = "bloxlink", skip(self, reqwest_client, id), fields(%id, status = field::Empty))]
+    async fn get(
+        &self,
+        reqwest_client: &Client,
+        lookup: &str,
+        id: impl fmt::Display,
+    ) -> Result<reqwest::Response, BotError> {
+        let response = self.request(reqwest_client, lookup, id)?.send().await;
+        METRICS.api_request(
+            "Bloxlink",
+            lookup,
+            response.as_ref().ok().map(|response| response.status().as_u16()),
+        );
+        let response = response.map_err(BotError::bloxlink)?;
+        Span::current().record("status", response.status().as_u16());
+        debug!("Bloxlink responded");
+        Ok(response)
+    }
+}
+
 pub async fn discord_id_to_roblox_id(
     reqwest_client: &Client,
+    bloxlink: &Bloxlink,
     discord_id: UserId,
-) -> Result<String, String> {
-    let quote_regex = Regex::new("/\"/gi").expect("regex err");
-    let bloxlink_api_key: HeaderValue = CONFIG
-        .main
-        .bloxlink_local_api_key // bloxlink moment
-        .parse::<HeaderValue>()
-        .expect("err");
-
-    let url = format!(
-        "https://api.blox.link/v4/public/guilds/{}/discord-to-roblox/{}",
-        CONFIG.main.guild_id, discord_id
-    );
-    let response = reqwest_client
-        .get(url)
-        .header("Authorization", bloxlink_api_key)
-        .send()
-        .await
-        .expect("??");
-    if response.status() != reqwest::StatusCode::OK {
-        Err(format!(
-            "Something went wrong attempting to get Bloxlink data for user `{}`. They might not be verified with Bloxlink.",
-            discord_id
-        ))
-    } else {
-        let serialized_json: Value =
-            serde_json::from_str(response.text().await.expect("err").as_str()).expect("err");
-        Ok(quote_regex
-            .replace(serialized_json["robloxID"].as_str().unwrap(), "")
-            .to_string())
+) -> Result<u64, BotError> {
+    let response = bloxlink
+        .get(reqwest_client, "discord-to-roblox", discord_id)
+        .await?;
+    match response.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => return Err(BotError::NotVerified(discord_id)),
+        status => return Err(BotError::bloxlink(format!("discord-to-roblox returned {}", status))),
     }
+
+    let body: Value = response.json().await.map_err(BotError::bloxlink)?;
+    body["robloxID"]
+        .as_str()
+        .and_then(|roblox_id| roblox_id.parse().ok())
+        .ok_or_else(|| BotError::bloxlink(format!("no Roblox id in {}", body)))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -56,39 +102,50 @@ struct ReverseLookupResponse {
 
 pub async fn roblox_id_to_discord_ids(
     reqwest_client: &Client,
+    bloxlink: &Bloxlink,
     roblox_id: String,
-) -> Result<Vec<String>, String> {
-    let bloxlink_api_key: HeaderValue = CONFIG
-        .main
-        .bloxlink_local_api_key
-        .parse::<HeaderValue>()
-        .expect("err");
+) -> Result<Vec<String>, BotError> {
+    let response = bloxlink
+        .get(reqwest_client, "roblox-to-discord", roblox_id)
+        .await?;
+    if response.status() != StatusCode::OK {
+        return Err(BotError::bloxlink(format!(
+            "roblox-to-discord returned {}",
+            response.status()
+        )));
+    }
 
-    let url = format!(
-        "https://api.blox.link/v4/public/guilds/{}/roblox-to-discord/{}",
-        CONFIG.main.guild_id, roblox_id
-    );
-    let response = reqwest_client
-        .get(url)
-        .header("Authorization", bloxlink_api_key)
-        .send()
+    Ok(response
+        .json::<ReverseLookupResponse>()
         .await
-        .expect("??");
-    if response.status() != reqwest::StatusCode::OK {
-        Err(format!(
-            "Something went wrong attempting to get Bloxlink data for user `{}`. They might not be verified with Bloxlink.",
-            roblox_id
-        ))
-    } else {
-        Ok(response
-            .json::<ReverseLookupResponse>()
-            .await
-            .unwrap()
-            .discord_IDs)
+        .map_err(BotError::bloxlink)?
+        .discord_IDs)
+}
+
+/// Fetches JSON from a Roblox web API.
+#[instrument(name = "roblox", skip(reqwest_client), fields(status = field::Empty))]
+async fn roblox_get(reqwest_client: &Client, url: &str) -> Result<Value, BotError> {
+    let response = reqwest_client.get(url).send().await;
+    // Roblox urls carry ids, so they're counted by host to keep the number of series down.
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    METRICS.api_request(
+        "Roblox",
+        &host,
+        response.as_ref().ok().map(|response| response.status().as_u16()),
+    );
+    let response = response.map_err(BotError::roblox)?;
+    Span::current().record("status", response.status().as_u16());
+    debug!("Roblox responded");
+    if !response.status().is_success() {
+        return Err(BotError::roblox(format!("{} returned {}", url, response.status())));
     }
+    response.json().await.map_err(BotError::roblox)
 }
 
-pub async fn duration_conversion(duration_string: String) -> Result<(u64, u64, String), String> {
+pub async fn duration_conversion(duration_string: String) -> Result<(u64, u64, String), BotError> {
     let mut date_map = HashMap::new();
     date_map.insert("s", (1, "Second"));
     date_map.insert("h", (3600, "Hour"));
@@ -103,10 +160,10 @@ pub async fn duration_conversion(duration_string: String) -> Result<(u64, u64, S
     let mut unix_total = 0;
     let mut final_string = String::new();
     if duration_list.is_empty() {
-        return Err(format!(
+        return Err(BotError::InvalidInput(format!(
             "Something went wrong parsing duration string `{}`.",
             duration_string
-        ));
+        )));
     } else {
         for duration in duration_list.clone() {
             let chars = duration.chars();
@@ -118,18 +175,23 @@ pub async fn duration_conversion(duration_string: String) -> Result<(u64, u64, S
             {
                 Ok(amount) => amount,
                 Err(_) => {
-                    return Err(format!(
+                    return Err(BotError::InvalidInput(format!(
                         "Something went wrong parsing duration string `{}`.",
                         duration_string
-                    ));
+                    )));
                 }
             };
-            let identifier = chars.last().expect("err");
+            let Some(identifier) = chars.last() else {
+                return Err(BotError::InvalidInput(format!(
+                    "Something went wrong parsing duration string `{}`.",
+                    duration_string
+                )));
+            };
             if !date_map.contains_key(identifier.to_string().as_str()) {
-                return Err(format!(
+                return Err(BotError::InvalidInput(format!(
                     "Something went wrong parsing duration string `{}`.",
                     duration_string
-                ));
+                )));
             }
             let mut name = date_map[&identifier.to_string().as_str()].1.to_string();
             if amount > 1 {
@@ -239,7 +301,7 @@ pub async fn badge_data(
     reqwest_client: &Client,
     roblox_id: String,
     badge_iterations: i64,
-) -> Result<(i64, f64, String), String> {
+) -> Result<(i64, f64, String), BotError> {
     let badge_count = Arc::new(Mutex::new(0));
     let total_win_rate = Arc::new(Mutex::new(0.0));
     let awarders = Arc::new(Mutex::new(IndexMap::new()));
@@ -269,26 +331,9 @@ pub async fn badge_data(
                         }
                     );
 
-                    let response = reqwest_client
-                        .get(&url)
-                        .send()
-                        .await
-                        .map_err(|e| format!("Request failed: {}", e))?;
-
-                    if !response.status().is_success() {
-                        return Err(format!("Request failed with status: {}", response.status()));
-                    }
-
-                    let text = response
-                        .text()
-                        .await
-                        .map_err(|e| format!("Failed to get response text: {}", e))?;
-
-                    let json: Value = serde_json::from_str(&text)
-                        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-
-                    let badge_response: BadgeResponse = serde_json::from_value(json)
-                        .map_err(|e| format!("Failed to deserialize BadgeResponse: {}", e))?;
+                    let json = roblox_get(reqwest_client, &url).await?;
+                    let badge_response: BadgeResponse =
+                        serde_json::from_value(json).map_err(BotError::roblox)?;
 
                     let mut badge_count = badge_count.lock().await;
                     *badge_count += badge_response.data.len() as i64;
@@ -349,125 +394,228 @@ pub async fn badge_data(
     Ok((badge_count, win_rate, awarders_string))
 }
 
+/// How many entries the `data` array of a Roblox API response has.
+async fn roblox_data_count(reqwest_client: &Client, url: &str) -> Result<usize, BotError> {
+    let body = roblox_get(reqwest_client, url).await?;
+    Ok(body["data"]
+        .as_array()
+        .ok_or_else(|| BotError::roblox(format!("{} didn't return a data array", url)))?
+        .len())
+}
+
 pub async fn roblox_friend_count(
     reqwest_client: &Client,
     roblox_id: &str,
-) -> Result<usize, String> {
+) -> Result<usize, BotError> {
     let url = format!("https://friends.roblox.com/v1/users/{}/friends", roblox_id);
-    let response = reqwest_client.get(&url).send().await.unwrap();
-    let response_text = response.text().await.unwrap();
-
-    let parsed_json: Value = serde_json::from_str(&response_text).unwrap();
-
-    Ok(parsed_json["data"]
-        .as_array()
-        .ok_or_else(|| "Data is not an array".to_string())?
-        .len())
+    roblox_data_count(reqwest_client, &url).await
 }
 
-pub async fn roblox_group_count(reqwest_client: &Client, roblox_id: &str) -> Result<usize, String> {
+pub async fn roblox_group_count(
+    reqwest_client: &Client,
+    roblox_id: &str,
+) -> Result<usize, BotError> {
     let url = format!(
         "https://groups.roblox.com/v2/users/{}/groups/roles?includeLocked=true",
         roblox_id
     );
-    let response = reqwest_client.get(&url).send().await.unwrap();
-    let response_text = response.text().await.unwrap();
+    roblox_data_count(reqwest_client, &url).await
+}
 
-    let parsed_json: Value = serde_json::from_str(&response_text).unwrap();
+/// What a user input turned out to be. Discord users only become `Linked` when the
+/// resolver was asked to look them up on Bloxlink and they are verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserIdentity {
+    Discord(UserId),
+    Roblox { id: u64, username: String },
+    Linked { discord_id: UserId, roblox_id: u64 },
+}
 
-    Ok(parsed_json["data"]
-        .as_array()
-        .ok_or_else(|| "Data is not an array".to_string())?
-        .len())
+impl UserIdentity {
+    pub fn roblox_id(&self) -> Option<u64> {
+        match self {
+            UserIdentity::Roblox { id, .. } => Some(*id),
+            UserIdentity::Linked { roblox_id, .. } => Some(*roblox_id),
+            UserIdentity::Discord(_) => None,
+        }
+    }
 }
 
-pub async fn merge_types(
+#[derive(Debug, Clone)]
+pub struct ResolvedUser {
+    pub input: String,
+    pub identity: UserIdentity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveErrorKind {
+    InvalidId,
+    RobloxLookupFailed(String),
+    RobloxUserNotFound,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub input: String,
+    pub kind: ResolveErrorKind,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ResolveErrorKind::InvalidId => {
+                write!(f, "Couldn't turn {} into a Discord or Roblox id.", self.input)
+            }
+            ResolveErrorKind::RobloxLookupFailed(details) => write!(
+                f,
+                "Couldn't find user details for {}, details:\n{}",
+                self.input, details
+            ),
+            ResolveErrorKind::RobloxUserNotFound => {
+                write!(f, "No Roblox user was found for {}.", self.input)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+#[derive(Debug, PartialEq, Eq)]
+enum UserInput {
+    DiscordId(u64),
+    RobloxId(u64),
+    Username(String),
+}
+
+/// Discord snowflakes are at least 17 digits long while Roblox ids are shorter, mentions are
+/// always Discord users.
+fn classify_input(input: &str) -> Option<UserInput> {
+    if let Some(mention) = input.strip_prefix("<@").and_then(|s| s.strip_suffix('>')) {
+        return mention
+            .trim_start_matches('!')
+            .parse::<u64>()
+            .ok()
+            .filter(|id| *id != 0)
+            .map(UserInput::DiscordId);
+    }
+
+    if !input.is_empty() && input.chars().all(|c| c.is_ascii_digit()) {
+        let id = input.parse::<u64>().ok().filter(|id| *id != 0)?;
+        if input.len() >= 17 {
+            Some(UserInput::DiscordId(id))
+        } else {
+            Some(UserInput::RobloxId(id))
+        }
+    } else {
+        Some(UserInput::Username(input.to_string()))
+    }
+}
+
+/// Resolves every input into a Discord user, Roblox user or (with `link_discord`) a
+/// Bloxlink-linked pair, keeping the original input alongside each result.
+pub async fn split_types(
     reqwest_client: &Client,
     rbx_client: &roboat::Client,
+    bloxlink: &Bloxlink,
     users: Vec<String>,
-) -> (Vec<String>, Vec<String>) {
-    let mut roblox_ids: Vec<String> = Vec::new();
-    let mut errors_vector: Vec<String> = Vec::new();
-
-    for user in users {
-        if user.len() >= 17 && user.chars().all(|c| c.is_ascii_digit()) {
-            let discord_id = match UserId::from_str(user.as_str()) {
-                Ok(id) => id,
-                Err(err) => {
-                    errors_vector.push(format!("Couldn't find turn discord id string into actual discord id for {}, details:\n{}", user, err));
-                    continue;
-                }
-            };
-            let roblox_id_str =
-                match self::discord_id_to_roblox_id(reqwest_client, discord_id).await {
-                    Ok(id) => id,
-                    Err(err) => {
-                        errors_vector.push(format!(
-                            "Couldn't find turn discord id into roblox id for {}, details:\n{}",
-                            user, err
-                        ));
-                        continue;
+    link_discord: bool,
+) -> Vec<Result<ResolvedUser, ResolveError>> {
+    let mut results = Vec::new();
+
+    for input in users.into_iter().filter(|input| !input.is_empty()) {
+        let error = |kind| ResolveError {
+            input: input.clone(),
+            kind,
+        };
+        let identity = match classify_input(&input) {
+            None => Err(error(ResolveErrorKind::InvalidId)),
+            Some(UserInput::DiscordId(id)) => {
+                let discord_id = UserId::new(id);
+                if !link_discord {
+                    Ok(UserIdentity::Discord(discord_id))
+                } else {
+                    match discord_id_to_roblox_id(reqwest_client, bloxlink, discord_id).await {
+                        Ok(roblox_id) => Ok(UserIdentity::Linked {
+                            discord_id,
+                            roblox_id,
+                        }),
+                        Err(_) => Ok(UserIdentity::Discord(discord_id)),
                     }
-                };
-            roblox_ids.push(roblox_id_str)
-        } else if user.len() < 17 && user.chars().all(|c| c.is_ascii_digit()) {
-            let user_search = match rbx_client
-                .username_user_details(vec![user.clone()], false)
-                .await
-            {
-                Ok(id) => id,
-                Err(err) => {
-                    errors_vector.push(format!(
-                        "Couldn't find user details for {}, details:\n{}",
-                        user, err
-                    ));
-                    continue;
                 }
-            };
-            roblox_ids.push(user);
-            for details in user_search {
-                roblox_ids.push(details.id.to_string())
             }
-        } else if !user.chars().all(|c| c.is_ascii_digit()) {
-            let user_search = match rbx_client
-                .username_user_details(vec![user.clone()], false)
-                .await
-            {
-                Ok(id) => id,
-                Err(err) => {
-                    errors_vector.push(format!(
-                        "Couldn't find user details for {}, details:\n{}",
-                        user, err
-                    ));
-                    continue;
+            Some(UserInput::RobloxId(id)) => match rbx_client.user_details(id).await {
+                Ok(details) => Ok(UserIdentity::Roblox {
+                    id: details.id,
+                    username: details.username,
+                }),
+                Err(err) => Err(error(ResolveErrorKind::RobloxLookupFailed(err.to_string()))),
+            },
+            Some(UserInput::Username(username)) => {
+                match rbx_client
+                    .username_user_details(vec![username], false)
+                    .await
+                {
+                    Ok(details) => match details.into_iter().next() {
+                        Some(details) => Ok(UserIdentity::Roblox {
+                            id: details.id,
+                            username: details.username,
+                        }),
+                        None => Err(error(ResolveErrorKind::RobloxUserNotFound)),
+                    },
+                    Err(err) => Err(error(ResolveErrorKind::RobloxLookupFailed(err.to_string()))),
                 }
-            };
-            for details in user_search {
-                roblox_ids.push(details.id.to_string())
             }
+        };
+
+        results.push(identity.map(|identity| ResolvedUser { input, identity }));
+    }
+
+    results
+}
+
+/// Turns every input into a Roblox id, going through Bloxlink for Discord ids.
+pub async fn merge_types(
+    reqwest_client: &Client,
+    rbx_client: &roboat::Client,
+    bloxlink: &Bloxlink,
+    users: Vec<String>,
+) -> (Vec<String>, Vec<String>) {
+    let mut roblox_ids: Vec<String> = Vec::new();
+    let mut errors_vector: Vec<String> = Vec::new();
+
+    for result in split_types(reqwest_client, rbx_client, bloxlink, users, true).await {
+        match result {
+            Ok(user) => match user.identity.roblox_id() {
+                Some(roblox_id) => roblox_ids.push(roblox_id.to_string()),
+                None => errors_vector.push(format!(
+                    "Couldn't turn discord id into roblox id for {}, they might not be verified with Bloxlink.",
+                    user.input
+                )),
+            },
+            Err(err) => errors_vector.push(err.to_string()),
         }
     }
     (roblox_ids, errors_vector)
 }
 
-pub async fn get_roblox_avatar_bust(reqwest_client: &Client, user_id: String) -> String {
-    let response = reqwest_client.get(format!("https://thumbnails.roblox.com/v1/users/avatar-bust?userIds={}&size=420x420&format=Png&isCircular=false", user_id))
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    let parsed_json: Value = serde_json::from_str(response.as_str()).unwrap();
-    parsed_json["data"].as_array().unwrap().first().unwrap()["imageUrl"]
-        .as_str()
-        .unwrap_or("")
-        .to_string()
+pub async fn get_roblox_avatar_bust(
+    reqwest_client: &Client,
+    user_id: String,
+) -> Result<String, BotError> {
+    let url = format!(
+        "https://thumbnails.roblox.com/v1/users/avatar-bust?userIds={}&size=420x420&format=Png&isCircular=false",
+        user_id
+    );
+    let body = roblox_get(reqwest_client, &url).await?;
+    let thumbnail = body["data"]
+        .get(0)
+        .ok_or_else(|| BotError::roblox(format!("no avatar bust for {}", user_id)))?;
+    // Thumbnails still being rendered or moderated don't have a url yet.
+    Ok(thumbnail["imageUrl"].as_str().unwrap_or("").to_string())
 }
 
 pub async fn new_embed_from_template(framework_data: &Data) -> CreateEmbed {
-    CreateEmbed::new().color(framework_data.bot_color).footer(
+    CreateEmbed::new().color(framework_data.config.get().main.color).footer(
         CreateEmbedFooter::new("Made by RabbyDevs, with 🦀 and ❤️.")
             .icon_url(framework_data.bot_avatar.clone()),
     )
@@ -519,6 +667,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_classify_input() {
+        assert_eq!(
+            classify_input("123456789012345678"),
+            Some(UserInput::DiscordId(123456789012345678))
+        );
+        assert_eq!(
+            classify_input("<@!123>"),
+            Some(UserInput::DiscordId(123))
+        );
+        assert_eq!(classify_input("1234567"), Some(UserInput::RobloxId(1234567)));
+        assert_eq!(
+            classify_input("Builderman"),
+            Some(UserInput::Username("Builderman".to_string()))
+        );
+        assert_eq!(classify_input("99999999999999999999999"), None);
+        assert_eq!(classify_input("<@0>"), None);
+    }
+
     #[test]
     fn test_with_only_unicode() {
         let message = "Hello 👋 🌍 world!";
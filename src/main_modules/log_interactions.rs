@@ -1,4 +1,4 @@
-use poise::SlashArgument;
+use poise::{ChoiceParameter, SlashArgument};
 use serenity::all::{
     ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow, CreateInputText,
     CreateInteractionResponse, CreateInteractionResponseMessage, CreateModal, CreateSelectMenu,
@@ -6,6 +6,10 @@ use serenity::all::{
 };
 use std::error::Error as StdError;
 
+use super::game_sanctions::unix_now;
+use super::logging_database::LogEntry;
+use super::mod_log::{ModLog, RobloxUser};
+use crate::Data;
 use crate::commands::log_module::roblox_log::RobloxInfTypes;
 
 fn build_enum_options() -> Vec<CreateSelectMenuOption> {
@@ -102,6 +106,7 @@ pub async fn handle_select_log_type(
 pub async fn handle_log_modal_submit(
     ctx: &Context,
     interaction: &ModalInteraction,
+    data: &Data,
 ) -> Result<(), Box<dyn StdError + Send + Sync>> {
     // Parse custom_id: "roblox_log_modal:{discord_id}:{username}:{roblox_id}"
     let parts: Vec<&str> = interaction.data.custom_id.split(':').collect();
@@ -110,11 +115,13 @@ pub async fn handle_log_modal_submit(
         return Err("Invalid modal custom_id format".into());
     }
 
-    let username = parts[1];
-    let roblox_id = parts[2];
+    let user = RobloxUser {
+        username: parts[1].to_string(),
+        id: parts[2].parse()?,
+    };
 
     // Extract form inputs
-    let mut action_type = parts[3];
+    let infraction = RobloxInfTypes::from_name(parts[3]).ok_or("Invalid log type in modal custom_id")?;
     let mut reason = String::new();
     let mut note = String::new();
 
@@ -130,31 +137,37 @@ pub async fn handle_log_modal_submit(
         }
     }
 
-    // Build the log message using format
-    let user_info = format!("[{}:{}]", username, roblox_id);
-
-    let note_string = if !note.is_empty() {
-        format!("\nNote: {}", note)
-    } else {
-        String::new()
+    let log = ModLog::Roblox {
+        infraction,
+        users: vec![user],
+        reason,
+        note: Some(note).filter(|note| !note.is_empty()),
     };
 
-    let log_message = format!(
-        "[{}]\n{}\n[{}]{}",
-        action_type, user_info, reason, note_string
-    );
-
     // Post the log in the same channel
     interaction
         .create_response(
             ctx,
             CreateInteractionResponse::Message(
                 CreateInteractionResponseMessage::new()
-                    .content(log_message)
+                    .content(log.to_string())
                     .ephemeral(false),
             ),
         )
         .await?;
 
+    let message = interaction.get_response(&ctx.http).await?;
+    data.logging_db.record(
+        LogEntry::from(&log)
+            .moderated_by(interaction.user.id)
+            .posted_as(&message),
+    )?;
+    for probation in data.probations.escalations(&log, unix_now())? {
+        interaction
+            .channel_id
+            .say(&ctx.http, probation.escalation_notice())
+            .await?;
+    }
+
     Ok(())
 }
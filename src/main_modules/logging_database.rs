@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{Message, UserId};
+use sled::transaction::{TransactionError, Transactional};
+use sled::{Db, Tree};
+use std::fmt;
+use std::sync::Arc;
+
+use super::mod_log::ModLog;
+
+/// Which logging command a log was produced by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogKind {
+    Discord,
+    Roblox,
+    Probation,
+    Role,
+    FalseInfraction,
+}
+
+impl fmt::Display for LogKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogKind::Discord => write!(f, "Discord"),
+            LogKind::Roblox => write!(f, "Roblox"),
+            LogKind::Probation => write!(f, "Probation"),
+            LogKind::Role => write!(f, "Role"),
+            LogKind::FalseInfraction => write!(f, "False Infraction"),
+        }
+    }
+}
+
+/// A user a log is about, either side may be unknown (e.g. unverified with Bloxlink).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSubject {
+    pub discord_id: Option<u64>,
+    pub roblox_id: Option<u64>,
+}
+
+impl LogSubject {
+    pub fn discord(discord_id: UserId) -> Self {
+        LogSubject {
+            discord_id: Some(discord_id.get()),
+            roblox_id: None,
+        }
+    }
+
+    pub fn roblox(roblox_id: u64) -> Self {
+        LogSubject {
+            discord_id: None,
+            roblox_id: Some(roblox_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub id: u64,
+    pub kind: LogKind,
+    pub log_type: String,
+    pub subjects: Vec<LogSubject>,
+    pub moderator_id: u64,
+    pub reason: String,
+    pub note: Option<String>,
+    pub channel_id: u64,
+    pub message_id: u64,
+    pub timestamp: u64,
+}
+
+impl From<&ModLog> for LogEntry {
+    fn from(log: &ModLog) -> Self {
+        LogEntry {
+            id: 0,
+            kind: log.kind(),
+            log_type: log.log_type(),
+            subjects: log.subjects(),
+            moderator_id: 0,
+            reason: log.reason().to_string(),
+            note: log.note().map(str::to_string),
+            channel_id: 0,
+            message_id: 0,
+            timestamp: 0,
+        }
+    }
+}
+
+impl LogEntry {
+    pub fn moderated_by(mut self, moderator_id: UserId) -> Self {
+        self.moderator_id = moderator_id.get();
+        self
+    }
+
+    /// Points the entry at the message the log was posted as.
+    pub fn posted_as(mut self, message: &Message) -> Self {
+        self.channel_id = message.channel_id.get();
+        self.message_id = message.id.get();
+        self.timestamp = message.timestamp.unix_timestamp() as u64;
+        self
+    }
+}
+
+/// The outcome of pushing a game sanction into the game through Open Cloud.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameActionAudit {
+    pub id: u64,
+    pub roblox_id: u64,
+    pub sanction_id: u64,
+    pub action: String,
+    pub moderator_id: u64,
+    /// `None` when the game accepted the action.
+    pub error: Option<String>,
+    pub timestamp: u64,
+}
+
+/// Persistent store of every moderation log the bot produces.
+///
+/// Entries live in the `logs` tree keyed by their id, while the `discord_index` and
+/// `roblox_index` trees hold `subject id ++ log id` keys so a user's history is a single
+/// prefix scan. `message_index` maps the message a log was posted as to its id, and
+/// `game_actions` keeps an audit trail of what was pushed into the game, keyed by
+/// `roblox id ++ audit id`.
+#[derive(Clone)]
+pub struct LoggingDB {
+    db: Arc<Db>,
+    logs: Tree,
+    discord_index: Tree,
+    roblox_index: Tree,
+    message_index: Tree,
+    game_actions: Tree,
+}
+
+fn index_key(subject_id: u64, log_id: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&subject_id.to_be_bytes());
+    key[8..].copy_from_slice(&log_id.to_be_bytes());
+    key
+}
+
+pub(super) fn flatten_transaction_error(err: TransactionError<sled::Error>) -> sled::Error {
+    match err {
+        TransactionError::Abort(err) | TransactionError::Storage(err) => err,
+    }
+}
+
+impl LoggingDB {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = LoggingDB {
+            logs: db.open_tree("logs")?,
+            discord_index: db.open_tree("discord_index")?,
+            roblox_index: db.open_tree("roblox_index")?,
+            message_index: db.open_tree("message_index")?,
+            game_actions: db.open_tree("game_actions")?,
+            db: Arc::clone(&db),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Stores a log and indexes it under all of its subjects, returning the id it was given.
+    pub fn record(&self, mut entry: LogEntry) -> sled::Result<u64> {
+        entry.id = self.db.generate_id()?;
+        let serialized = bincode::serialize(&entry)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+
+        (
+            &self.logs,
+            &self.discord_index,
+            &self.roblox_index,
+            &self.message_index,
+        )
+            .transaction(|(logs, discord_index, roblox_index, message_index)| {
+                logs.insert(entry.id.to_be_bytes().to_vec(), serialized.as_slice())?;
+                if entry.message_id != 0 {
+                    message_index.insert(
+                        entry.message_id.to_be_bytes().to_vec(),
+                        entry.id.to_be_bytes().to_vec(),
+                    )?;
+                }
+                for subject in &entry.subjects {
+                    if let Some(discord_id) = subject.discord_id {
+                        discord_index.insert(index_key(discord_id, entry.id).to_vec(), &b""[..])?;
+                    }
+                    if let Some(roblox_id) = subject.roblox_id {
+                        roblox_index.insert(index_key(roblox_id, entry.id).to_vec(), &b""[..])?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(flatten_transaction_error)?;
+
+        Ok(entry.id)
+    }
+
+    pub fn get(&self, log_id: u64) -> sled::Result<Option<LogEntry>> {
+        match self.logs.get(log_id.to_be_bytes())? {
+            Some(value) => {
+                let entry: LogEntry = bincode::deserialize(&value)
+                    .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove(&self, log_id: u64) -> sled::Result<Option<LogEntry>> {
+        let Some(entry) = self.get(log_id)? else {
+            return Ok(None);
+        };
+
+        (
+            &self.logs,
+            &self.discord_index,
+            &self.roblox_index,
+            &self.message_index,
+        )
+            .transaction(|(logs, discord_index, roblox_index, message_index)| {
+                logs.remove(log_id.to_be_bytes().to_vec())?;
+                if entry.message_id != 0 {
+                    message_index.remove(entry.message_id.to_be_bytes().to_vec())?;
+                }
+                for subject in &entry.subjects {
+                    if let Some(discord_id) = subject.discord_id {
+                        discord_index.remove(index_key(discord_id, log_id).to_vec())?;
+                    }
+                    if let Some(roblox_id) = subject.roblox_id {
+                        roblox_index.remove(index_key(roblox_id, log_id).to_vec())?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(flatten_transaction_error)?;
+
+        Ok(Some(entry))
+    }
+
+    fn scan_index(&self, index: &Tree, subject_id: u64) -> sled::Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+
+        for result in index.scan_prefix(subject_id.to_be_bytes()) {
+            let (key, _) = result?;
+            let log_id = u64::from_be_bytes(key[8..16].try_into().unwrap());
+            if let Some(entry) = self.get(log_id)? {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    pub fn get_by_discord_id(&self, discord_id: u64) -> sled::Result<Vec<LogEntry>> {
+        let mut entries = self.scan_index(&self.discord_index, discord_id)?;
+        entries.sort_by_key(|entry| entry.timestamp);
+        Ok(entries)
+    }
+
+    pub fn get_by_roblox_id(&self, roblox_id: u64) -> sled::Result<Vec<LogEntry>> {
+        let mut entries = self.scan_index(&self.roblox_index, roblox_id)?;
+        entries.sort_by_key(|entry| entry.timestamp);
+        Ok(entries)
+    }
+
+    /// Looks an id up in both indexes, oldest log first.
+    pub fn get_by_id(&self, id: u64) -> sled::Result<Vec<LogEntry>> {
+        let mut entries = self.scan_index(&self.discord_index, id)?;
+        for entry in self.scan_index(&self.roblox_index, id)? {
+            if !entries.iter().any(|existing| existing.id == entry.id) {
+                entries.push(entry);
+            }
+        }
+        entries.sort_by_key(|entry| entry.timestamp);
+        Ok(entries)
+    }
+
+    /// The log that was posted as the given message, if it has been recorded.
+    pub fn get_by_message_id(&self, message_id: u64) -> sled::Result<Option<LogEntry>> {
+        match self.message_index.get(message_id.to_be_bytes())? {
+            Some(log_id) => self.get(u64::from_be_bytes(log_id.as_ref().try_into().unwrap())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn record_game_action(&self, mut audit: GameActionAudit) -> sled::Result<u64> {
+        audit.id = self.db.generate_id()?;
+        let serialized = bincode::serialize(&audit)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+        self.game_actions
+            .insert(index_key(audit.roblox_id, audit.id), serialized)?;
+        Ok(audit.id)
+    }
+
+    /// Every game action pushed for the player, oldest first.
+    pub fn get_game_actions(&self, roblox_id: u64) -> sled::Result<Vec<GameActionAudit>> {
+        let mut audits = Vec::new();
+
+        for result in self.game_actions.scan_prefix(roblox_id.to_be_bytes()) {
+            let (_, value) = result?;
+            let audit: GameActionAudit = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            audits.push(audit);
+        }
+
+        Ok(audits)
+    }
+
+    pub fn len(&self) -> usize {
+        self.logs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.logs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::log_module::discord_log::DiscordInfTypes;
+    use crate::commands::log_module::probation_log::ProbationTypes;
+    use crate::commands::log_module::roblox_log::RobloxInfTypes;
+    use crate::main_modules::mod_log::{LogUser, RobloxUser};
+
+    fn test_db() -> (tempfile::TempDir, LoggingDB) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LoggingDB::init(dir.path().to_str().unwrap()).unwrap();
+        (dir, db)
+    }
+
+    fn builderman() -> RobloxUser {
+        RobloxUser {
+            username: "builderman".to_string(),
+            id: 42,
+        }
+    }
+
+    #[test]
+    fn test_lookup_by_either_id() {
+        let (_dir, db) = test_db();
+        let moderator = UserId::new(1);
+        let discord_ban = ModLog::Discord {
+            infraction: DiscordInfTypes::Ban,
+            users: vec![LogUser::Linked {
+                discord_id: UserId::new(123456789012345678),
+                roblox: Some(builderman()),
+            }],
+            reason: "first".to_string(),
+            note: None,
+        };
+        let game_ban = ModLog::Roblox {
+            infraction: RobloxInfTypes::Ban,
+            users: vec![builderman()],
+            reason: "second".to_string(),
+            note: None,
+        };
+
+        let first = db
+            .record(LogEntry::from(&discord_ban).moderated_by(moderator))
+            .unwrap();
+        let second = db
+            .record(LogEntry::from(&game_ban).moderated_by(moderator))
+            .unwrap();
+
+        let by_discord = db.get_by_discord_id(123456789012345678).unwrap();
+        assert_eq!(
+            by_discord.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![first]
+        );
+
+        let by_roblox = db.get_by_roblox_id(42).unwrap();
+        assert_eq!(
+            by_roblox.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+
+        assert!(db.get_by_id(7).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_remove_clears_indexes() {
+        let (_dir, db) = test_db();
+        let probation = ModLog::Probation {
+            infraction: ProbationTypes::RobloxBan,
+            user: LogUser::Linked {
+                discord_id: UserId::new(5),
+                roblox: None,
+            },
+            reason: "reason".to_string(),
+            duration: "1 week".to_string(),
+        };
+        let id = db.record(LogEntry::from(&probation)).unwrap();
+
+        let entry = db.get(id).unwrap().unwrap();
+        assert_eq!(entry.note.as_deref(), Some("1 week"));
+        assert_eq!(entry.subjects[0].roblox_id, None);
+
+        db.remove(id).unwrap();
+        assert!(db.get_by_discord_id(5).unwrap().is_empty());
+        assert!(db.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use sled::transaction::{ConflictableTransactionError, Transactional};
+use sled::{Db, IVec, Tree};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::warn;
+
+use super::logging_database::flatten_transaction_error;
+
+/// Masks shipped in `.default_masks`, by name and file.
+pub const DEFAULT_MASKS: [(&str, &str); 2] = [
+    ("esm Bot Style", "EsmBotStyle.png"),
+    ("RON Bot Style", "RONBotStyle.png"),
+];
+/// What `/media speechbubble` uses when no style is picked.
+pub const DEFAULT_MASK: &str = DEFAULT_MASKS[0].0;
+
+/// A speech bubble overlay and how it's applied unless the command says otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mask {
+    pub name: String,
+    /// How much of the input's height the overlay covers, from 0 to 1.
+    pub height_ratio: f32,
+    /// Whether the overlay is flipped horizontally.
+    pub flip: bool,
+    /// `0` for the masks shipped with the bot.
+    pub added_by: u64,
+}
+
+/// Masks keyed by their lowercased name, with their overlay PNGs under the same keys in
+/// `images`.
+#[derive(Clone)]
+pub struct MaskLibrary {
+    db: Arc<Db>,
+    masks: Tree,
+    images: Tree,
+}
+
+fn mask_key(name: &str) -> Vec<u8> {
+    name.trim().to_lowercase().into_bytes()
+}
+
+impl MaskLibrary {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let library = MaskLibrary {
+            masks: db.open_tree("masks")?,
+            images: db.open_tree("images")?,
+            db: Arc::clone(&db),
+        };
+
+        Ok(library)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn size_on_disk(&self) -> sled::Result<u64> {
+        self.db.size_on_disk()
+    }
+
+    /// Adds the `DEFAULT_MASKS` found in `dir` that the library doesn't have yet.
+    pub fn add_defaults(&self, dir: &Path) -> sled::Result<()> {
+        for (name, file) in DEFAULT_MASKS {
+            if self.get(name)?.is_some() {
+                continue;
+            }
+            let png = match fs::read(dir.join(file)) {
+                Ok(png) => png,
+                Err(err) => {
+                    warn!(file, error = %err, "Couldn't read a default mask");
+                    continue;
+                }
+            };
+            let mask = Mask {
+                name: name.to_string(),
+                height_ratio: 0.2,
+                flip: false,
+                added_by: 0,
+            };
+            self.add(&mask, &png)?;
+        }
+        Ok(())
+    }
+
+    /// Stores a mask and its overlay, returning false without changing anything if there's
+    /// already one by that name. A record missing its overlay doesn't count, and is replaced.
+    pub fn add(&self, mask: &Mask, png: &[u8]) -> sled::Result<bool> {
+        let key = mask_key(&mask.name);
+        let serialized = bincode::serialize(mask)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+        (&self.masks, &self.images)
+            .transaction(|(masks, images)| {
+                if masks.get(&key)?.is_some() && images.get(&key)?.is_some() {
+                    return Ok(false);
+                }
+                masks.insert(key.as_slice(), serialized.as_slice())?;
+                images.insert(key.as_slice(), png)?;
+                Ok::<_, ConflictableTransactionError<sled::Error>>(true)
+            })
+            .map_err(flatten_transaction_error)
+    }
+
+    /// The mask called `name`, whatever its case, with its overlay PNG.
+    pub fn get(&self, name: &str) -> sled::Result<Option<(Mask, IVec)>> {
+        let key = mask_key(name);
+        let (Some(mask), Some(png)) = (self.masks.get(&key)?, self.images.get(&key)?) else {
+            return Ok(None);
+        };
+        let mask: Mask = bincode::deserialize(&mask)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+        Ok(Some((mask, png)))
+    }
+
+    /// The names of the masks containing `partial`, in alphabetical order.
+    pub fn names_matching(&self, partial: &str) -> sled::Result<Vec<String>> {
+        let partial = partial.trim().to_lowercase();
+        let mut names = Vec::new();
+        for result in self.masks.iter() {
+            let (key, value) = result?;
+            if !String::from_utf8_lossy(&key).contains(&partial)
+                || !self.images.contains_key(&key)?
+            {
+                continue;
+            }
+            let mask: Mask = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            names.push(mask.name);
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(name: &str) -> Mask {
+        Mask {
+            name: name.to_string(),
+            height_ratio: 0.3,
+            flip: true,
+            added_by: 1,
+        }
+    }
+
+    #[test]
+    fn test_add_and_find_masks() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = MaskLibrary::init(dir.path().join("db").to_str().unwrap()).unwrap();
+
+        assert!(library.add(&mask("Wide Bubble"), b"wide").unwrap());
+        assert!(!library.add(&mask("wide bubble"), b"other").unwrap());
+        assert!(library.add(&mask("Thought"), b"thought").unwrap());
+
+        let (found, png) = library.get("WIDE BUBBLE").unwrap().unwrap();
+        assert_eq!(found, mask("Wide Bubble"));
+        assert_eq!(png.as_ref(), b"wide");
+        assert!(library.get("Missing").unwrap().is_none());
+
+        assert_eq!(
+            library.names_matching("").unwrap(),
+            ["Thought", "Wide Bubble"]
+        );
+        assert_eq!(library.names_matching("BUB").unwrap(), ["Wide Bubble"]);
+
+        fs::write(dir.path().join(DEFAULT_MASKS[0].1), b"esm").unwrap();
+        library.add_defaults(dir.path()).unwrap();
+        library.add_defaults(dir.path()).unwrap();
+        let (default, png) = library.get(DEFAULT_MASK).unwrap().unwrap();
+        assert_eq!(default.added_by, 0);
+        assert_eq!(png.as_ref(), b"esm");
+        assert!(library.get(DEFAULT_MASKS[1].0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_half_written_mask_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = MaskLibrary::init(dir.path().to_str().unwrap()).unwrap();
+
+        // A record left without its overlay, like one written before adds were transactional.
+        let stray = bincode::serialize(&mask("Stray")).unwrap();
+        library.masks.insert(mask_key("Stray"), stray).unwrap();
+        assert!(library.get("Stray").unwrap().is_none());
+        assert!(library.names_matching("").unwrap().is_empty());
+
+        assert!(library.add(&mask("Stray"), b"stray").unwrap());
+        assert_eq!(library.get("stray").unwrap().unwrap().1.as_ref(), b"stray");
+        assert!(!library.add(&mask("Stray"), b"other").unwrap());
+    }
+}
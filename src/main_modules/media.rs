@@ -1,188 +1,217 @@
 use reqwest::Client;
-use serenity::all::{Attachment, EditMessage, Message};
+use serenity::all::{Attachment, CreateAttachment, EditMessage, Message, UserId};
 use uuid::Uuid;
-use std::io::{BufReader, Write};
-use std::process::{Command, Output};
-use std::sync::Arc;
+use std::io::{self, BufReader, Write};
+use std::num::NonZeroUsize;
+use super::error::{self, BotError};
+use super::ffmpeg::{self, Ffmpeg};
+use super::media_format::{self, MediaFormat, MediaKind};
+use super::media_queue::{Progress, StatusMessage};
+use super::upload_size::{self, Fitted, GifStep, VideoTarget};
 use image::{DynamicImage, GenericImageView, ImageBuffer, ImageFormat, Rgba};
 use std::path::{Path, PathBuf};
 use std::fs::{self, File};
-use rayon::prelude::*;
-use tempfile::tempdir;
-
-pub fn apply_mask(
+use tempfile::{tempdir, tempdir_in};
+use tokio::task::JoinSet;
+use tracing::{Instrument, debug, info_span, instrument};
+use std::time::Duration;
+use crate::Data;
+
+/// How many two-pass encodes a video gets to land under the limit, in case the first overshoots.
+const FIT_ATTEMPTS: usize = 2;
+
+#[allow(clippy::too_many_arguments)]
+#[instrument(skip_all, fields(input = %input_path, overlay = overlay_path))]
+pub async fn apply_mask(
     input_path: String,
+    input_format: MediaFormat,
     overlay_path: &str,
     flip_overlay: bool,
     height_float: f32,
     transparent: bool,
-    no_force_gif: bool
-) -> Result<String, String> {
-    let input_extension = Path::new(&input_path).extension().and_then(|s| s.to_str()).unwrap_or("");
-    
+    no_force_gif: bool,
+    progress: &Progress,
+) -> Result<String, BotError> {
     let temp_dir_path = Path::new(".tmp");
     
-    fs::create_dir_all(temp_dir_path).unwrap();
-
-    let input_path = match input_extension.to_lowercase().as_str() {
-        "jpg" | "jpeg" | "bmp" | "tiff" | "webp" | "ico" | "heic" | "heif" | 
-        "raw" | "cr2" | "nef" | "arw" | "dng" | "psd" => {
-            image_to_png_converter(&input_path, &input_path);
-            // Move the input file after conversion
-            let new_input_path = format!("./.tmp/{}.png", Uuid::new_v4());
-            fs::rename(input_path, &new_input_path).unwrap();
-            Ok(new_input_path)
-        },
-        
-        "mov" | "avi" | "wmv" | "flv" | "mkv" | "webm" | "m4v" | "3gp" | "mpeg" | 
-        "mpg" | "divx" | "vob" | "mts" | "m2ts" | "ts" => {
-            video_format_changer(&input_path, &input_path);
-            // Move the input file after processing
-            let new_input_path = format!("./.tmp/{}.mp4", Uuid::new_v4());
-            fs::rename(input_path, &new_input_path).unwrap();
-            Ok(new_input_path)
+    fs::create_dir_all(temp_dir_path)?;
+    // Holds the input converted to a format the masks work on, removed however this returns.
+    let conversion_dir = tempdir_in(temp_dir_path)?;
+
+    let input_path = match (input_format, input_format.kind()) {
+        (MediaFormat::Png | MediaFormat::Gif | MediaFormat::Mp4, _) => input_path,
+
+        (_, MediaKind::Image) => {
+            let new_input_path = conversion_dir.path().join("input.png").to_string_lossy().into_owned();
+            image_to_png_converter(&input_path, &new_input_path).await?;
+            new_input_path
         },
-        
-        "png" | "mp4" | "gif" => Ok(input_path),
-        
+
         _ => {
-            println!("Skipping unsupported format: {}", input_extension);
-            Err("Unsupported format.".to_string())
+            let new_input_path = conversion_dir.path().join("input.mp4").to_string_lossy().into_owned();
+            video_format_changer(&input_path, &new_input_path, progress).await?;
+            new_input_path
         },
     };
 
     let file_name = Uuid::new_v4();
-    let binding = input_path.clone().unwrap();
-    let input_extension = Path::new(&binding).extension().and_then(|s| s.to_str()).unwrap_or("");
 
-    if input_path.is_err() {
-        Err("I couldn't get the file format here.".to_string())
-    } else {
-        let input_path = input_path.unwrap();
-        match input_extension.to_lowercase().as_str() {
-            "png" => {
-                let mut output_path = format!("./.tmp/{}.png", file_name);
-                apply_image_mask(&input_path, overlay_path, output_path.as_str(), flip_overlay, height_float, transparent).unwrap();
-    
-                if !no_force_gif {
-                    output_path = format!("./.tmp/{}.gif", file_name);
-                    png_to_gif_converter(format!("./.tmp/{}.png", file_name).as_str(), output_path.as_str(), QualityPreset::HighQuality).unwrap();
-                    fs::remove_file(format!("./.tmp/{}.png", file_name)).unwrap();
-                }
-    
-                Ok(output_path)
-            },
-            "gif" => {
-                let output_path = format!("./.tmp/{}.gif", file_name);
-                apply_gif_mask(&input_path, overlay_path, output_path.as_str(), flip_overlay, height_float, transparent).unwrap();
-    
-                Ok(output_path.to_string())
-            },
-            "mp4" => {
-                let output_path = format!("./.tmp/{}.mp4", file_name);
-                apply_video_mask(temp_dir_path, &input_path, overlay_path, output_path.as_str(), flip_overlay, height_float).unwrap();
-    
-                Ok(output_path.to_string())
-            },
+    match input_format.kind() {
+        MediaKind::Image => {
+            let mut output_path = format!("./.tmp/{}.png", file_name);
+            progress.stage("Adding the mask");
+            apply_image_mask(&input_path, overlay_path, output_path.as_str(), flip_overlay, height_float, transparent).await?;
 
-            _ => {
-                Err("Uh oh, that's a bad file format.".to_string())
+            if !no_force_gif {
+                output_path = format!("./.tmp/{}.gif", file_name);
+                png_to_gif_converter(format!("./.tmp/{}.png", file_name).as_str(), output_path.as_str(), QualityPreset::HighQuality).await?;
+                fs::remove_file(format!("./.tmp/{}.png", file_name))?;
             }
-        }
+
+            Ok(output_path)
+        },
+        MediaKind::Animation => {
+            let output_path = format!("./.tmp/{}.gif", file_name);
+            apply_gif_mask(&input_path, overlay_path, output_path.as_str(), flip_overlay, height_float, transparent, progress).await?;
+
+            Ok(output_path)
+        },
+        MediaKind::Video => {
+            let output_path = format!("./.tmp/{}.mp4", file_name);
+            apply_video_mask(temp_dir_path, &input_path, overlay_path, output_path.as_str(), flip_overlay, height_float, progress).await?;
+
+            Ok(output_path)
+        },
     }
 }
 
 
-fn apply_gif_mask(
+/// Masks each of `frame_paths` into an `output_` file next to it, a few at a time. Dropping the
+/// returned future stops the frames still being masked.
+async fn mask_frames(
+    frame_paths: &[PathBuf],
+    overlay_path: &str,
+    flip_overlay: bool,
+    height_float: f32,
+    transparent: bool,
+    progress: &Progress,
+) -> Result<(), BotError> {
+    progress.stage("Adding the mask to frames");
+    let parallelism = std::thread::available_parallelism().map_or(4, NonZeroUsize::get);
+    let mut frames = frame_paths.iter();
+    let mut masking = JoinSet::new();
+    let mut done = 0;
+    loop {
+        while masking.len() < parallelism && let Some(frame_path) = frames.next() {
+            let output_frame = frame_path.with_file_name(format!(
+                "output_{}",
+                frame_path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            let (frame_path, overlay_path) = (frame_path.clone(), overlay_path.to_string());
+            masking.spawn(async move {
+                apply_image_mask(
+                    &frame_path.to_string_lossy(),
+                    &overlay_path,
+                    &output_frame.to_string_lossy(),
+                    flip_overlay,
+                    height_float,
+                    transparent,
+                ).await
+            });
+        }
+        let Some(result) = masking.join_next().await else {
+            break;
+        };
+        result.map_err(io::Error::other)??;
+        done += 1;
+        progress.set(done as f32 / frame_paths.len() as f32);
+    }
+    Ok(())
+}
+
+async fn apply_gif_mask(
     input_path: &str,
     overlay_path: &str,
     output_path: &str,
     flip_overlay: bool,
     height_float: f32,
     transparent: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+    progress: &Progress,
+) -> Result<(), BotError> {
     let temp_dir = tempdir()?;
     let temp_dir_path = temp_dir.path();
     
-    let frames_path = temp_dir_path.join("frame_%04d.png");
-    Command::new("ffmpeg")
-        .args([
-            "-i", input_path,
-            frames_path.to_str().unwrap()
-        ])
-        .output()?;
+    progress.stage("Splitting into frames");
+    Ffmpeg::new()
+        .input(input_path)
+        .output(temp_dir_path.join("frame_%04d.png"))
+        .run().await?;
     
     let frame_paths: Vec<_> = fs::read_dir(temp_dir_path)?
         .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry.file_name()
-                .to_str()
-                .map(|s| s.starts_with("frame_"))
-                .unwrap_or(false)
-        })
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("frame_"))
         .map(|entry| entry.path())
         .collect();
     
-    frame_paths.par_iter().for_each(|frame_path| {
-        let output_frame = temp_dir_path.join(format!(
-            "output_{}",
-            frame_path.file_name().unwrap().to_str().unwrap()
-        ));
-        
-        apply_image_mask(
-            frame_path.to_str().unwrap(),
-            overlay_path,
-            output_frame.to_str().unwrap(),
-            flip_overlay,
-            height_float,
-            transparent,
-        ).unwrap();
-    });
+    mask_frames(&frame_paths, overlay_path, flip_overlay, height_float, transparent, progress).await?;
     
-    Command::new("ffmpeg")
-        .args([
-            "-i", temp_dir_path.join("output_frame_%04d.png").to_str().unwrap(),
-            "-vf", "split[a][b];[a]palettegen=max_colors=256[p];[b][p]paletteuse=dither=bayer",
-            "-framerate", "25",
-            output_path
-        ])
-        .output()?;
+    progress.stage("Encoding the gif");
+    Ffmpeg::new()
+        .args(["-framerate", "25"])
+        .input(temp_dir_path.join("output_frame_%04d.png"))
+        .video_filter("split[a][b];[a]palettegen=max_colors=256[p];[b][p]paletteuse=dither=bayer")
+        .output(output_path)
+        .run().await?;
     
     Ok(())
 }
 
-fn convert_to_standard_png(input_path: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    Command::new("ffmpeg")
-        .arg("-i")
-        .arg(input_path)
-        .arg("-c:v")
-        .arg("png")
-        .arg("-pix_fmt")
-        .arg("rgba")
-        .arg(output_path)
-        .output()?;
+async fn convert_to_standard_png(input_path: &str, output_path: &str) -> Result<(), BotError> {
+    Ffmpeg::new()
+        .input(input_path)
+        .video_codec("png")
+        .pixel_format("rgba")
+        .output(output_path)
+        .run().await?;
     Ok(())
 }
 
-fn open_image(path: &str) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+fn open_image(path: &str) -> Result<DynamicImage, BotError> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
     let img = image::load(reader, ImageFormat::Png)?;
     Ok(img)
 }
 
-fn apply_image_mask(
+async fn apply_image_mask(
     input_path: &str,
     overlay_path: &str,
     output_path: &str,
     flip_overlay: bool,
     height_float: f32,
     transparent: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), BotError> {
     let temp_input_path = format!("./.tmp/{}.png", Uuid::new_v4());
-    convert_to_standard_png(input_path, &temp_input_path)?;
+    convert_to_standard_png(input_path, &temp_input_path).await?;
 
-    let input_image = open_image(&temp_input_path)?;
+    let (input_path, overlay_path, output_path) = (temp_input_path.clone(), overlay_path.to_string(), output_path.to_string());
+    let masked = tokio::task::spawn_blocking(move || {
+        mask_image(&input_path, &overlay_path, &output_path, flip_overlay, height_float, transparent)
+    }).await.map_err(io::Error::other)?;
+    fs::remove_file(&temp_input_path)?;
+    masked
+}
+
+/// The pixel work of `apply_image_mask`, on a PNG `convert_to_standard_png` made.
+fn mask_image(
+    input_path: &str,
+    overlay_path: &str,
+    output_path: &str,
+    flip_overlay: bool,
+    height_float: f32,
+    transparent: bool,
+) -> Result<(), BotError> {
+    let input_image = open_image(input_path)?;
     let mut overlay_image = open_image(overlay_path)?;
     let (input_width, input_height) = input_image.dimensions();
     let mask_height = (input_height as f32 * height_float) as u32;
@@ -212,7 +241,6 @@ fn apply_image_mask(
     }
 
     output_image.save(output_path)?;
-    fs::remove_file(&temp_input_path)?;
     Ok(())
 }
 
@@ -228,14 +256,15 @@ fn apply_full_transparency(pixel: Rgba<u8>, mask_alpha: f32) -> Rgba<u8> {
     }
 }
 
-fn apply_video_mask(
+async fn apply_video_mask(
     temp_dir: &Path,
     input_path: &str,
     overlay_path: &str,
     output_path: &str,
     flip_overlay: bool,
-    height_float: f32
-) -> Result<(), Box<dyn std::error::Error>> {
+    height_float: f32,
+    progress: &Progress,
+) -> Result<(), BotError> {
 
     let temp_input_path = temp_dir.join("input.mp4");
     let temp_overlay_path = temp_dir.join("overlay.png");
@@ -243,122 +272,238 @@ fn apply_video_mask(
     fs::copy(input_path, &temp_input_path)?;
     fs::copy(overlay_path, &temp_overlay_path)?;
 
-    let mut command = Command::new("ffmpeg");
-    command.arg("-i").arg(&temp_input_path);
-    command.arg("-vf");
-    command.arg("fps=25");
-    command.arg("-q:v");
-    command.arg("2");
-    command.arg(temp_dir.join("frame_%03d.png"));
-
-    let output = command.output()?;
-    if!output.status.success() {
-        return Err(format!("FFmpeg command failed: {:#?}", output).into());
-    }
+    progress.stage("Splitting into frames");
+    Ffmpeg::new()
+        .input(&temp_input_path)
+        .video_filter("fps=25")
+        .args(["-q:v", "2"])
+        .output(temp_dir.join("frame_%03d.png"))
+        .run().await?;
 
     let frame_paths: Vec<_> = fs::read_dir(temp_dir)?
        .filter_map(|entry| entry.ok())
        .filter(|entry| entry.file_type().ok().is_some_and(|ft| ft.is_file()))
        .map(|entry| entry.path())
-       .filter(|path| path.file_name().unwrap().to_str().unwrap().starts_with("frame_"))
+       .filter(|path| path.file_name().unwrap_or_default().to_string_lossy().starts_with("frame_"))
        .collect();
 
-    println!("Applying mask to {} frames", frame_paths.len());
-    frame_paths.par_iter().for_each(|path| {
-        let output_path = temp_dir.join(format!("output_{:03}", path.file_name().unwrap().to_str().unwrap()));
-        apply_image_mask(
-            path.to_str().unwrap(),
-            temp_overlay_path.to_str().unwrap(),
-            output_path.to_str().unwrap(),
-            flip_overlay,
-            height_float,
-            false,
-        )
-       .unwrap();
-    });
-
-    let mut command = Command::new("ffmpeg");
-    command.arg("-framerate").arg("25");
-    command.arg("-i").arg(temp_dir.join("output_frame_%03d.png"));
-    command.arg("-c:v");
-    command.arg("libvpx-vp9");
-    command.arg("-pix_fmt");
-    command.arg("yuva420p");
-    command.arg("-crf");
-    command.arg("18");
-    command.arg("-y");
-    command.arg(output_path);
-
-    let output = command.output()?;
-    if!output.status.success() {
-        return Err(format!("FFmpeg command failed: {:#?}", output).into());
-    }
+    debug!(frames = frame_paths.len(), "Applying mask to frames");
+    mask_frames(&frame_paths, &temp_overlay_path.to_string_lossy(), flip_overlay, height_float, false, progress).await?;
+
+    progress.stage("Encoding the video");
+    Ffmpeg::new()
+        .args(["-framerate", "25"])
+        .input(temp_dir.join("output_frame_%03d.png"))
+        .video_codec("libvpx-vp9")
+        .pixel_format("yuva420p")
+        .crf(18)
+        .output(output_path)
+        .run().await?;
 
     fs::remove_file(&temp_input_path)?;
     fs::remove_file(&temp_overlay_path)?;
     for path in frame_paths {
-        fs::remove_file(temp_dir.join(format!("output_{}", path.clone().file_name().unwrap().to_str().unwrap())))?;
+        fs::remove_file(temp_dir.join(format!("output_{}", path.file_name().unwrap_or_default().to_string_lossy())))?;
         fs::remove_file(path)?;
     }
 
     Ok(())
 }
 
-pub fn video_format_changer(input_filename: &str, output_filename: &str) -> Output {
-    Command::new("ffmpeg")
-        .args([
-            "-i", input_filename,
-            "-c:v", "libx264",
-            "-preset", "medium",
-            "-crf", "23",
-            "-c:a", "aac",
-            "-b:a", "128k",
-            "-fs", "100M",
-            output_filename
-        ])
-        .output()
-        .expect("Failed to execute FFmpeg command.")
+pub async fn video_format_changer(input_filename: &str, output_filename: &str, progress: &Progress) -> Result<(), BotError> {
+    progress.stage("Converting to MP4");
+    let info = ffmpeg::probe(Path::new(input_filename)).await?;
+    let command = Ffmpeg::new()
+        .input(input_filename)
+        .video_codec("libx264")
+        .args(["-preset", "medium"])
+        .crf(23);
+    let command = if info.has_audio() {
+        command.audio_codec("aac").audio_bitrate(128)
+    } else {
+        command.arg("-an")
+    };
+    command
+        .output(output_filename)
+        .run_with_progress(info.duration, progress).await
 }
 
-pub async fn video_convert(new_message: Message, ctx: serenity::prelude::Context, reqwest_client: Arc<Client>, attachment: Attachment) {
-    let mut msg = new_message.reply_ping(&ctx.http, format!("Converting {} to MP4!", attachment.filename)).await.unwrap();
-    let input_filename = format!("./.tmp/input_{}.tmp", Uuid::new_v4());
-    let output_filename = format!("./.tmp/output_{}.mp4", Uuid::new_v4());
+/// Shrinks the output at `path` until it fits in `limit` bytes, if it doesn't already, and
+/// removes it if it can't be made to.
+pub async fn fit_output(path: &str, limit: u64, progress: &Progress) -> Result<Fitted, BotError> {
+    let result = async {
+        let size = fs::metadata(path)?.len();
+        if size <= limit {
+            return Ok(Fitted { size, limit, compromise: None });
+        }
+        match media_format::sniff(Path::new(path), path).await? {
+            MediaFormat::Gif => fit_gif(path, size, limit, progress).await,
+            format @ (MediaFormat::Mp4 | MediaFormat::Webm) => fit_video(path, format, size, limit, progress).await,
+            _ => Err(BotError::TooLarge { size, limit }),
+        }
+    }.await;
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
 
-    let response = reqwest_client.get(&attachment.url).send().await.unwrap();
-    let bytes = response.bytes().await.unwrap();
-    let mut file = std::fs::File::create(&input_filename).expect("Failed to create input file");
-    file.write_all(&bytes).expect("Failed to write input file");
+/// Goes down the gif ladder from the gentlest step that could fit, keeping the first that does.
+async fn fit_gif(path: &str, size: u64, limit: u64, progress: &Progress) -> Result<Fitted, BotError> {
+    progress.stage("Shrinking the gif to fit");
+    let fps = ffmpeg::probe(Path::new(path)).await?.video.and_then(|video| video.fps);
+    let ladder = GifStep::ladder(size, fps, limit);
+    let temp_dir = tempdir_in(Path::new(path).parent().unwrap_or(Path::new(".")))?;
+    let candidate = temp_dir.path().join("fitted.gif");
+    let mut smallest = size;
+    for (tried, step) in ladder.iter().enumerate() {
+        Ffmpeg::new()
+            .input(path)
+            .filter_complex(&step.filter(fps))
+            .args(["-loop", "0"])
+            .output(&candidate)
+            .run().await?;
+        progress.set((tried + 1) as f32 / ladder.len() as f32);
+
+        let fitted_size = fs::metadata(&candidate)?.len();
+        debug!(size = fitted_size, ?step, "Shrunk gif");
+        if fitted_size <= limit {
+            fs::rename(&candidate, path)?;
+            return Ok(Fitted { size: fitted_size, limit, compromise: Some(step.describe(fps)) });
+        }
+        smallest = smallest.min(fitted_size);
+    }
+    Err(BotError::TooLarge { size: smallest, limit })
+}
 
-    let output = video_format_changer(&input_filename, &output_filename);
+/// Re-encodes the video at `path` in two passes at the bitrate that fits it, aiming lower if it
+/// still comes out too big.
+async fn fit_video(path: &str, format: MediaFormat, size: u64, limit: u64, progress: &Progress) -> Result<Fitted, BotError> {
+    let info = ffmpeg::probe(Path::new(path)).await?;
+    let too_large = BotError::TooLarge { size, limit };
+    let Some(duration) = info.duration else {
+        return Err(too_large);
+    };
+    let Some(mut target) = VideoTarget::new(limit, duration, info.has_audio()) else {
+        return Err(too_large);
+    };
 
-    if output.status.success() {
-        let file = serenity::all::CreateAttachment::path(&output_filename).await.unwrap();
-        let build = EditMessage::new().new_attachment(file).content("Done!");
-        match msg.edit(&ctx.http, build).await {
-            Ok(()) => (),
-            Err(_) => {msg.edit(&ctx.http, EditMessage::new().content("Message failed to edit, file may have been too large!")).await.unwrap(); } 
-        };
-    } else {
-        println!("FFmpeg conversion failed: {:?}", String::from_utf8_lossy(&output.stderr));
-        let _ = new_message.channel_id.say(&ctx.http, "Failed to convert the video.").await;
-        let _ = std::fs::remove_file(&input_filename);
+    let temp_dir = tempdir_in(Path::new(path).parent().unwrap_or(Path::new(".")))?;
+    let candidate = temp_dir.path().join(if format == MediaFormat::Webm { "fitted.webm" } else { "fitted.mp4" });
+    let pass_log = temp_dir.path().join("pass");
+    let mut fitted_size = size;
+    for _ in 0..FIT_ATTEMPTS {
+        encode_two_pass(path, &candidate, &pass_log, format, target, duration, progress).await?;
+        fitted_size = fs::metadata(&candidate)?.len();
+        debug!(size = fitted_size, ?target, "Re-encoded video to fit");
+        if fitted_size <= limit {
+            fs::rename(&candidate, path)?;
+            return Ok(Fitted { size: fitted_size, limit, compromise: Some(target.to_string()) });
+        }
+        match target.shrunk(fitted_size, limit) {
+            Some(shrunk) => target = shrunk,
+            None => break,
+        }
+    }
+    Err(BotError::TooLarge { size: fitted_size, limit })
+}
+
+async fn encode_two_pass(input: &str, output: &Path, pass_log: &Path, format: MediaFormat, target: VideoTarget, duration: Duration, progress: &Progress) -> Result<(), BotError> {
+    let (codec, pixel_format, audio_codec) = match format {
+        MediaFormat::Webm => ("libvpx-vp9", "yuva420p", "libopus"),
+        _ => ("libx264", "yuv420p", "aac"),
+    };
+    let encode = |pass: u8| {
+        let command = Ffmpeg::new()
+            .input(input)
+            .video_codec(codec)
+            .pixel_format(pixel_format)
+            .video_bitrate(target.video_kbps)
+            .pass(pass, pass_log);
+        match target.max_height {
+            Some(height) => command.video_filter(&format!("scale=-2:'min(ih,{})'", height)),
+            None => command,
+        }
+    };
+
+    progress.stage("Re-encoding to fit, pass 1 of 2");
+    encode(1)
+        .arg("-an")
+        .format("null")
+        .output("-")
+        .run_with_progress(Some(duration), progress).await?;
+
+    progress.stage("Re-encoding to fit, pass 2 of 2");
+    let second_pass = match target.audio_kbps {
+        Some(kbps) => encode(2).audio_codec(audio_codec).audio_bitrate(kbps),
+        None => encode(2).arg("-an"),
+    };
+    second_pass
+        .output(output)
+        .run_with_progress(Some(duration), progress).await
+}
+
+async fn convert_attachment(reqwest_client: &Client, attachment: &Attachment, input_filename: &str, output_filename: &str, upload_limit: u64, progress: Progress) -> Result<Fitted, BotError> {
+    progress.stage("Downloading");
+    let response = reqwest_client.get(&attachment.url).send().await.map_err(|err| BotError::Api { service: "Discord CDN", details: err.to_string() })?;
+    let bytes = response.bytes().await.map_err(|err| BotError::Api { service: "Discord CDN", details: err.to_string() })?;
+    let mut file = std::fs::File::create(input_filename)?;
+    file.write_all(&bytes)?;
+
+    if media_format::sniff(Path::new(input_filename), &attachment.filename).await?.kind() != MediaKind::Video {
+        return Err(BotError::UnsupportedMedia(attachment.filename.clone()));
+    }
+    video_format_changer(input_filename, output_filename, &progress).await?;
+    fit_output(output_filename, upload_limit, &progress).await
+}
+
+/// Converts `attachment` to MP4 as a job in the media queue, replying to `new_message` with its
+/// progress and then the result, shrunk to fit the upload limit. `requested_by` is who can cancel
+/// it.
+pub async fn video_convert(new_message: Message, ctx: serenity::prelude::Context, data: Data, attachment: Attachment, requested_by: UserId) {
+    let label = format!("Converting {} to MP4!", attachment.filename);
+    let msg = match new_message.reply_ping(&ctx.http, &label).await {
+        Ok(msg) => msg,
+        Err(err) => {
+            error::report("video conversion", &BotError::from(err));
+            return;
+        }
+    };
+    let status = StatusMessage::new(ctx.http.clone(), msg.channel_id, msg.id, label);
+    let input_filename = format!("./.tmp/input_{}.tmp", Uuid::new_v4());
+    let output_filename = format!("./.tmp/output_{}.mp4", Uuid::new_v4());
+    let upload_limit = upload_size::guild_upload_limit(&ctx.cache, new_message.guild_id);
+
+    let span = info_span!("video_convert", message_id = %new_message.id, file = attachment.filename);
+    let result = async {
+        let job = data.media_queue.enqueue(requested_by, "video_to_mp4")?;
+        let fitted = job.run(&status, |progress| convert_attachment(&data.reqwest_client, &attachment, &input_filename, &output_filename, upload_limit, progress)).await?;
+
+        let file = CreateAttachment::path(&output_filename).await?;
+        status.finish(EditMessage::new().new_attachment(file).content(format!("Done! {}", fitted))).await
+    }.instrument(span).await;
+    match result {
+        Ok(()) => {}
+        Err(BotError::Cancelled) => {
+            let _ = status.finish(EditMessage::new().content("Conversion cancelled.")).await;
+        }
+        Err(err) => {
+            let id = error::report(&format!("video conversion of {}", attachment.url), &err);
+            let _ = status.finish(EditMessage::new().content(format!("Failed to convert the video: {} (error `{}`)", err, id))).await;
+        }
     }
 
-    std::fs::remove_file(&input_filename).unwrap();
-    std::fs::remove_file(&output_filename).unwrap();
+    let _ = std::fs::remove_file(&input_filename);
+    let _ = std::fs::remove_file(&output_filename);
 }
 
-pub fn image_to_png_converter(input_filename: &str, output_filename: &str) -> std::process::Output {
-    Command::new("ffmpeg")
-        .args([
-            "-i", input_filename,
-            "-f", "png",
-            "-fs", "100M",
-            output_filename
-        ])
-        .output()
-        .expect("Failed to execute FFmpeg command.")
+pub async fn image_to_png_converter(input_filename: &str, output_filename: &str) -> Result<(), BotError> {
+    Ffmpeg::new()
+        .input(input_filename)
+        .format("png")
+        .output(output_filename)
+        .run().await?;
+    Ok(())
 }
 
 #[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
@@ -383,7 +528,7 @@ pub enum QualityPreset {
     Muted,
 }
 
-pub fn video_to_gif_converter(input_filename: &str, output_filename: &str, preset: QualityPreset) -> std::io::Result<()> {
+pub async fn video_to_gif_converter(input_filename: &str, output_filename: &str, preset: QualityPreset, progress: &Progress) -> Result<(), BotError> {
     let (fps, colors, compression, quality, dither, bayer_scale, scale, additional_filters) = match preset {
         QualityPreset::BestQuality => ("30", "256", "6", "100", "sierra2_4a", "0", "1920:-1", ""),
         QualityPreset::HighQuality => ("24", "256", "7", "95", "floyd_steinberg", "3", "1280:-1", ""),
@@ -406,111 +551,106 @@ pub fn video_to_gif_converter(input_filename: &str, output_filename: &str, prese
     };
 
     // Create a temporary directory for storing intermediate files
-    let temp_dir = tempdir().expect("Failed to create temporary directory");
+    let temp_dir = tempdir()?;
     let temp_path = temp_dir.path();
 
     // Split the video into 10-second segments
     let segment_duration = 10;
-    let segment_pattern = temp_path.join("segment_%03d.mp4").to_str().unwrap().to_string();
+    let segment_pattern = temp_path.join("segment_%03d.mp4");
     
-    Command::new("ffmpeg")
-        .args([
-            "-i", input_filename,
-            "-c", "copy",
-            "-f", "segment",
-            "-segment_time", &segment_duration.to_string(),
-            "-reset_timestamps", "1",
-            &segment_pattern,
-        ])
-        .output()
-        .expect("Failed to split video into segments");
+    progress.stage("Splitting the video");
+    Ffmpeg::new()
+        .input(input_filename)
+        .copy_codecs()
+        .format("segment")
+        .args(["-segment_time", &segment_duration.to_string(), "-reset_timestamps", "1"])
+        .output(&segment_pattern)
+        .run().await?;
+
+    // Segments are named in order, and combined in the order they're processed
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(temp_path)? {
+        let path = entry?.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("mp4") {
+            segments.push(path);
+        }
+    }
+    segments.sort();
 
     // Process segments incrementally
+    progress.stage("Converting to gif");
     let mut processed_segments = Vec::new();
-    for entry in fs::read_dir(temp_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.extension().and_then(|s| s.to_str()) == Some("mp4") {
-            let output_gif = path.with_extension("gif");
-            let filter_complex = format!(
-                "[0:v] fps={fps},scale={scale}:flags=lanczos{} [scaled];
-                [scaled] split [a][b];
-                [a] palettegen=max_colors={colors}:reserve_transparent=0:stats_mode=diff [p];
-                [b][p] paletteuse=new=1:dither={dither}:bayer_scale={bayer_scale}:diff_mode=rectangle",
-                if additional_filters.is_empty() { String::new() } else { format!(",{}", additional_filters) }
-            );
-
-            Command::new("ffmpeg")
-                .args([
-                    "-i", path.to_str().unwrap(),
-                    "-filter_complex", &filter_complex,
-                    "-compression_level", compression,
-                    "-quality", quality,
-                    output_gif.to_str().unwrap(),
-                ])
-                .output()
-                .expect("Failed to convert segment to GIF");
-
-            processed_segments.push(output_gif);
-
-            // Combine processed segments when we have a certain number (e.g., 5)
-            if processed_segments.len() >= 5 {
-                combine_gifs(&processed_segments, temp_path, output_filename)?;
-                processed_segments.clear();
-            }
+    for (done, path) in segments.iter().enumerate() {
+        let output_gif = path.with_extension("gif");
+        let filter_complex = format!(
+            "[0:v] fps={fps},scale={scale}:flags=lanczos{} [scaled];
+            [scaled] split [a][b];
+            [a] palettegen=max_colors={colors}:reserve_transparent=0:stats_mode=diff [p];
+            [b][p] paletteuse=new=1:dither={dither}:bayer_scale={bayer_scale}:diff_mode=rectangle",
+            if additional_filters.is_empty() { String::new() } else { format!(",{}", additional_filters) }
+        );
+
+        Ffmpeg::new()
+            .input(path)
+            .filter_complex(&filter_complex)
+            .args(["-compression_level", compression, "-quality", quality])
+            .output(&output_gif)
+            .run().await?;
+
+        processed_segments.push(output_gif);
+
+        // Combine processed segments when we have a certain number (e.g., 5)
+        if processed_segments.len() >= 5 {
+            combine_gifs(&processed_segments, temp_path, output_filename).await?;
+            processed_segments.clear();
         }
+        progress.set((done + 1) as f32 / segments.len() as f32);
     }
 
     // Combine any remaining segments
     if !processed_segments.is_empty() {
-        combine_gifs(&processed_segments, temp_path, output_filename)?;
+        combine_gifs(&processed_segments, temp_path, output_filename).await?;
     }
 
     // Clean up temporary files
-    temp_dir.close().expect("Failed to clean up temporary directory");
+    temp_dir.close()?;
 
     Ok(())
 }
 
-fn combine_gifs(segments: &[PathBuf], temp_path: &Path, output_filename: &str) -> std::io::Result<()> {
+async fn combine_gifs(segments: &[PathBuf], temp_path: &Path, output_filename: &str) -> Result<(), BotError> {
     let concat_list = temp_path.join("concat_list.txt");
     let mut concat_file = fs::File::create(&concat_list)?;
     for gif in segments {
-        writeln!(concat_file, "file '{}'", gif.to_str().unwrap())?;
+        writeln!(concat_file, "file '{}'", gif.display())?;
     }
 
     let temp_output = temp_path.join("temp_output.gif");
-    Command::new("ffmpeg")
-        .args([
-            "-f", "concat",
-            "-safe", "0",
-            "-i", concat_list.to_str().unwrap(),
-            "-filter_complex", "split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse",
-            temp_output.to_str().unwrap(),
-        ])
-        .output()
-        .expect("Failed to concatenate GIF segments");
+    Ffmpeg::new()
+        .format("concat")
+        .args(["-safe", "0"])
+        .input(&concat_list)
+        .filter_complex("split[s0][s1];[s0]palettegen[p];[s1][p]paletteuse")
+        .output(&temp_output)
+        .run().await?;
 
     // Append the temp_output to the final output file
     if Path::new(output_filename).exists() {
         let final_concat_list = temp_path.join("final_concat_list.txt");
         let mut final_concat_file = fs::File::create(&final_concat_list)?;
         writeln!(final_concat_file, "file '{}'", output_filename)?;
-        writeln!(final_concat_file, "file '{}'", temp_output.to_str().unwrap())?;
-
-        Command::new("ffmpeg")
-            .args([
-                "-f", "concat",
-                "-safe", "0",
-                "-i", final_concat_list.to_str().unwrap(),
-                "-c", "copy",
-                "-fs", "100M",
-                &format!("{}..tmp", output_filename),
-            ])
-            .output()
-            .expect("Failed to append to final GIF");
-
-        fs::rename(format!("{}.tmp", output_filename), output_filename)?;
+        writeln!(final_concat_file, "file '{}'", temp_output.display())?;
+
+        let appended = format!("{}.tmp.gif", output_filename);
+        Ffmpeg::new()
+            .format("concat")
+            .args(["-safe", "0"])
+            .input(&final_concat_list)
+            .copy_codecs()
+            .output(&appended)
+            .run().await?;
+
+        fs::rename(appended, output_filename)?;
     } else {
         fs::rename(temp_output, output_filename)?;
     }
@@ -518,7 +658,7 @@ fn combine_gifs(segments: &[PathBuf], temp_path: &Path, output_filename: &str) -
     Ok(())
 }
 
-pub fn png_to_gif_converter(input_filename: &str, output_filename: &str, preset: QualityPreset) -> std::io::Result<()> {
+pub async fn png_to_gif_converter(input_filename: &str, output_filename: &str, preset: QualityPreset) -> Result<(), BotError> {
     let (colors, compression, quality, dither, bayer_scale, scale, additional_filters) = match preset {
         QualityPreset::BestQuality => ("256", "6", "100", "sierra2_4a", "0", "1920:-1", ""),
         QualityPreset::HighQuality => ("256", "7", "95", "floyd_steinberg", "3", "1280:-1", ""),
@@ -545,17 +685,12 @@ pub fn png_to_gif_converter(input_filename: &str, output_filename: &str, preset:
         if additional_filters.is_empty() { String::new() } else { format!(",{}", additional_filters) }
     );
 
-    Command::new("ffmpeg")
-        .args([
-            "-i", input_filename,
-            "-filter_complex", &filter_complex,
-            "-loop", "0",
-            "-compression_level", compression,
-            "-quality", quality,
-            "-fs", "100M",
-            output_filename
-        ])
-        .output()?;
+    Ffmpeg::new()
+        .input(input_filename)
+        .filter_complex(&filter_complex)
+        .args(["-loop", "0", "-compression_level", compression, "-quality", quality])
+        .output(output_filename)
+        .run().await?;
    
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+use super::error::BotError;
+use super::ffmpeg;
+
+/// How much of a file `MediaFormat::from_magic` needs, enough to see a second transport stream
+/// packet.
+pub const HEADER_LEN: usize = 200;
+
+/// Which pipeline a file goes down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Animation,
+    Video,
+}
+
+/// What a file really is, whatever its name or content type claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Bmp,
+    Tiff,
+    Ico,
+    Heif,
+    Avif,
+    Gif,
+    Mp4,
+    Mov,
+    Webm,
+    Matroska,
+    Avi,
+    Flv,
+    MpegTs,
+    Mpeg,
+    Wmv,
+    /// Something ffmpeg can read as a still image that isn't listed above.
+    OtherImage,
+    /// Something ffmpeg can read as a video that isn't listed above.
+    OtherVideo,
+}
+
+impl MediaFormat {
+    pub fn kind(self) -> MediaKind {
+        match self {
+            MediaFormat::Png
+            | MediaFormat::Jpeg
+            | MediaFormat::Webp
+            | MediaFormat::Bmp
+            | MediaFormat::Tiff
+            | MediaFormat::Ico
+            | MediaFormat::Heif
+            | MediaFormat::Avif
+            | MediaFormat::OtherImage => MediaKind::Image,
+            MediaFormat::Gif => MediaKind::Animation,
+            _ => MediaKind::Video,
+        }
+    }
+
+    /// Whether Discord already plays it inline, so converting it would gain nothing.
+    pub fn plays_in_discord(self) -> bool {
+        matches!(
+            self,
+            MediaFormat::Mp4 | MediaFormat::Mov | MediaFormat::Webm | MediaFormat::Gif
+        )
+    }
+
+    /// Recognises a file from its first `HEADER_LEN` bytes.
+    pub fn from_magic(bytes: &[u8]) -> Option<MediaFormat> {
+        let at =
+            |offset: usize, magic: &[u8]| bytes.get(offset..offset + magic.len()) == Some(magic);
+        if at(0, b"\x89PNG\r\n\x1a\n") {
+            Some(MediaFormat::Png)
+        } else if at(0, b"\xff\xd8\xff") {
+            Some(MediaFormat::Jpeg)
+        } else if at(0, b"GIF87a") || at(0, b"GIF89a") {
+            Some(MediaFormat::Gif)
+        } else if at(0, b"RIFF") && at(8, b"WEBP") {
+            Some(MediaFormat::Webp)
+        } else if at(0, b"RIFF") && at(8, b"AVI ") {
+            Some(MediaFormat::Avi)
+        } else if at(4, b"ftyp") {
+            // ISO media files name what they hold in their first brand.
+            match bytes.get(8..12)? {
+                b"qt  " => Some(MediaFormat::Mov),
+                b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"msf1" => {
+                    Some(MediaFormat::Heif)
+                }
+                b"avif" | b"avis" => Some(MediaFormat::Avif),
+                _ => Some(MediaFormat::Mp4),
+            }
+        } else if at(0, b"\x1a\x45\xdf\xa3") {
+            // Matroska's header names its doc type, which is `webm` for WebM files.
+            if bytes.windows(4).any(|window| window == b"webm") {
+                Some(MediaFormat::Webm)
+            } else {
+                Some(MediaFormat::Matroska)
+            }
+        } else if at(0, b"FLV") {
+            Some(MediaFormat::Flv)
+        } else if at(0, b"\x00\x00\x01\xba") || at(0, b"\x00\x00\x01\xb3") {
+            Some(MediaFormat::Mpeg)
+        } else if at(0, b"\x30\x26\xb2\x75\x8e\x66\xcf\x11") {
+            Some(MediaFormat::Wmv)
+        } else if at(0, b"BM") {
+            Some(MediaFormat::Bmp)
+        } else if at(0, b"II*\x00") || at(0, b"MM\x00*") {
+            Some(MediaFormat::Tiff)
+        } else if at(0, b"\x00\x00\x01\x00") {
+            Some(MediaFormat::Ico)
+        } else if at(0, b"\x47") && at(188, b"\x47") {
+            // Transport streams are runs of 188 byte packets, each starting with 0x47.
+            Some(MediaFormat::MpegTs)
+        } else {
+            None
+        }
+    }
+}
+
+/// Works out what the file at `path` is from its first bytes, asking ffprobe when they aren't
+/// recognised. `name` is what the user called it, for the error when it's nothing usable.
+pub async fn sniff(path: &Path, name: &str) -> Result<MediaFormat, BotError> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)
+        .await?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .await?;
+    if let Some(format) = MediaFormat::from_magic(&header) {
+        return Ok(format);
+    }
+
+    let unsupported = || BotError::UnsupportedMedia(name.to_string());
+    let info = ffmpeg::probe(path).await.map_err(|_| unsupported())?;
+    let video = info.video.as_ref().ok_or_else(unsupported)?;
+    if info.duration.is_some() && video.fps.is_some() {
+        Ok(MediaFormat::OtherVideo)
+    } else {
+        Ok(MediaFormat::OtherImage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_magic() {
+        let cases: &[(&[u8], Option<MediaFormat>)] = &[
+            (
+                b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR",
+                Some(MediaFormat::Png),
+            ),
+            (b"RIFF\x24\x00\x00\x00WEBPVP8 ", Some(MediaFormat::Webp)),
+            (b"GIF89a\x01\x00\x01\x00", Some(MediaFormat::Gif)),
+            (
+                b"\x00\x00\x00\x18ftypmp42\x00\x00\x00\x00",
+                Some(MediaFormat::Mp4),
+            ),
+            (
+                b"\x00\x00\x00\x14ftypqt  \x00\x00\x00\x00",
+                Some(MediaFormat::Mov),
+            ),
+            (
+                b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00",
+                Some(MediaFormat::Heif),
+            ),
+            (
+                b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x84webm",
+                Some(MediaFormat::Webm),
+            ),
+            (
+                b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81\x01\x42\x82\x88matroska",
+                Some(MediaFormat::Matroska),
+            ),
+            (b"Hello, this is a text file.", None),
+            (b"", None),
+        ];
+        for (bytes, format) in cases {
+            assert_eq!(MediaFormat::from_magic(bytes), *format, "{:?}", bytes);
+        }
+
+        assert_eq!(MediaFormat::Webp.kind(), MediaKind::Image);
+        assert_eq!(MediaFormat::Gif.kind(), MediaKind::Animation);
+        assert_eq!(MediaFormat::Matroska.kind(), MediaKind::Video);
+        assert!(MediaFormat::Mp4.plays_in_discord());
+        assert!(!MediaFormat::Avi.plays_in_discord());
+    }
+}
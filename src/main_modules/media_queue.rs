@@ -0,0 +1,387 @@
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, EditMessage, Http, MessageId,
+    UserId,
+};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, watch};
+use tracing::warn;
+
+use super::error::BotError;
+use super::metrics::METRICS;
+
+/// How often a waiting job checks whether its place in the queue changed.
+const POSITION_INTERVAL: Duration = Duration::from_secs(2);
+/// How often a running job's status message is edited, to stay clear of rate limits.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How far along a running job is, sent from the conversion to whoever shows it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JobProgress {
+    pub stage: &'static str,
+    /// Between 0 and 1, `None` while there's no telling.
+    pub fraction: Option<f32>,
+}
+
+/// The sending half of a job's progress, passed to the conversion doing the work.
+#[derive(Clone)]
+pub struct Progress(Arc<watch::Sender<JobProgress>>);
+
+impl Progress {
+    pub fn channel() -> (Progress, watch::Receiver<JobProgress>) {
+        let (sender, receiver) = watch::channel(JobProgress {
+            stage: "Starting",
+            fraction: None,
+        });
+        (Progress(Arc::new(sender)), receiver)
+    }
+
+    /// Moves on to the next step of the job, whose progress isn't known yet.
+    pub fn stage(&self, stage: &'static str) {
+        self.0.send_replace(JobProgress {
+            stage,
+            fraction: None,
+        });
+    }
+
+    pub fn set(&self, fraction: f32) {
+        self.0
+            .send_modify(|progress| progress.fraction = Some(fraction.clamp(0.0, 1.0)));
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    waiting: VecDeque<u64>,
+    per_user: HashMap<UserId, usize>,
+    cancellations: HashMap<u64, (UserId, watch::Sender<bool>)>,
+}
+
+/// Runs media conversions a few at a time, in the order they were asked for, with a limit on how
+/// many each user can have queued or running.
+#[derive(Clone)]
+pub struct MediaQueue {
+    workers: Arc<Semaphore>,
+    max_per_user: usize,
+    state: Arc<Mutex<QueueState>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl MediaQueue {
+    pub fn new(max_jobs: usize, max_per_user: usize) -> Self {
+        MediaQueue {
+            workers: Arc::new(Semaphore::new(max_jobs)),
+            max_per_user,
+            state: Arc::new(Mutex::new(QueueState::default())),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Adds a job for `user_id` to the back of the queue, unless they already have as many as
+    /// they're allowed.
+    pub fn enqueue(&self, user_id: UserId, kind: &'static str) -> Result<Job, BotError> {
+        let mut state = self.state.lock().unwrap();
+        let jobs = state.per_user.entry(user_id).or_default();
+        if *jobs >= self.max_per_user {
+            return Err(BotError::InvalidInput(format!(
+                "You already have {} media jobs queued or running, wait for one to finish first.",
+                jobs
+            )));
+        }
+        *jobs += 1;
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (cancel, cancelled) = watch::channel(false);
+        state.waiting.push_back(id);
+        state.cancellations.insert(id, (user_id, cancel));
+        Ok(Job {
+            id,
+            kind,
+            user_id,
+            queue: self.clone(),
+            cancelled,
+        })
+    }
+
+    /// Cancels a job for whoever started it, returning false if it isn't theirs or already ended.
+    pub fn cancel(&self, job_id: u64, user_id: UserId) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.cancellations.get(&job_id) {
+            Some((owner, cancel)) if *owner == user_id => {
+                cancel.send_replace(true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// How many jobs are running and how many are waiting for a turn.
+    pub fn counts(&self) -> (usize, usize) {
+        let state = self.state.lock().unwrap();
+        let waiting = state.waiting.len();
+        (state.cancellations.len() - waiting, waiting)
+    }
+}
+
+/// A place in the queue, given up when dropped.
+pub struct Job {
+    id: u64,
+    kind: &'static str,
+    user_id: UserId,
+    queue: MediaQueue,
+    cancelled: watch::Receiver<bool>,
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        let mut state = self.queue.state.lock().unwrap();
+        state.waiting.retain(|id| *id != self.id);
+        state.cancellations.remove(&self.id);
+        if let Some(jobs) = state.per_user.get_mut(&self.user_id) {
+            *jobs -= 1;
+            if *jobs == 0 {
+                state.per_user.remove(&self.user_id);
+            }
+        }
+    }
+}
+
+impl Job {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 1 for the next job to run, `None` once this one is running.
+    fn position(&self) -> Option<usize> {
+        let state = self.queue.state.lock().unwrap();
+        state
+            .waiting
+            .iter()
+            .position(|id| *id == self.id)
+            .map(|index| index + 1)
+    }
+
+    async fn wait_for_worker(
+        &mut self,
+        status: &StatusMessage,
+    ) -> Result<OwnedSemaphorePermit, BotError> {
+        let workers = Arc::clone(&self.queue.workers);
+        if let Ok(permit) = Arc::clone(&workers).try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let acquire = workers.acquire_owned();
+        tokio::pin!(acquire);
+        let mut shown_position = None;
+        loop {
+            let position = self.position();
+            if position != shown_position {
+                if let Some(position) = position {
+                    status
+                        .show(self.id, &format!("Queued, position {} in line.", position))
+                        .await;
+                }
+                shown_position = position;
+            }
+            tokio::select! {
+                permit = &mut acquire => {
+                    return permit.map_err(|_| BotError::Cancelled);
+                }
+                _ = cancellation(&mut self.cancelled) => {
+                    return Err(BotError::Cancelled);
+                }
+                _ = tokio::time::sleep(POSITION_INTERVAL) => {}
+            }
+        }
+    }
+
+    /// Waits for a free worker, then runs the job `work` starts, keeping `status` updated with
+    /// the job's place in the queue and then its progress. Cancelling the job drops `work`,
+    /// which kills any ffmpeg process it's waiting on.
+    pub async fn run<T, F>(
+        mut self,
+        status: &StatusMessage,
+        work: impl FnOnce(Progress) -> F,
+    ) -> Result<T, BotError>
+    where
+        F: Future<Output = Result<T, BotError>>,
+    {
+        let _permit = self.wait_for_worker(status).await?;
+        self.queue
+            .state
+            .lock()
+            .unwrap()
+            .waiting
+            .retain(|id| *id != self.id);
+
+        let started = Instant::now();
+        let (progress, mut updates) = Progress::channel();
+        let work = work(progress);
+        tokio::pin!(work);
+        let mut last_edit: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                result = &mut work => {
+                    METRICS.media_job(self.kind, started.elapsed(), result.is_ok());
+                    return result;
+                }
+                _ = cancellation(&mut self.cancelled) => {
+                    return Err(BotError::Cancelled);
+                }
+                Ok(()) = updates.changed() => {
+                    if last_edit.is_some_and(|last_edit| last_edit.elapsed() < PROGRESS_INTERVAL) {
+                        continue;
+                    }
+                    let progress = *updates.borrow_and_update();
+                    status.show(self.id, &describe(&progress)).await;
+                    last_edit = Some(Instant::now());
+                }
+            }
+        }
+    }
+}
+
+/// Resolves once the job is cancelled. Waiting on `wait_for` directly would hold a lock guard
+/// across awaits, which keeps the job's future from being `Send`.
+async fn cancellation(cancelled: &mut watch::Receiver<bool>) {
+    let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+}
+
+fn describe(progress: &JobProgress) -> String {
+    match progress.fraction {
+        Some(fraction) => format!("{}... {:.0}%", progress.stage, fraction * 100.0),
+        None => format!("{}...", progress.stage),
+    }
+}
+
+/// The message a job shows its state on, with a button to cancel it.
+pub struct StatusMessage {
+    http: Arc<Http>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    label: String,
+}
+
+impl StatusMessage {
+    pub fn new(
+        http: Arc<Http>,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        label: impl Into<String>,
+    ) -> Self {
+        StatusMessage {
+            http,
+            channel_id,
+            message_id,
+            label: label.into(),
+        }
+    }
+
+    async fn show(&self, job_id: u64, state: &str) {
+        let cancel = CreateButton::new(format!("cancel_media_job:{}", job_id))
+            .label("Cancel")
+            .style(ButtonStyle::Danger);
+        let edit = EditMessage::new()
+            .content(format!("{}\n{}", self.label, state))
+            .components(vec![CreateActionRow::Buttons(vec![cancel])]);
+        if let Err(err) = self
+            .channel_id
+            .edit_message(&self.http, self.message_id, edit)
+            .await
+        {
+            warn!(error = %err, "Couldn't update a media job's status message");
+        }
+    }
+
+    /// Replaces the job's state for good, dropping the cancel button.
+    pub async fn finish(&self, edit: EditMessage) -> Result<(), BotError> {
+        self.channel_id
+            .edit_message(&self.http, self.message_id, edit.components(vec![]))
+            .await?;
+        Ok(())
+    }
+
+    /// Shows that the job ended with `err`, passing it on unless the job was just cancelled.
+    pub async fn fail(&self, err: BotError) -> Result<(), BotError> {
+        let state = match &err {
+            BotError::Cancelled => "Cancelled.".to_string(),
+            err => format!("Failed: {}", err),
+        };
+        self.finish(EditMessage::new().content(format!("{}\n{}", self.label, state)))
+            .await?;
+        match err {
+            BotError::Cancelled => Ok(()),
+            err => Err(err),
+        }
+    }
+}
+
+/// Handles the cancel button on a media job's status message.
+pub async fn handle_cancel_button(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    queue: &MediaQueue,
+) -> Result<(), BotError> {
+    let cancelled = interaction
+        .data
+        .custom_id
+        .strip_prefix("cancel_media_job:")
+        .and_then(|job_id| job_id.parse().ok())
+        .is_some_and(|job_id| queue.cancel(job_id, interaction.user.id));
+    let reply = if cancelled {
+        "Cancelling the job."
+    } else {
+        "This job already ended or isn't yours to cancel."
+    };
+    interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(reply)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_queue_limits_and_cancellation() {
+        let queue = MediaQueue::new(1, 2);
+        let user = UserId::new(1);
+        let first = queue.enqueue(user, "test").unwrap();
+        let second = queue.enqueue(user, "test").unwrap();
+        assert!(queue.enqueue(user, "test").is_err());
+        assert!(queue.enqueue(UserId::new(2), "test").is_ok());
+        assert_eq!(second.position(), Some(2));
+
+        assert!(!queue.cancel(first.id(), UserId::new(2)));
+        assert!(queue.cancel(first.id(), user));
+        drop(first);
+        assert_eq!(second.position(), Some(1));
+        assert!(!queue.cancel(1, user));
+        assert_eq!(queue.counts(), (0, 1));
+
+        drop(second);
+        assert!(queue.enqueue(user, "test").is_ok());
+    }
+
+    #[test]
+    fn test_progress() {
+        let (progress, receiver) = Progress::channel();
+        progress.stage("Converting");
+        progress.set(1.5);
+        assert_eq!(describe(&receiver.borrow()), "Converting... 100%");
+        progress.stage("Uploading");
+        assert_eq!(describe(&receiver.borrow()), "Uploading...");
+    }
+}
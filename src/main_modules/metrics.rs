@@ -0,0 +1,241 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{info, warn};
+
+use crate::Data;
+
+/// Media jobs run for seconds to minutes, so the default buckets would lump most of them together.
+const MEDIA_JOB_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+pub struct Metrics {
+    registry: Registry,
+    command_duration: HistogramVec,
+    media_job_duration: HistogramVec,
+    media_job_failures: IntCounterVec,
+    api_requests: IntCounterVec,
+    attachment_db_entries: IntGauge,
+    attachment_db_bytes: IntGauge,
+    timers: IntGaugeVec,
+}
+
+/// Recorded to from wherever commands, media jobs and API calls happen, like the attachment
+/// store, so nothing has to be passed around to reach it.
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let command_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "bot_command_duration_seconds",
+                "How long commands took to run.",
+            ),
+            &["command", "outcome"],
+        )
+        .unwrap();
+        let media_job_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "bot_media_job_duration_seconds",
+                "How long media conversions took, failed ones included.",
+            )
+            .buckets(MEDIA_JOB_BUCKETS.to_vec()),
+            &["job"],
+        )
+        .unwrap();
+        let media_job_failures = IntCounterVec::new(
+            Opts::new(
+                "bot_media_job_failures_total",
+                "Media conversions that failed.",
+            ),
+            &["job"],
+        )
+        .unwrap();
+        let api_requests = IntCounterVec::new(
+            Opts::new(
+                "bot_api_requests_total",
+                "Requests to outside APIs, by the status they got back or `error` if none.",
+            ),
+            &["service", "endpoint", "status"],
+        )
+        .unwrap();
+        let attachment_db_entries = IntGauge::new(
+            "bot_attachment_db_entries",
+            "Messages with attachments stored for deletion logs.",
+        )
+        .unwrap();
+        let attachment_db_bytes = IntGauge::new(
+            "bot_attachment_db_bytes",
+            "Size of the attachment store on disk.",
+        )
+        .unwrap();
+        let timers = IntGaugeVec::new(
+            Opts::new("bot_timers", "Pending timers, by whether they're paused."),
+            &["state"],
+        )
+        .unwrap();
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(command_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(media_job_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(media_job_failures.clone()))
+            .unwrap();
+        registry.register(Box::new(api_requests.clone())).unwrap();
+        registry
+            .register(Box::new(attachment_db_entries.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(attachment_db_bytes.clone()))
+            .unwrap();
+        registry.register(Box::new(timers.clone())).unwrap();
+
+        Metrics {
+            registry,
+            command_duration,
+            media_job_duration,
+            media_job_failures,
+            api_requests,
+            attachment_db_entries,
+            attachment_db_bytes,
+            timers,
+        }
+    }
+
+    pub fn command(&self, command: &str, outcome: &str, duration: Duration) {
+        self.command_duration
+            .with_label_values(&[command, outcome])
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn media_job(&self, job: &str, duration: Duration, succeeded: bool) {
+        self.media_job_duration
+            .with_label_values(&[job])
+            .observe(duration.as_secs_f64());
+        if !succeeded {
+            self.media_job_failures.with_label_values(&[job]).inc();
+        }
+    }
+
+    /// Counts a request to `service`, `status` being `None` when no response came back.
+    pub fn api_request(&self, service: &str, endpoint: &str, status: Option<u16>) {
+        let status = status.map_or("error".to_string(), |status| status.to_string());
+        self.api_requests
+            .with_label_values(&[service, endpoint, &status])
+            .inc();
+    }
+
+    /// Everything recorded so far in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            warn!(error = %err, "Couldn't encode metrics");
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Reads the gauges that describe the bot's current state rather than counting events.
+    async fn update_gauges(&self, data: &Data) {
+        let (entries, bytes) = {
+            let attachment_db = data.attachment_db.lock().unwrap();
+            (attachment_db.entry_count(), attachment_db.size_on_disk())
+        };
+        self.attachment_db_entries.set(entries as i64);
+        match bytes {
+            Ok(bytes) => self.attachment_db_bytes.set(bytes as i64),
+            Err(err) => warn!(error = %err, "Couldn't get the attachment store's size"),
+        }
+
+        let (active, paused) = data.timer_system.counts().await;
+        self.timers
+            .with_label_values(&["active"])
+            .set(active as i64);
+        self.timers
+            .with_label_values(&["paused"])
+            .set(paused as i64);
+    }
+}
+
+async fn respond(mut stream: TcpStream, data: &Data) -> std::io::Result<()> {
+    // Only the request line matters, and nothing worth scraping sends a large request.
+    let mut request = [0; 1024];
+    let read = stream.read(&mut request).await?;
+    let request = String::from_utf8_lossy(&request[..read]);
+    let response = if request.starts_with("GET /metrics ") {
+        METRICS.update_gauges(data).await;
+        let body = METRICS.render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Serves `/metrics` on `address` until the bot exits.
+pub async fn serve(address: SocketAddr, data: Data) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!(%address, error = %err, "Couldn't start the metrics endpoint");
+            return;
+        }
+    };
+    info!(%address, "Serving metrics");
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                warn!(error = %err, "Couldn't accept a metrics connection");
+                continue;
+            }
+        };
+        let data = data.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(stream, &data).await {
+                warn!(error = %err, "Couldn't answer a metrics request");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics.command("timed_role add", "succeeded", Duration::from_millis(250));
+        metrics.media_job("speech_bubble", Duration::from_secs(3), false);
+        metrics.api_request("Bloxlink", "discord-to-roblox", Some(404));
+        metrics.api_request("Bloxlink", "discord-to-roblox", None);
+
+        let rendered = metrics.render();
+        assert!(rendered.contains(
+            r#"bot_command_duration_seconds_count{command="timed_role add",outcome="succeeded"} 1"#
+        ));
+        assert!(rendered.contains(r#"bot_media_job_failures_total{job="speech_bubble"} 1"#));
+        assert!(
+            rendered
+                .contains(r#"bot_media_job_duration_seconds_bucket{job="speech_bubble",le="5"} 1"#)
+        );
+        assert!(rendered.contains(
+            r#"bot_api_requests_total{endpoint="discord-to-roblox",service="Bloxlink",status="404"} 1"#
+        ));
+        assert!(rendered.contains(r#"status="error"} 1"#));
+    }
+}
@@ -1,10 +1,28 @@
-use super::{UserId, CONFIG};
+use super::UserId;
 
+pub mod command_audit;
+pub mod config;
+pub mod diagnostics;
+pub mod error;
+pub mod ffmpeg;
 pub mod helper;
 pub mod timer;
+pub mod updater;
 pub mod deleted_attachments;
 pub mod media;
+pub mod media_format;
+pub mod media_queue;
 pub mod policy_updater;
 pub mod logging_database;
+pub mod mask_library;
+pub mod metrics;
 pub mod guide_updater;
-pub mod log_interactions;
\ No newline at end of file
+pub mod game_sanctions;
+pub mod guild_settings;
+pub mod log_interactions;
+pub mod mod_log;
+pub mod open_cloud;
+pub mod probation;
+pub mod permissions;
+pub mod shutdown;pub mod telemetry;
+pub mod upload_size;
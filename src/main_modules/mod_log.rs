@@ -0,0 +1,663 @@
+use poise::ChoiceParameter;
+use serenity::all::UserId;
+use std::fmt;
+use std::str::FromStr;
+
+use super::logging_database::{LogKind, LogSubject};
+use crate::commands::log_module::discord_log::DiscordInfTypes;
+use crate::commands::log_module::false_infraction::FalseInfTypes;
+use crate::commands::log_module::probation_log::ProbationTypes;
+use crate::commands::log_module::roblox_log::RobloxInfTypes;
+use crate::commands::log_module::role_log::{LogType, RoleEnums};
+
+/// A Roblox account as written in logs, `username:id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RobloxUser {
+    pub username: String,
+    pub id: u64,
+}
+
+impl fmt::Display for RobloxUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.username, self.id)
+    }
+}
+
+/// A user line in a log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogUser {
+    /// `<@id>:id`
+    Discord(UserId),
+    /// `<@id>:id - username:id`, or `<@id>:id - null:null` when Bloxlink had no linked account.
+    Linked {
+        discord_id: UserId,
+        roblox: Option<RobloxUser>,
+    },
+    /// `username:id`
+    Roblox(RobloxUser),
+}
+
+impl LogUser {
+    pub fn subject(&self) -> LogSubject {
+        match self {
+            LogUser::Discord(discord_id) => LogSubject::discord(*discord_id),
+            LogUser::Linked { discord_id, roblox } => LogSubject {
+                discord_id: Some(discord_id.get()),
+                roblox_id: roblox.as_ref().map(|roblox| roblox.id),
+            },
+            LogUser::Roblox(roblox) => LogSubject::roblox(roblox.id),
+        }
+    }
+}
+
+impl fmt::Display for LogUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogUser::Discord(discord_id) => write!(f, "<@{}>:{}", discord_id, discord_id),
+            LogUser::Linked {
+                discord_id,
+                roblox: Some(roblox),
+            } => write!(f, "<@{}>:{} - {}", discord_id, discord_id, roblox),
+            LogUser::Linked {
+                discord_id,
+                roblox: None,
+            } => write!(f, "<@{}>:{} - null:null", discord_id, discord_id),
+            LogUser::Roblox(roblox) => write!(f, "{}", roblox),
+        }
+    }
+}
+
+fn parse_id(id: &str) -> Result<u64, String> {
+    id.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .ok_or_else(|| format!("`{}` isn't a valid id", id))
+}
+
+impl FromStr for RobloxUser {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (username, id) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("couldn't parse user `{}`", s))?;
+        Ok(RobloxUser {
+            username: username.trim().to_string(),
+            id: parse_id(id)?,
+        })
+    }
+}
+
+impl FromStr for LogUser {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (user, linked) = match s.split_once(" - ") {
+            Some((user, linked)) => (user.trim(), Some(linked.trim())),
+            None => (s.trim(), None),
+        };
+
+        if !user.starts_with("<@") {
+            return match linked {
+                None => Ok(LogUser::Roblox(user.parse()?)),
+                Some(_) => Err(format!("couldn't parse user `{}`", s)),
+            };
+        }
+
+        let (_, discord_id) = user
+            .rsplit_once(':')
+            .ok_or_else(|| format!("couldn't parse user `{}`", s))?;
+        let discord_id = UserId::new(parse_id(discord_id)?);
+        match linked {
+            None => Ok(LogUser::Discord(discord_id)),
+            Some("null:null") => Ok(LogUser::Linked {
+                discord_id,
+                roblox: None,
+            }),
+            Some(roblox) => Ok(LogUser::Linked {
+                discord_id,
+                roblox: Some(roblox.parse()?),
+            }),
+        }
+    }
+}
+
+/// How a false infraction log refers to a moderator. When several moderators are logged at
+/// once only the last one is mentioned, the rest are written by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogModerator {
+    Mention(UserId),
+    Named { name: String, id: UserId },
+}
+
+impl fmt::Display for LogModerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogModerator::Mention(id) => write!(f, "<@{}>:{}", id, id),
+            LogModerator::Named { name, id } => write!(f, "{}:{}", name, id),
+        }
+    }
+}
+
+impl FromStr for LogModerator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, id) = s
+            .rsplit_once(':')
+            .ok_or_else(|| format!("couldn't parse moderator `{}`", s))?;
+        let id = UserId::new(parse_id(id)?);
+        if name.starts_with("<@") {
+            Ok(LogModerator::Mention(id))
+        } else {
+            Ok(LogModerator::Named {
+                name: name.trim().to_string(),
+                id,
+            })
+        }
+    }
+}
+
+/// A moderation log as posted by the log commands.
+///
+/// `Display` produces the exact bracketed text the bot posts and `FromStr` reads it back, so
+/// every log the bot writes can be turned back into a `ModLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModLog {
+    Discord {
+        infraction: DiscordInfTypes,
+        users: Vec<LogUser>,
+        reason: String,
+        note: Option<String>,
+    },
+    Roblox {
+        infraction: RobloxInfTypes,
+        users: Vec<RobloxUser>,
+        reason: String,
+        note: Option<String>,
+    },
+    Probation {
+        infraction: ProbationTypes,
+        user: LogUser,
+        reason: String,
+        duration: String,
+    },
+    Role {
+        change: LogType,
+        role: RoleEnums,
+        user: LogUser,
+        reason: Option<String>,
+    },
+    FalseInfraction {
+        infraction: FalseInfTypes,
+        moderator: LogModerator,
+        affected: Vec<LogUser>,
+        reason: String,
+        invalidation_reason: String,
+    },
+}
+
+impl ModLog {
+    pub fn kind(&self) -> LogKind {
+        match self {
+            ModLog::Discord { .. } => LogKind::Discord,
+            ModLog::Roblox { .. } => LogKind::Roblox,
+            ModLog::Probation { .. } => LogKind::Probation,
+            ModLog::Role { .. } => LogKind::Role,
+            ModLog::FalseInfraction { .. } => LogKind::FalseInfraction,
+        }
+    }
+
+    pub fn log_type(&self) -> String {
+        match self {
+            ModLog::Discord { infraction, .. } => infraction.name().to_string(),
+            ModLog::Roblox { infraction, .. } => infraction.name().to_string(),
+            ModLog::Probation { infraction, .. } => infraction.name().to_string(),
+            ModLog::Role { change, role, .. } => {
+                format!("Role {}: {}", change.name(), role.name())
+            }
+            ModLog::FalseInfraction { infraction, .. } => infraction.name().to_string(),
+        }
+    }
+
+    pub fn subjects(&self) -> Vec<LogSubject> {
+        match self {
+            ModLog::Discord { users, .. } => users.iter().map(LogUser::subject).collect(),
+            ModLog::Roblox { users, .. } => users
+                .iter()
+                .map(|user| LogSubject::roblox(user.id))
+                .collect(),
+            ModLog::Probation { user, .. } | ModLog::Role { user, .. } => vec![user.subject()],
+            ModLog::FalseInfraction { affected, .. } => {
+                affected.iter().map(LogUser::subject).collect()
+            }
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            ModLog::Discord { reason, .. }
+            | ModLog::Roblox { reason, .. }
+            | ModLog::Probation { reason, .. }
+            | ModLog::FalseInfraction { reason, .. } => reason,
+            ModLog::Role { reason, .. } => reason.as_deref().unwrap_or_default(),
+        }
+    }
+
+    /// The extra text stored alongside the reason: the note, the probation duration or the
+    /// reason a false infraction was invalidated.
+    pub fn note(&self) -> Option<&str> {
+        match self {
+            ModLog::Discord { note, .. } | ModLog::Roblox { note, .. } => note.as_deref(),
+            ModLog::Probation { duration, .. } => Some(duration),
+            ModLog::Role { .. } => None,
+            ModLog::FalseInfraction {
+                invalidation_reason,
+                ..
+            } => Some(invalidation_reason),
+        }
+    }
+
+    /// Whether the log is a new infraction against its subjects, rather than a lifted one, a
+    /// probation or a correction.
+    pub fn is_infraction(&self) -> bool {
+        match self {
+            ModLog::Discord { .. } => true,
+            ModLog::Roblox { infraction, .. } => *infraction != RobloxInfTypes::Unban,
+            ModLog::Probation { .. } | ModLog::Role { .. } | ModLog::FalseInfraction { .. } => {
+                false
+            }
+        }
+    }
+}
+
+fn write_note(f: &mut fmt::Formatter<'_>, note: &Option<String>) -> fmt::Result {
+    match note {
+        Some(note) => write!(f, "\nNote: {}", note),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for ModLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModLog::Discord {
+                infraction,
+                users,
+                reason,
+                note,
+            } => {
+                writeln!(f, "[{}]", infraction.name())?;
+                for user in users {
+                    writeln!(f, "[{}]", user)?;
+                }
+                write!(f, "[{}]", reason)?;
+                write_note(f, note)
+            }
+            ModLog::Roblox {
+                infraction,
+                users,
+                reason,
+                note,
+            } => {
+                writeln!(f, "[{}]", infraction.name())?;
+                for user in users {
+                    writeln!(f, "[{}]", user)?;
+                }
+                write!(f, "[{}]", reason)?;
+                write_note(f, note)
+            }
+            ModLog::Probation {
+                infraction,
+                user,
+                reason,
+                duration,
+            } => write!(
+                f,
+                "[{}]\n\n[{}]\n\n[{}]\n\n[{}]",
+                infraction.name(),
+                user,
+                reason,
+                duration
+            ),
+            ModLog::Role {
+                change,
+                role,
+                user,
+                reason,
+            } => {
+                write!(f, "[{}]\n[{}]\n[{}]", change.name(), role.name(), user)?;
+                match reason {
+                    Some(reason) => write!(f, "\n[{}]", reason),
+                    None => Ok(()),
+                }
+            }
+            ModLog::FalseInfraction {
+                infraction,
+                moderator,
+                affected,
+                reason,
+                invalidation_reason,
+            } => {
+                write!(f, "[{}]\n[{}]", infraction.name(), moderator)?;
+                for user in affected {
+                    write!(f, "\n[{}]", user)?;
+                }
+                write!(f, "\n[{}]\n[{}]", reason, invalidation_reason)
+            }
+        }
+    }
+}
+
+/// Splits a log into its bracketed fields and trailing `Note:`, allowing fields that span
+/// several lines (modal reasons can).
+fn split_fields(s: &str) -> Result<(Vec<String>, Option<String>), String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut open_field: Option<String> = None;
+    let mut note: Option<String> = None;
+
+    for line in s.lines() {
+        if let Some(note) = &mut note {
+            note.push('\n');
+            note.push_str(line);
+            continue;
+        }
+        let line = line.trim();
+        if let Some(mut field) = open_field.take() {
+            field.push('\n');
+            match line.strip_suffix(']') {
+                Some(rest) => {
+                    field.push_str(rest);
+                    fields.push(field);
+                }
+                None => {
+                    field.push_str(line);
+                    open_field = Some(field);
+                }
+            }
+        } else if line.is_empty() {
+            continue;
+        } else if let Some(rest) = line.strip_prefix('[') {
+            match rest.strip_suffix(']') {
+                Some(field) => fields.push(field.to_string()),
+                None => open_field = Some(rest.to_string()),
+            }
+        } else if let Some(rest) = line.strip_prefix("Note:") {
+            note = Some(rest.trim_start().to_string());
+        } else {
+            return Err(format!("line `{}` isn't bracketed", line));
+        }
+    }
+
+    if open_field.is_some() {
+        return Err("log has an unclosed bracket".to_string());
+    }
+    Ok((fields, note.map(|note| note.trim_end().to_string())))
+}
+
+fn parse_users<T: FromStr<Err = String>>(fields: &[String]) -> Result<Vec<T>, String> {
+    if fields.is_empty() {
+        return Err("no users in log".to_string());
+    }
+    fields.iter().map(|field| field.parse()).collect()
+}
+
+impl FromStr for ModLog {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (fields, note) = split_fields(s)?;
+        let Some((log_type, rest)) = fields.split_first() else {
+            return Err("message is empty".to_string());
+        };
+
+        if let Some(change) = LogType::from_name(log_type) {
+            let (role, user, reason) = match rest {
+                [role, user] => (role, user, None),
+                [role, user, reason] => (role, user, Some(reason.clone())),
+                _ => return Err("role logs need a role, a user and an optional reason".to_string()),
+            };
+            return Ok(ModLog::Role {
+                change,
+                role: RoleEnums::from_name(role)
+                    .ok_or_else(|| format!("unknown role `{}`", role))?,
+                user: user.parse()?,
+                reason,
+            });
+        }
+
+        if let Some(infraction) = FalseInfTypes::from_name(log_type) {
+            let [moderator, affected @ .., reason, invalidation_reason] = rest else {
+                return Err(
+                    "false infraction logs need a moderator, users and two reasons".to_string(),
+                );
+            };
+            return Ok(ModLog::FalseInfraction {
+                infraction,
+                moderator: moderator.parse()?,
+                affected: parse_users(affected)?,
+                reason: reason.clone(),
+                invalidation_reason: invalidation_reason.clone(),
+            });
+        }
+
+        if let Some(infraction) = ProbationTypes::from_name(log_type) {
+            let [user, reason, duration] = rest else {
+                return Err("probation logs need a user, a reason and a duration".to_string());
+            };
+            return Ok(ModLog::Probation {
+                infraction,
+                user: user.parse()?,
+                reason: reason.clone(),
+                duration: duration.clone(),
+            });
+        }
+
+        let Some((reason, users)) = rest.split_last() else {
+            return Err("log has no reason".to_string());
+        };
+        let reason = reason.clone();
+        let users: Vec<LogUser> = parse_users(users)?;
+
+        // Kick and Warn exist on both sides, a log of only Roblox users is a game log.
+        let roblox_users: Option<Vec<RobloxUser>> = users
+            .iter()
+            .map(|user| match user {
+                LogUser::Roblox(roblox) => Some(roblox.clone()),
+                _ => None,
+            })
+            .collect();
+        match (
+            RobloxInfTypes::from_name(log_type),
+            roblox_users,
+            DiscordInfTypes::from_name(log_type),
+        ) {
+            (Some(infraction), Some(users), _) => Ok(ModLog::Roblox {
+                infraction,
+                users,
+                reason,
+                note,
+            }),
+            (_, _, Some(infraction)) => Ok(ModLog::Discord {
+                infraction,
+                users,
+                reason,
+                note,
+            }),
+            (Some(_), None, None) => Err("game logs can only contain Roblox users".to_string()),
+            (None, _, None) => Err(format!("unknown log type `{}`", log_type)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn choice<T: ChoiceParameter + fmt::Debug>() -> impl Strategy<Value = T> {
+        (0..T::list().len()).prop_map(|index| T::from_index(index).unwrap())
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        "[A-Za-z0-9,.!?'()]{1,12}( [A-Za-z0-9,.!?'()]{1,12}){0,5}"
+    }
+
+    fn roblox_user() -> impl Strategy<Value = RobloxUser> {
+        ("[A-Za-z0-9_]{3,20}", 1..u64::MAX).prop_map(|(username, id)| RobloxUser { username, id })
+    }
+
+    fn discord_id() -> impl Strategy<Value = UserId> {
+        (1..u64::MAX).prop_map(UserId::new)
+    }
+
+    fn discord_user() -> impl Strategy<Value = LogUser> {
+        prop_oneof![
+            discord_id().prop_map(LogUser::Discord),
+            (discord_id(), proptest::option::of(roblox_user()))
+                .prop_map(|(discord_id, roblox)| LogUser::Linked { discord_id, roblox }),
+        ]
+    }
+
+    fn any_user() -> impl Strategy<Value = LogUser> {
+        prop_oneof![discord_user(), roblox_user().prop_map(LogUser::Roblox)]
+    }
+
+    fn moderator() -> impl Strategy<Value = LogModerator> {
+        prop_oneof![
+            discord_id().prop_map(LogModerator::Mention),
+            ("[a-z0-9_.]{2,32}", discord_id())
+                .prop_map(|(name, id)| LogModerator::Named { name, id }),
+        ]
+    }
+
+    fn mod_log() -> impl Strategy<Value = ModLog> {
+        prop_oneof![
+            (
+                choice::<DiscordInfTypes>(),
+                prop::collection::vec(discord_user(), 1..5),
+                text(),
+                proptest::option::of(text())
+            )
+                .prop_map(|(infraction, users, reason, note)| ModLog::Discord {
+                    infraction,
+                    users,
+                    reason,
+                    note
+                }),
+            (
+                choice::<RobloxInfTypes>(),
+                prop::collection::vec(roblox_user(), 1..5),
+                text(),
+                proptest::option::of(text())
+            )
+                .prop_map(|(infraction, users, reason, note)| ModLog::Roblox {
+                    infraction,
+                    users,
+                    reason,
+                    note
+                }),
+            (choice::<ProbationTypes>(), discord_user(), text(), text()).prop_map(
+                |(infraction, user, reason, duration)| ModLog::Probation {
+                    infraction,
+                    user,
+                    reason,
+                    duration
+                }
+            ),
+            (
+                choice::<LogType>(),
+                choice::<RoleEnums>(),
+                discord_user(),
+                proptest::option::of(text())
+            )
+                .prop_map(|(change, role, user, reason)| ModLog::Role {
+                    change,
+                    role,
+                    user,
+                    reason
+                }),
+            (
+                choice::<FalseInfTypes>(),
+                moderator(),
+                prop::collection::vec(any_user(), 1..5),
+                text(),
+                text()
+            )
+                .prop_map(
+                    |(infraction, moderator, affected, reason, invalidation_reason)| {
+                        ModLog::FalseInfraction {
+                            infraction,
+                            moderator,
+                            affected,
+                            reason,
+                            invalidation_reason,
+                        }
+                    }
+                ),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(log in mod_log()) {
+            let parsed: ModLog = log.to_string().parse().unwrap();
+            prop_assert_eq!(parsed, log);
+        }
+
+        #[test]
+        fn test_multiline_reason_round_trip(first in text(), second in text()) {
+            let log = ModLog::Roblox {
+                infraction: RobloxInfTypes::Warn,
+                users: vec![RobloxUser { username: "builderman".to_string(), id: 156 }],
+                reason: format!("{}\n{}", first, second),
+                note: None,
+            };
+            let parsed: ModLog = log.to_string().parse().unwrap();
+            prop_assert_eq!(parsed, log);
+        }
+    }
+
+    #[test]
+    fn test_parse_posted_logs() {
+        let game_ban: ModLog =
+            "[Game Ban]\n[builderman:156]\n[roblox:1]\n[Exploiting]\nNote: Flew around"
+                .parse()
+                .unwrap();
+        assert_eq!(game_ban.kind(), LogKind::Roblox);
+        assert_eq!(
+            game_ban.subjects(),
+            vec![LogSubject::roblox(156), LogSubject::roblox(1)]
+        );
+        assert_eq!(game_ban.note(), Some("Flew around"));
+
+        let warn: ModLog = "[Warn]\n[<@123456789012345678>:123456789012345678]\n[Spam]"
+            .parse()
+            .unwrap();
+        assert_eq!(warn.kind(), LogKind::Discord);
+
+        let probation: ModLog =
+            "[Roblox Ban]\n\n[<@123456789012345678>:123456789012345678 - null:null]\n\n[Appealed]\n\n[1 week (<t:1:D> - <t:2:D>)]"
+                .parse()
+                .unwrap();
+        assert_eq!(probation.subjects()[0].roblox_id, None);
+        assert_eq!(probation.note(), Some("1 week (<t:1:D> - <t:2:D>)"));
+    }
+
+    #[test]
+    fn test_parse_rejects_non_logs() {
+        assert!("Making logs, please standby!".parse::<ModLog>().is_err());
+        assert!(
+            "[Dance]\n[builderman:156]\n[Fun]"
+                .parse::<ModLog>()
+                .is_err()
+        );
+        assert!("[Kick]\n[builderman]\n[No id]".parse::<ModLog>().is_err());
+        assert!(
+            "[Game Ban]\n[<@5>:5]\n[Wrong side]"
+                .parse::<ModLog>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+use tracing::{instrument, warn};
+
+use super::config::GameConfig;
+use super::game_sanctions::GameSanction;
+use super::metrics::METRICS;
+
+#[derive(Debug)]
+pub enum OpenCloudError {
+    Request(reqwest::Error),
+    Status { status: StatusCode, body: String },
+    Serialization(serde_json::Error),
+}
+
+impl fmt::Display for OpenCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenCloudError::Request(err) => write!(f, "request to Open Cloud failed: {}", err),
+            OpenCloudError::Status { status, body } => {
+                write!(f, "Open Cloud responded with {}: {}", status, body)
+            }
+            OpenCloudError::Serialization(err) => {
+                write!(f, "couldn't serialize Open Cloud payload: {}", err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpenCloudError {}
+
+/// What the game reads from the ban DataStore when a player joins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BanRecord {
+    pub banned: bool,
+    pub reason: String,
+    pub moderator_id: u64,
+    pub issued_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl From<&GameSanction> for BanRecord {
+    fn from(sanction: &GameSanction) -> Self {
+        BanRecord {
+            banned: true,
+            reason: sanction.reason.clone(),
+            moderator_id: sanction.moderator_id,
+            issued_at: sanction.issued_at,
+            expires_at: sanction.expires_at,
+        }
+    }
+}
+
+/// Published to live servers so a sanction applies without the player rejoining.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationMessage {
+    Ban {
+        user_id: u64,
+        reason: String,
+        expires_at: Option<u64>,
+    },
+    Warn {
+        user_id: u64,
+        reason: String,
+    },
+    Unban {
+        user_id: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct PublishRequest {
+    message: String,
+}
+
+/// Client for the Roblox Open Cloud DataStore and MessagingService endpoints the game reads
+/// moderation actions from.
+#[derive(Clone)]
+pub struct OpenCloudClient {
+    http: Client,
+    base_url: String,
+    api_key: String,
+    universe_id: String,
+    ban_datastore: String,
+    moderation_topic: String,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+fn should_retry(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+impl OpenCloudClient {
+    pub fn new(
+        http: Client,
+        base_url: &str,
+        api_key: &str,
+        universe_id: &str,
+        ban_datastore: &str,
+        moderation_topic: &str,
+    ) -> Self {
+        OpenCloudClient {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            universe_id: universe_id.to_string(),
+            ban_datastore: ban_datastore.to_string(),
+            moderation_topic: moderation_topic.to_string(),
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+
+    pub fn from_config(http: Client, game: &GameConfig) -> Self {
+        OpenCloudClient::new(
+            http,
+            &game.open_cloud_base_url,
+            &game.open_cloud_api_key,
+            &game.universe_id,
+            &game.ban_datastore_name,
+            &game.moderation_topic,
+        )
+    }
+
+    /// Retries rate limits, server errors and failed connections, waiting `retry_delay` and
+    /// doubling it each time.
+    async fn send(
+        &self,
+        endpoint: &str,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response, OpenCloudError> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        loop {
+            let result = request().header("x-api-key", &self.api_key).send().await;
+            METRICS.api_request(
+                "Open Cloud",
+                endpoint,
+                result
+                    .as_ref()
+                    .ok()
+                    .map(|response| response.status().as_u16()),
+            );
+            let retryable = match &result {
+                Ok(response) => should_retry(response.status()),
+                Err(err) => err.is_connect() || err.is_timeout(),
+            };
+            if !retryable || attempt >= self.max_retries {
+                let response = result.map_err(OpenCloudError::Request)?;
+                if response.status().is_success() {
+                    return Ok(response);
+                }
+                return Err(OpenCloudError::Status {
+                    status: response.status(),
+                    body: response.text().await.unwrap_or_default(),
+                });
+            }
+
+            attempt += 1;
+            let reason = match &result {
+                Ok(response) => response.status().to_string(),
+                Err(err) => err.to_string(),
+            };
+            warn!(
+                reason,
+                attempt,
+                ?delay,
+                "Open Cloud request failed, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    fn entry_url(&self) -> String {
+        format!(
+            "{}/datastores/v1/universes/{}/standard-datastores/datastore/entries/entry",
+            self.base_url, self.universe_id
+        )
+    }
+
+    pub async fn set_entry<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), OpenCloudError> {
+        let body = serde_json::to_string(value).map_err(OpenCloudError::Serialization)?;
+        self.send("datastore", || {
+            self.http
+                .post(self.entry_url())
+                .query(&[
+                    ("datastoreName", self.ban_datastore.as_str()),
+                    ("entryKey", key),
+                ])
+                .header("content-type", "application/json")
+                .body(body.clone())
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Removes an entry, treating one that is already gone as removed.
+    pub async fn delete_entry(&self, key: &str) -> Result<(), OpenCloudError> {
+        let result = self
+            .send("datastore", || {
+                self.http.delete(self.entry_url()).query(&[
+                    ("datastoreName", self.ban_datastore.as_str()),
+                    ("entryKey", key),
+                ])
+            })
+            .await;
+        match result {
+            Err(OpenCloudError::Status { status, .. }) if status == StatusCode::NOT_FOUND => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+
+    pub async fn publish(&self, message: &ModerationMessage) -> Result<(), OpenCloudError> {
+        let body = PublishRequest {
+            message: serde_json::to_string(message).map_err(OpenCloudError::Serialization)?,
+        };
+        let url = format!(
+            "{}/messaging-service/v1/universes/{}/topics/{}",
+            self.base_url, self.universe_id, self.moderation_topic
+        );
+        self.send("messaging", || self.http.post(&url).json(&body))
+            .await?;
+        Ok(())
+    }
+
+    /// Writes the ban where joining players are checked and kicks the player from live servers.
+    #[instrument(name = "open_cloud_ban", skip_all, fields(roblox_id = sanction.roblox_id))]
+    pub async fn ban(&self, sanction: &GameSanction) -> Result<(), OpenCloudError> {
+        self.set_entry(&sanction.roblox_id.to_string(), &BanRecord::from(sanction))
+            .await?;
+        self.publish(&ModerationMessage::Ban {
+            user_id: sanction.roblox_id,
+            reason: sanction.reason.clone(),
+            expires_at: sanction.expires_at,
+        })
+        .await
+    }
+
+    /// Clears the player's ban so they can join again and tells live servers it was lifted.
+    #[instrument(name = "open_cloud_unban", skip(self))]
+    pub async fn unban(&self, roblox_id: u64) -> Result<(), OpenCloudError> {
+        self.delete_entry(&roblox_id.to_string()).await?;
+        self.publish(&ModerationMessage::Unban { user_id: roblox_id })
+            .await
+    }
+
+    #[instrument(name = "open_cloud_warn", skip_all, fields(roblox_id = sanction.roblox_id))]
+    pub async fn warn(&self, sanction: &GameSanction) -> Result<(), OpenCloudError> {
+        self.publish(&ModerationMessage::Warn {
+            user_id: sanction.roblox_id,
+            reason: sanction.reason.clone(),
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_modules::game_sanctions::SanctionKind;
+    use mockito::Matcher;
+    use serenity::all::UserId;
+
+    fn test_client(server: &mockito::Server) -> OpenCloudClient {
+        let mut client = OpenCloudClient::new(
+            Client::new(),
+            &server.url(),
+            "key",
+            "42",
+            "Bans",
+            "Moderation",
+        );
+        client.max_retries = 2;
+        client.retry_delay = Duration::from_millis(1);
+        client
+    }
+
+    fn sanction() -> GameSanction {
+        GameSanction::new(SanctionKind::Ban, 156, UserId::new(1), "exploiting", 10).expires_at(20)
+    }
+
+    #[tokio::test]
+    async fn test_ban_writes_entry_and_publishes() {
+        let mut server = mockito::Server::new_async().await;
+        let entry = server
+            .mock(
+                "POST",
+                "/datastores/v1/universes/42/standard-datastores/datastore/entries/entry",
+            )
+            .match_header("x-api-key", "key")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("datastoreName".into(), "Bans".into()),
+                Matcher::UrlEncoded("entryKey".into(), "156".into()),
+            ]))
+            .match_body(Matcher::Json(
+                serde_json::to_value(BanRecord::from(&sanction())).unwrap(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+        let publish = server
+            .mock("POST", "/messaging-service/v1/universes/42/topics/Moderation")
+            .match_header("x-api-key", "key")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"message": "{\"action\":\"ban\",\"user_id\":156,\"reason\":\"exploiting\",\"expires_at\":20}"}"#.to_string(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        test_client(&server).ban(&sanction()).await.unwrap();
+        entry.assert_async().await;
+        publish.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_unban_tolerates_missing_entry() {
+        let mut server = mockito::Server::new_async().await;
+        let entry = server
+            .mock(
+                "DELETE",
+                "/datastores/v1/universes/42/standard-datastores/datastore/entries/entry",
+            )
+            .match_query(Matcher::UrlEncoded("entryKey".into(), "156".into()))
+            .with_status(404)
+            .create_async()
+            .await;
+        let publish = server
+            .mock("POST", "/messaging-service/v1/universes/42/topics/Moderation")
+            .match_body(Matcher::PartialJsonString(
+                r#"{"message": "{\"action\":\"unban\",\"user_id\":156}"}"#.to_string(),
+            ))
+            .with_status(200)
+            .create_async()
+            .await;
+
+        test_client(&server).unban(156).await.unwrap();
+        entry.assert_async().await;
+        publish.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/messaging-service/v1/universes/42/topics/Moderation";
+        let unavailable = server
+            .mock("POST", path)
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let recovered = server
+            .mock("POST", path)
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        test_client(&server).warn(&sanction()).await.unwrap();
+        unavailable.assert_async().await;
+        recovered.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_retries() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("POST", Matcher::Any)
+            .with_status(429)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let result = test_client(&server).warn(&sanction()).await;
+        assert!(matches!(
+            result,
+            Err(OpenCloudError::Status { status, .. }) if status == StatusCode::TOO_MANY_REQUESTS
+        ));
+        rate_limited.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let forbidden = server
+            .mock("POST", Matcher::Any)
+            .with_status(403)
+            .with_body("Invalid API key")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let err = test_client(&server).ban(&sanction()).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Open Cloud responded with 403 Forbidden: Invalid API key"
+        );
+        forbidden.assert_async().await;
+    }
+}
@@ -0,0 +1,115 @@
+use serenity::all::RoleId;
+use std::fmt;
+use std::str::FromStr;
+
+use super::config::Config;
+use super::guild_settings::GuildSettings;
+
+/// Who can run a command, each tier also granting everything below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionTier {
+    Staff,
+    Moderator,
+    Admin,
+}
+
+impl PermissionTier {
+    pub const ALL: [PermissionTier; 3] = [
+        PermissionTier::Staff,
+        PermissionTier::Moderator,
+        PermissionTier::Admin,
+    ];
+
+    /// The roles given this tier in the config. A guild's own admin roles only make someone a
+    /// `GuildAdmin` of it.
+    pub fn role_ids(&self, config: &Config) -> Vec<RoleId> {
+        match self {
+            PermissionTier::Staff => config.permissions.staff_role_ids.clone(),
+            PermissionTier::Moderator => config.permissions.moderator_role_ids.clone(),
+            PermissionTier::Admin => config.main.admin_role_ids.clone(),
+        }
+    }
+
+    /// The highest tier any of the roles is given.
+    pub fn highest(config: &Config, roles: &[RoleId]) -> Option<PermissionTier> {
+        PermissionTier::ALL.into_iter().rev().find(|tier| {
+            tier.role_ids(config)
+                .iter()
+                .any(|role_id| roles.contains(role_id))
+        })
+    }
+}
+
+/// Someone with one of a guild's own admin roles, which lets them manage that guild's settings
+/// and nothing bot-wide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuildAdmin;
+
+impl GuildAdmin {
+    /// Whether any of the roles, which must be from the guild `settings` belong to, is one of
+    /// its admin roles.
+    pub fn holds(settings: &GuildSettings, roles: &[RoleId]) -> bool {
+        settings
+            .admin_role_ids
+            .iter()
+            .any(|role_id| roles.contains(role_id))
+    }
+}
+
+impl fmt::Display for PermissionTier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermissionTier::Staff => write!(f, "Staff"),
+            PermissionTier::Moderator => write!(f, "Moderator"),
+            PermissionTier::Admin => write!(f, "Admin"),
+        }
+    }
+}
+
+/// Commands name the tier they need as their category.
+impl FromStr for PermissionTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PermissionTier::ALL
+            .into_iter()
+            .find(|tier| tier.to_string() == s)
+            .ok_or_else(|| format!("`{}` isn't a permission tier", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::main_modules::config::tests::VALID;
+    use crate::main_modules::guild_settings::GuildSettingsDB;
+    use serenity::all::GuildId;
+
+    #[test]
+    fn test_highest_tier() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = GuildSettingsDB::init(dir.path().to_str().unwrap()).unwrap();
+        let config = Config::parse(VALID).ok().unwrap();
+        let appeals = GuildId::new(5000);
+        db.set_admin_roles(appeals, &[RoleId::new(7000)]).unwrap();
+        let main = db.get(config.main.guild_id, &config).unwrap();
+        let appeals = db.get(appeals, &config).unwrap();
+
+        let roles = |roles: &[u64]| roles.iter().copied().map(RoleId::new).collect::<Vec<_>>();
+        let highest = |ids: &[u64]| PermissionTier::highest(&config, &roles(ids));
+        assert_eq!(highest(&[1]), None);
+        assert_eq!(highest(&[2100]), Some(PermissionTier::Staff));
+        assert_eq!(highest(&[2100, 2201]), Some(PermissionTier::Moderator));
+        assert_eq!(highest(&[2001]), Some(PermissionTier::Admin));
+        assert_eq!(highest(&[2000]), Some(PermissionTier::Admin));
+
+        // An appeals server admin can manage that server's settings, but isn't a bot-wide Admin.
+        assert_eq!(highest(&[7000]), None);
+        assert!(GuildAdmin::holds(&appeals, &roles(&[7000])));
+        assert!(!GuildAdmin::holds(&main, &roles(&[7000])));
+        assert!(!GuildAdmin::holds(&appeals, &roles(&[2001])));
+
+        assert_eq!("Moderator".parse(), Ok(PermissionTier::Moderator));
+        assert!("Owner".parse::<PermissionTier>().is_err());
+    }
+}
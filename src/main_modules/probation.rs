@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use sled::transaction::Transactional;
+use sled::{Db, Tree};
+use std::sync::Arc;
+
+use super::logging_database::{LogSubject, flatten_transaction_error};
+use super::mod_log::ModLog;
+
+/// Key the callback posting probation expiry notifications is registered under in the timer
+/// system.
+pub const PROBATION_EXPIRY: &str = "probation_expiry";
+
+/// A probation window logged with `/probationlog`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Probation {
+    pub id: u64,
+    pub discord_id: u64,
+    pub roblox_id: Option<u64>,
+    pub probation_type: String,
+    pub reason: String,
+    pub moderator_id: u64,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Probation {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.start <= now && now < self.end
+    }
+
+    /// Posted in the probation channel once the window is over.
+    pub fn expiry_notice(&self) -> String {
+        format!(
+            "Probation ended for <@{}>:{} ({}, <t:{}:D> - <t:{}:D>): {}",
+            self.discord_id,
+            self.discord_id,
+            self.probation_type,
+            self.start,
+            self.end,
+            self.reason
+        )
+    }
+
+    /// Posted when someone on probation is logged for another infraction.
+    pub fn escalation_notice(&self) -> String {
+        format!(
+            "**Escalation:** <@{}> is on probation ({}) until <t:{}:f> for: {}",
+            self.discord_id, self.probation_type, self.end, self.reason
+        )
+    }
+}
+
+/// Probations keyed by `discord id ++ probation id`, with `roblox_index` holding
+/// `roblox id ++ probation id` keys pointing back at the Discord id so game logs can be checked
+/// against them too.
+#[derive(Clone)]
+pub struct ProbationDB {
+    db: Arc<Db>,
+    probations: Tree,
+    roblox_index: Tree,
+}
+
+fn probation_key(subject_id: u64, probation_id: u64) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..8].copy_from_slice(&subject_id.to_be_bytes());
+    key[8..].copy_from_slice(&probation_id.to_be_bytes());
+    key
+}
+
+impl ProbationDB {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = ProbationDB {
+            probations: db.open_tree("probations")?,
+            roblox_index: db.open_tree("roblox_index")?,
+            db: Arc::clone(&db),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Stores a probation, returning the id it was given.
+    pub fn add(&self, mut probation: Probation) -> sled::Result<u64> {
+        probation.id = self.db.generate_id()?;
+        let serialized = bincode::serialize(&probation)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+
+        (&self.probations, &self.roblox_index)
+            .transaction(|(probations, roblox_index)| {
+                probations.insert(
+                    probation_key(probation.discord_id, probation.id).to_vec(),
+                    serialized.as_slice(),
+                )?;
+                if let Some(roblox_id) = probation.roblox_id {
+                    roblox_index.insert(
+                        probation_key(roblox_id, probation.id).to_vec(),
+                        probation.discord_id.to_be_bytes().to_vec(),
+                    )?;
+                }
+                Ok(())
+            })
+            .map_err(flatten_transaction_error)?;
+
+        Ok(probation.id)
+    }
+
+    pub fn get(&self, discord_id: u64, probation_id: u64) -> sled::Result<Option<Probation>> {
+        match self
+            .probations
+            .get(probation_key(discord_id, probation_id))?
+        {
+            Some(value) => {
+                let probation: Probation = bincode::deserialize(&value)
+                    .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+                Ok(Some(probation))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Every probation the user has been put on, oldest first.
+    pub fn get_by_discord_id(&self, discord_id: u64) -> sled::Result<Vec<Probation>> {
+        let mut probations = Vec::new();
+
+        for result in self.probations.scan_prefix(discord_id.to_be_bytes()) {
+            let (_, value) = result?;
+            let probation: Probation = bincode::deserialize(&value)
+                .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+            probations.push(probation);
+        }
+
+        probations.sort_by_key(|probation| probation.start);
+        Ok(probations)
+    }
+
+    pub fn get_by_roblox_id(&self, roblox_id: u64) -> sled::Result<Vec<Probation>> {
+        let mut probations = Vec::new();
+
+        for result in self.roblox_index.scan_prefix(roblox_id.to_be_bytes()) {
+            let (key, value) = result?;
+            let probation_id = u64::from_be_bytes(key[8..16].try_into().unwrap());
+            let discord_id = u64::from_be_bytes(value.as_ref().try_into().unwrap());
+            if let Some(probation) = self.get(discord_id, probation_id)? {
+                probations.push(probation);
+            }
+        }
+
+        probations.sort_by_key(|probation| probation.start);
+        Ok(probations)
+    }
+
+    /// The probations a new log escalates, empty unless it is an infraction.
+    pub fn escalations(&self, log: &ModLog, now: u64) -> sled::Result<Vec<Probation>> {
+        if !log.is_infraction() {
+            return Ok(Vec::new());
+        }
+        self.active_for(&log.subjects(), now)
+    }
+
+    /// The probations in force for any of a log's subjects, each listed once.
+    pub fn active_for(&self, subjects: &[LogSubject], now: u64) -> sled::Result<Vec<Probation>> {
+        let mut active: Vec<Probation> = Vec::new();
+
+        for subject in subjects {
+            let mut probations = Vec::new();
+            if let Some(discord_id) = subject.discord_id {
+                probations.extend(self.get_by_discord_id(discord_id)?);
+            }
+            if let Some(roblox_id) = subject.roblox_id {
+                probations.extend(self.get_by_roblox_id(roblox_id)?);
+            }
+            for probation in probations {
+                if probation.is_active(now) && !active.iter().any(|seen| seen.id == probation.id) {
+                    active.push(probation);
+                }
+            }
+        }
+
+        Ok(active)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probation(discord_id: u64, roblox_id: Option<u64>, start: u64, end: u64) -> Probation {
+        Probation {
+            id: 0,
+            discord_id,
+            roblox_id,
+            probation_type: "Roblox Ban".to_string(),
+            reason: "Appealed".to_string(),
+            moderator_id: 1,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_active_for_either_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = ProbationDB::init(dir.path().to_str().unwrap()).unwrap();
+
+        let linked = db.add(probation(10, Some(156), 100, 200)).unwrap();
+        db.add(probation(10, None, 0, 50)).unwrap();
+        db.add(probation(20, None, 100, 200)).unwrap();
+
+        let both = [
+            LogSubject::discord(serenity::all::UserId::new(10)),
+            LogSubject::roblox(156),
+        ];
+        let active = db.active_for(&both, 150).unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].id, linked);
+
+        assert_eq!(
+            db.active_for(&[LogSubject::roblox(156)], 150).unwrap()[0].id,
+            linked
+        );
+        assert!(
+            db.active_for(&[LogSubject::roblox(156)], 200)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(db.get_by_discord_id(10).unwrap().len(), 2);
+    }
+}
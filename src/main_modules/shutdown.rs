@@ -0,0 +1,241 @@
+use serenity::all::{Context, Http, ShardManager};
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::Data;
+
+/// How long shutting down waits for running tasks before giving up on them.
+const TASK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Whether the bot is shutting down, and the background tasks it has to wait for when it does.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    started: Arc<AtomicBool>,
+    running: Arc<AtomicUsize>,
+    finished: Arc<Notify>,
+}
+
+/// Counts its task as running until dropped, so tasks that panic still count as finished.
+struct RunningTask {
+    running: Arc<AtomicUsize>,
+    finished: Arc<Notify>,
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        if self.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.finished.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn is_shutting_down(&self) -> bool {
+        self.started.load(Ordering::SeqCst)
+    }
+
+    /// Starts shutting down, returning false if that had already started.
+    pub fn begin(&self) -> bool {
+        !self.started.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self) {
+        self.started.store(false, Ordering::SeqCst);
+    }
+
+    /// Spawns a task that shutting down waits for.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let running = RunningTask {
+            running: Arc::clone(&self.running),
+            finished: Arc::clone(&self.finished),
+        };
+        tokio::spawn(async move {
+            let _running = running;
+            task.await
+        })
+    }
+
+    /// Waits for the spawned tasks to finish, returning how many were still running after
+    /// `timeout`.
+    pub async fn wait(&self, timeout: Duration) -> usize {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let finished = self.finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+            if self.running.load(Ordering::SeqCst) == 0 {
+                return 0;
+            }
+            if tokio::time::timeout_at(deadline, finished).await.is_err() {
+                return self.running.load(Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+async fn notify_ops(http: &Http, data: &Data, notice: String) {
+    let Some(channel_id) = data.config.get().modules.logging.ops_channel_id else {
+        return;
+    };
+    if let Err(err) = channel_id.say(http, notice).await {
+        warn!(error = %err, "Couldn't post to the ops channel");
+    }
+}
+
+pub async fn announce_up(http: &Http, data: &Data) {
+    notify_ops(
+        http,
+        data,
+        format!("🟢 Back up on v{}.", env!("CARGO_PKG_VERSION")),
+    )
+    .await;
+}
+
+/// Stops taking commands, lets running tasks and queued logs finish and flushes every database,
+/// so the process can exit without losing anything. Returns false if the bot was already
+/// shutting down.
+pub async fn shut_down(ctx: &Context, data: &Data, reason: &str) -> bool {
+    if !data.shutdown.begin() {
+        return false;
+    }
+    info!(reason, "Shutting down");
+    if let Err(err) = data.timer_system.stop().await {
+        error!(error = %err, "Couldn't flush timers");
+    }
+
+    // Deletions still waiting on their message's attachments to be stored.
+    let queued_logs = std::mem::take(&mut *data.queued_logs.lock().unwrap());
+    let mut dropped_logs = 0;
+    for log in queued_logs {
+        let stored = data
+            .attachment_db
+            .lock()
+            .unwrap()
+            .get(log.message_id.to_string().as_str())
+            .is_some();
+        if stored {
+            log.do_image_logging(ctx, data.clone()).await;
+        } else {
+            dropped_logs += 1;
+        }
+    }
+
+    let unfinished = data.shutdown.wait(TASK_TIMEOUT).await;
+    let flushed = [
+        ("attachments", data.attachment_db.lock().unwrap().flush()),
+        ("policies", data.policy_system.flush()),
+        ("guides", data.guide_system.flush()),
+        ("logs", data.logging_db.flush()),
+        ("game sanctions", data.game_sanctions.flush()),
+        ("probations", data.probations.flush()),
+        ("guild settings", data.guild_settings.flush()),
+        ("command audit", data.command_audit.flush()),
+        ("updater", data.updater.flush()),
+        ("masks", data.mask_library.flush()),
+    ];
+    for (name, result) in flushed {
+        if let Err(err) = result {
+            error!(database = name, error = %err, "Couldn't flush database");
+        }
+    }
+
+    let mut notice = format!(
+        "🔴 Going down on v{}, {}.",
+        env!("CARGO_PKG_VERSION"),
+        reason
+    );
+    if unfinished > 0 {
+        notice.push_str(&format!(" {} tasks didn't finish in time.", unfinished));
+    }
+    if dropped_logs > 0 {
+        notice.push_str(&format!(
+            " {} deleted messages weren't logged, their attachments were never stored.",
+            dropped_logs
+        ));
+    }
+    info!(unfinished, dropped_logs, "Shut down");
+    notify_ops(&ctx.http, data, notice).await;
+    true
+}
+
+/// Goes back to running normally after shutting down for a restart that didn't happen.
+pub async fn resume(http: &Http, data: &Data, reason: &str) {
+    data.timer_system.resume();
+    data.shutdown.cancel();
+    notify_ops(
+        http,
+        data,
+        format!(
+            "🟢 Staying up on v{}, {}.",
+            env!("CARGO_PKG_VERSION"),
+            reason
+        ),
+    )
+    .await;
+}
+
+/// Shuts down once the process is asked to stop, then disconnects from Discord so the bot exits.
+pub async fn shut_down_on_signal(ctx: Context, data: Data, shard_manager: Arc<ShardManager>) {
+    #[cfg(unix)]
+    let reason = {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminations = match signal(SignalKind::terminate()) {
+            Ok(terminations) => terminations,
+            Err(err) => {
+                warn!(
+                    error = %err,
+                    "Couldn't listen for SIGTERM, shutting down won't be graceful"
+                );
+                return;
+            }
+        };
+        tokio::select! {
+            _ = terminations.recv() => "received SIGTERM",
+            _ = tokio::signal::ctrl_c() => "received SIGINT",
+        }
+    };
+    #[cfg(not(unix))]
+    let reason = {
+        let _ = tokio::signal::ctrl_c().await;
+        "received Ctrl+C"
+    };
+
+    if shut_down(&ctx, &data, reason).await {
+        shard_manager.shutdown_all().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_tasks() {
+        let shutdown = Shutdown::default();
+        assert_eq!(shutdown.wait(Duration::from_millis(10)).await, 0);
+
+        shutdown.spawn(tokio::time::sleep(Duration::from_millis(20)));
+        shutdown.spawn(async { panic!("task failed") });
+        assert_eq!(shutdown.wait(Duration::from_secs(5)).await, 0);
+
+        shutdown.spawn(tokio::time::sleep(Duration::from_secs(60)));
+        assert_eq!(shutdown.wait(Duration::from_millis(20)).await, 1);
+
+        assert!(shutdown.begin());
+        assert!(shutdown.is_shutting_down());
+        assert!(!shutdown.begin());
+        shutdown.cancel();
+        assert!(!shutdown.is_shutting_down());
+    }
+}
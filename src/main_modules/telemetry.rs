@@ -0,0 +1,115 @@
+use serenity::all::{Client, Context, FullEvent, Interaction};
+use serenity::async_trait;
+use serenity::framework::Framework;
+use tracing::{Instrument, Span, info_span};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use super::config::{LogRotation, TracingConfig};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn output_layer<W>(json: bool, ansi: bool, writer: W) -> BoxedLayer
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(ansi)
+        .with_writer(writer);
+    if json {
+        layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed()
+    } else {
+        layer.boxed()
+    }
+}
+
+fn filter(config: &TracingConfig) -> Result<EnvFilter, String> {
+    match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) => EnvFilter::try_new(&directives)
+            .map_err(|err| format!("RUST_LOG isn't a valid filter: {}", err)),
+        Err(_) => EnvFilter::try_new(&config.level)
+            .map_err(|err| format!("tracing.level isn't a valid filter: {}", err)),
+    }
+}
+
+/// Starts logging to stdout and, if configured, to rotating log files. The returned guard
+/// writes out buffered file logs when dropped, so it has to be kept until the bot exits.
+pub fn init(config: &TracingConfig) -> Result<Option<WorkerGuard>, String> {
+    let mut layers = vec![output_layer(config.json, !config.json, std::io::stdout)];
+    let mut guard = None;
+    if let Some(file) = &config.file {
+        let rotation = match file.rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        };
+        let mut appender = RollingFileAppender::builder()
+            .rotation(rotation)
+            .filename_prefix(&file.prefix);
+        if let Some(max_files) = file.max_files {
+            appender = appender.max_log_files(max_files);
+        }
+        let appender = appender.build(&file.directory).map_err(|err| {
+            format!(
+                "couldn't open log files in {}: {}",
+                file.directory.display(),
+                err
+            )
+        })?;
+        let (writer, file_guard) = tracing_appender::non_blocking(appender);
+        layers.push(output_layer(config.json, false, writer));
+        guard = Some(file_guard);
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter(config)?)
+        .try_init()
+        .map_err(|err| err.to_string())?;
+    Ok(guard)
+}
+
+fn event_span(event: &FullEvent) -> Span {
+    match event {
+        FullEvent::InteractionCreate {
+            interaction: Interaction::Command(command),
+        } => info_span!(
+            "command",
+            name = %command.data.name,
+            user_id = %command.user.id,
+            guild_id = ?command.guild_id,
+        ),
+        FullEvent::Message { new_message } => info_span!(
+            "event",
+            kind = event.snake_case_name(),
+            message_id = %new_message.id,
+            channel_id = %new_message.channel_id,
+        ),
+        _ => info_span!("event", kind = event.snake_case_name()),
+    }
+}
+
+/// Runs the wrapped framework's handling of each gateway event inside a span, so everything a
+/// command or event handler logs can be traced back to what caused it.
+pub struct Traced<F>(pub F);
+
+#[async_trait]
+impl<F: Framework> Framework for Traced<F> {
+    async fn init(&mut self, client: &Client) {
+        self.0.init(client).await
+    }
+
+    async fn dispatch(&self, ctx: Context, event: FullEvent) {
+        let span = event_span(&event);
+        self.0.dispatch(ctx, event).instrument(span).await
+    }
+}
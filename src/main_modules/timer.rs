@@ -1,16 +1,61 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
 use tokio::time::{Duration, Instant};
 use sled::Db;
 use futures::future::BoxFuture;
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use tracing::{Instrument, debug, debug_span, error, info, info_span, warn};
+
+const SCHEMA_VERSION: u32 = 3;
+
+/// What happens to a timer's user when it runs out.
+///
+/// Records store the variant's index, so new actions must only ever be added at the end.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    RemoveRole { role_id: u64 },
+    /// Lifts a temporary game ban, posting the unban log where the ban was logged.
+    LiftGameBan { roblox_id: u64, sanction_id: u64, log_channel_id: u64 },
+    AddRole { role_id: u64 },
+    Unban { guild_id: u64 },
+    /// Ends the user's timeout.
+    Unmute { guild_id: u64 },
+    PostMessage { channel_id: u64, content: String },
+    /// Runs the callback registered under `key` with `TimerSystem::register_custom`.
+    Custom { key: String, payload: String },
+}
 
+impl Action {
+    /// Role changes can only happen while the user is in the server, so their timers are
+    /// paused while they're away.
+    pub fn needs_member(&self) -> bool {
+        matches!(self, Action::RemoveRole { .. } | Action::AddRole { .. })
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::RemoveRole { role_id } => write!(f, "Remove role <@&{}>", role_id),
+            Action::LiftGameBan { roblox_id, sanction_id, .. } => {
+                write!(f, "Lift game ban {} on {}", sanction_id, roblox_id)
+            }
+            Action::AddRole { role_id } => write!(f, "Add role <@&{}>", role_id),
+            Action::Unban { guild_id } => write!(f, "Unban from {}", guild_id),
+            Action::Unmute { guild_id } => write!(f, "End timeout in {}", guild_id),
+            Action::PostMessage { channel_id, .. } => write!(f, "Post a message in <#{}>", channel_id),
+            Action::Custom { key, .. } => write!(f, "Run `{}`", key),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TimerData {
     pub timer_id: String,
-    pub role_id: String,
+    pub action: Action,
     pub end_timestamp: u64,
     pub is_paused: bool,
     pub paused_duration: u64,
@@ -18,39 +63,73 @@ pub struct TimerData {
     pub delete_on_ban: bool,  // New field
 }
 
+/// The role-only record written before timers carried an action.
+#[derive(Deserialize)]
+struct TimerDataV2 {
+    timer_id: String,
+    role_id: String,
+    end_timestamp: u64,
+    is_paused: bool,
+    paused_duration: u64,
+    schema_version: u32,
+    delete_on_ban: bool,
+}
+
+impl TimerDataV2 {
+    fn migrate(self) -> Option<TimerData> {
+        if self.schema_version != 2 {
+            return None;
+        }
+        Some(TimerData {
+            timer_id: self.timer_id,
+            action: Action::RemoveRole { role_id: self.role_id.parse().ok()? },
+            end_timestamp: self.end_timestamp,
+            is_paused: self.is_paused,
+            paused_duration: self.paused_duration,
+            schema_version: SCHEMA_VERSION,
+            delete_on_ban: self.delete_on_ban,
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UserTimer {
     end_time: Instant,
-    role_id: String,
+    action: Action,
     paused_at: Option<Instant>,
     paused_duration: Duration,
     delete_on_ban: bool,  // New field
 }
 
-type EventHandler = Arc<Mutex<Box<dyn Fn(String, String) -> BoxFuture<'static, ()> + Send + Sync>>>;
+type EventHandler = Arc<Mutex<Box<dyn Fn(String, Action) -> BoxFuture<'static, ()> + Send + Sync>>>;
+type CustomHandler = Arc<dyn Fn(String, String) -> BoxFuture<'static, ()> + Send + Sync>;
 
 pub struct TimerSystem {
     db: Arc<Db>,
     timers: Arc<Mutex<HashMap<String, HashMap<String, UserTimer>>>>,
     event_handler: EventHandler,
+    custom_handlers: Arc<Mutex<HashMap<String, CustomHandler>>>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl TimerSystem {
     pub async fn new(db_path: &str) -> sled::Result<Self> {
-        println!("Initializing TimerSystem with database at: {}", db_path);
+        info!(db_path, "Initializing timer system");
         let db = Arc::new(sled::open(db_path)?);
         let timers = Arc::new(Mutex::new(HashMap::new()));
         let event_handler: EventHandler = 
-            Arc::new(Mutex::new(Box::new(|_: String, _: String| Box::pin(async {}))));
+            Arc::new(Mutex::new(Box::new(|_: String, _: Action| Box::pin(async {}))));
         let system = TimerSystem {
             db: Arc::clone(&db),
             timers: Arc::clone(&timers),
             event_handler,
+            custom_handlers: Arc::new(Mutex::new(HashMap::new())),
+            stopped: Arc::new(AtomicBool::new(false)),
         };
 
-        println!("Starting database migration check...");
+        debug!("Starting database migration check");
         if let Err(e) = system.migrate_database().await {
-            println!("Error during migration: {:?}", e);
+            error!(error = %e, "Timer migration failed");
         }
 
         Ok(system)
@@ -61,7 +140,7 @@ impl TimerSystem {
         let mut keys_to_delete = Vec::new();
         let mut migration_count = 0;
 
-        println!("Scanning database for entries requiring migration...");
+        debug!("Scanning database for entries requiring migration");
 
         // First pass: Identify old format data
         for result in self.db.iter() {
@@ -69,22 +148,22 @@ impl TimerSystem {
             let key_str = String::from_utf8_lossy(&key);
 
             if !key_str.contains(':') {
-                println!("Found old format entry with key: {}", key_str);
+                debug!(key = %key_str, "Found old format entry");
                 keys_to_migrate.push((key.to_vec(), value.to_vec()));
                 keys_to_delete.push(key.to_vec());
                 migration_count += 1;
             }
         }
 
-        println!("Found {} entries requiring migration", migration_count);
+        debug!(count = migration_count, "Found entries requiring migration");
 
         // Second pass: Migrate old format data
         for (key, value) in keys_to_migrate {
             let user_id = String::from_utf8_lossy(&key).to_string();
-            println!("Migrating timer for user: {}", user_id);
+            debug!(user_id, "Migrating timer");
             
             if let Some(migrated_data) = self.migrate_old_format(&user_id, &value)? {
-                println!("Successfully migrated timer: {:?}", migrated_data);
+                debug!(?migrated_data, "Migrated timer");
                 let timer_id = migrated_data.timer_id.clone();
                 let now = std::time::SystemTime::now()
                     .duration_since(std::time::SystemTime::UNIX_EPOCH)
@@ -95,13 +174,13 @@ impl TimerSystem {
                     
                     let timer = UserTimer {
                         end_time: Instant::now() + duration,
-                        role_id: migrated_data.role_id.clone(),
+                        action: migrated_data.action.clone(),
                         paused_at: if migrated_data.is_paused { Some(Instant::now()) } else { None },
                         paused_duration: Duration::from_secs(migrated_data.paused_duration),
                         delete_on_ban: true,
                     };
 
-                    println!("Successfully converted user timer, {:#?}", timer);
+                    debug!(?timer, "Converted user timer");
 
                     // Asynchronously update the in-memory timers
                     let mut timers = self.timers.lock().await;
@@ -109,38 +188,42 @@ impl TimerSystem {
                     user_timers.insert(timer_id.clone(), timer);
                 }
             } else {
-                println!("Failed to migrate timer for user: {}", user_id);
+                warn!(user_id, "Couldn't migrate timer");
             }
         }
 
         // Clean up: Remove old format data
         for key in keys_to_delete {
             let key_str = String::from_utf8_lossy(&key);
-            println!("Removing old format entry: {}", key_str);
+            debug!(key = %key_str, "Removing old format entry");
             self.db.remove(key)?;
         }
 
         // Verify and load all existing timers after migration
-        println!("Loading all existing timers...");
+        debug!("Loading existing timers");
         for result in self.db.iter() {
             let (key, value) = result?;
             let key_str = String::from_utf8_lossy(&key).to_string();
             
             if key_str.contains(':') {
-                match bincode::deserialize::<TimerData>(&value) {
-                    Ok(timer_data) => {
-                        println!("Loading existing timer: {} for role: {}", timer_data.timer_id, timer_data.role_id);
+                let timer_data = match bincode::deserialize::<TimerData>(&value) {
+                    Ok(timer_data) => Some(timer_data),
+                    Err(_) => self.migrate_v2_record(&key_str, &value)?,
+                };
+                match timer_data {
+                    Some(timer_data) => {
+                        debug!(timer_id = timer_data.timer_id, action = %timer_data.action, "Loading timer");
                         self.load_timer_data(&key_str, timer_data).await?;
                     },
-                    Err(e) => {
-                        println!("Error deserializing timer data for key {}: {:?}", key_str, e);
+                    None => {
+                        warn!(key = key_str, "Couldn't deserialize timer, removing it");
                         self.db.remove(key)?;
                     }
                 }
             }
         }
 
-        println!("Database migration and loading completed successfully");
+        info!("Timers loaded");
         Ok(())
     }
 
@@ -157,7 +240,7 @@ impl TimerSystem {
                 
                 let timer = UserTimer {
                     end_time: Instant::now() + duration,
-                    role_id: timer_data.role_id.clone(),
+                    action: timer_data.action.clone(),
                     paused_at: if timer_data.is_paused { Some(Instant::now()) } else { None },
                     paused_duration: Duration::from_secs(timer_data.paused_duration),
                     delete_on_ban: timer_data.delete_on_ban,
@@ -175,6 +258,17 @@ impl TimerSystem {
         Ok(())
     }
 
+    /// Rewrites a role-only v2 record as a `RemoveRole` timer.
+    fn migrate_v2_record(&self, key_str: &str, value: &[u8]) -> sled::Result<Option<TimerData>> {
+        let Some(timer_data) = bincode::deserialize::<TimerDataV2>(value).ok().and_then(TimerDataV2::migrate) else {
+            return Ok(None);
+        };
+        info!(key = key_str, "Migrated v2 timer");
+        let db_value = bincode::serialize(&timer_data).unwrap();
+        self.db.insert(key_str.as_bytes(), db_value)?;
+        Ok(Some(timer_data))
+    }
+
     fn migrate_old_format(&self, user_id: &str, value: &[u8]) -> sled::Result<Option<TimerData>> {
         if value.len() < 8 {
             return Ok(None);
@@ -185,7 +279,7 @@ impl TimerSystem {
         
         if let Some(end_pos) = role_id_end {
             let end_pos = end_pos + 8;
-            if let Ok(role_id) = String::from_utf8(value[8..end_pos].to_vec()) {
+            if let Some(role_id) = String::from_utf8(value[8..end_pos].to_vec()).ok().and_then(|role_id| role_id.parse().ok()) {
                 let is_paused = value[end_pos] == 1;
                 let paused_duration = if value.len() > end_pos + 1 {
                     u64::from_be_bytes(value[end_pos+1..].try_into().unwrap())
@@ -197,11 +291,11 @@ impl TimerSystem {
                 
                 let timer_data = TimerData {
                     timer_id: timer_id.clone(),
-                    role_id,
+                    action: Action::RemoveRole { role_id },
                     end_timestamp: timestamp,
                     is_paused,
                     paused_duration,
-                    schema_version: 2,
+                    schema_version: SCHEMA_VERSION,
                     delete_on_ban: true,  // Default value for migrated timers
                 };
 
@@ -219,7 +313,7 @@ impl TimerSystem {
     pub async fn add_timer(
         &self,
         user_id: String,
-        role_id: String,
+        action: Action,
         duration_secs: u64,
         is_paused: bool,
         paused_duration: Option<u64>,
@@ -240,7 +334,7 @@ impl TimerSystem {
         
         let timer = UserTimer {
             end_time,
-            role_id: role_id.clone(),
+            action: action.clone(),
             paused_at: if is_paused { Some(Instant::now()) } else { None },
             paused_duration: paused_duration.map(Duration::from_secs).unwrap_or(Duration::from_secs(0)),
             delete_on_ban,  // New field
@@ -250,11 +344,11 @@ impl TimerSystem {
 
         let timer_data = TimerData {
             timer_id: timer_id.clone(),
-            role_id,
+            action,
             end_timestamp,
             is_paused,
             paused_duration: paused_duration.unwrap_or(0),
-            schema_version: 2,
+            schema_version: SCHEMA_VERSION,
             delete_on_ban,  // New field
         };
 
@@ -265,7 +359,13 @@ impl TimerSystem {
         Ok(timer_id)
     }
 
-    pub async fn toggle_timer(&self, user_id: &str, timer_id: &str) -> Result<Option<String>, String> {
+    /// Runs `action` for `user_id` after `duration_secs`, for modules scheduling work that isn't
+    /// tied to the user staying in the server.
+    pub async fn schedule(&self, user_id: String, action: Action, duration_secs: u64) -> sled::Result<String> {
+        self.add_timer(user_id, action, duration_secs, false, None, false).await
+    }
+
+    pub async fn toggle_timer(&self, user_id: &str, timer_id: &str) -> Result<Option<Action>, String> {
         let mut timers = self.timers.lock().await;
         
         let user_timers = timers.get_mut(user_id)
@@ -319,10 +419,22 @@ impl TimerSystem {
             self.db.insert(db_key.as_bytes(), db_value)
                 .map_err(|_| "Failed to update database".to_string())?;
             
-            Ok(Some(timer.role_id.clone())) // Return Some(role_id) when resuming
+            Ok(Some(timer.action.clone())) // Return Some(action) when resuming
         }
     }
 
+    /// How many timers are running and how many are paused, across every user.
+    pub async fn counts(&self) -> (usize, usize) {
+        let timers = self.timers.lock().await;
+        let paused = timers
+            .values()
+            .flat_map(|user_timers| user_timers.values())
+            .filter(|timer| timer.paused_at.is_some())
+            .count();
+        let total: usize = timers.values().map(HashMap::len).sum();
+        (total - paused, paused)
+    }
+
     pub async fn list_user_timers(&self, user_id: &str) -> Vec<TimerData> {
         let timers = self.timers.lock().await;
         let mut result = Vec::new();
@@ -338,14 +450,14 @@ impl TimerSystem {
                 
                 let timer_data = TimerData {
                     timer_id: timer_id.clone(),
-                    role_id: timer.role_id.clone(),
+                    action: timer.action.clone(),
                     end_timestamp: std::time::SystemTime::now()
                         .duration_since(std::time::SystemTime::UNIX_EPOCH)
                         .unwrap()
                         .as_secs() + remaining_secs,
                     is_paused: timer.paused_at.is_some(),
                     paused_duration: timer.paused_duration.as_secs(),
-                    schema_version: 2,
+                    schema_version: SCHEMA_VERSION,
                     delete_on_ban: timer.delete_on_ban,  // Include in output
                 };
                 
@@ -381,100 +493,248 @@ impl TimerSystem {
     }
 
     pub async fn set_event_handler<F, Fut>(&self, handler: F)
+    where
+        F: Fn(String, Action) -> Fut + Send + Sync + 'static,
+        Fut: futures::Future<Output = ()> + Send + 'static,
+    {
+        *self.event_handler.lock().await = Box::new(move |user_id, action| 
+            Box::pin(handler(user_id, action))
+        );
+    }
+
+    /// Registers the callback `Action::Custom` timers with this key run, given the timer's user
+    /// and payload.
+    pub async fn register_custom<F, Fut>(&self, key: &str, handler: F)
     where
         F: Fn(String, String) -> Fut + Send + Sync + 'static,
         Fut: futures::Future<Output = ()> + Send + 'static,
     {
-        *self.event_handler.lock().await = Box::new(move |user_id, role_id| 
-            Box::pin(handler(user_id, role_id))
+        self.custom_handlers.lock().await.insert(
+            key.to_string(),
+            Arc::new(move |user_id, payload| Box::pin(handler(user_id, payload))),
         );
     }
 
+    pub fn size_on_disk(&self) -> sled::Result<u64> {
+        self.db.size_on_disk()
+    }
+
+    /// Stops the timer thread handling expired timers once it's done with the ones it's on, then
+    /// flushes the database.
+    pub async fn stop(&self) -> sled::Result<()> {
+        self.stopped.store(true, Ordering::SeqCst);
+        let _timers = self.timers.lock().await;
+        self.db.flush_async().await?;
+        Ok(())
+    }
+
+    pub fn resume(&self) {
+        self.stopped.store(false, Ordering::SeqCst);
+    }
+
     pub fn start_timer_thread(&self) {
         let timers = Arc::clone(&self.timers);
         let db = Arc::clone(&self.db);
         let event_handler = Arc::clone(&self.event_handler);
+        let custom_handlers = Arc::clone(&self.custom_handlers);
+        let stopped = Arc::clone(&self.stopped);
 
         tokio::spawn(async move {
             loop {
                 tokio::time::sleep(Duration::from_secs(1)).await;
                 let mut timers = timers.lock().await;
-                let now = Instant::now();
+                if stopped.load(Ordering::SeqCst) {
+                    continue;
+                }
+                async {
+                    let now = Instant::now();
                 
-                let mut expired_timers = Vec::new();
+                    let mut expired_timers = Vec::new();
                 
-                // Collect expired timers
-                for (user_id, user_timers) in timers.iter() {
-                    for (timer_id, timer) in user_timers.iter() {
-                        if timer.paused_at.is_none() && timer.end_time <= now {
-                            println!("Timer expired:");
-                            println!("  User ID: {}", user_id);
-                            println!("  Timer ID: {}", timer_id);
-                            println!("  Role ID: {}", timer.role_id);
-                            println!("  Total pause duration: {:?}", timer.paused_duration);
-                            println!("  End time reached at: {:?}", timer.end_time);
-                            
-                            expired_timers.push((
-                                user_id.clone(),
-                                timer_id.clone(),
-                                timer.role_id.clone()
-                            ));
+                    // Collect expired timers
+                    for (user_id, user_timers) in timers.iter() {
+                        for (timer_id, timer) in user_timers.iter() {
+                            if timer.paused_at.is_none() && timer.end_time <= now {
+                                info!(
+                                    user_id,
+                                    timer_id,
+                                    action = %timer.action,
+                                    paused_for = ?timer.paused_duration,
+                                    "Timer expired"
+                                );
+                                expired_timers.push((
+                                    user_id.clone(),
+                                    timer_id.clone(),
+                                    timer.action.clone()
+                                ));
+                            }
                         }
                     }
-                }
 
-                // Handle expired timers
-                for (user_id, timer_id, role_id) in &expired_timers {
-                    let db_key = format!("{}:{}", user_id, timer_id);
-                    match db.remove(db_key.as_bytes()) {
-                        Ok(_) => println!("Successfully removed expired timer from database: {}", db_key),
-                        Err(e) => println!("Failed to remove expired timer from database: {}", e),
+                    // Handle expired timers
+                    for (user_id, timer_id, action) in &expired_timers {
+                        let db_key = format!("{}:{}", user_id, timer_id);
+                        if let Err(e) = db.remove(db_key.as_bytes()) {
+                            error!(key = db_key, error = %e, "Couldn't remove expired timer from the database");
+                        }
+                        let span = info_span!("timer_action", user_id, timer_id, action = %action);
+
+                        if let Action::Custom { key, payload } = action {
+                            let handler = custom_handlers.lock().await.get(key).cloned();
+                            match handler {
+                                Some(handler) => handler(user_id.clone(), payload.clone()).instrument(span).await,
+                                None => warn!(key, "No handler registered for custom timer action"),
+                            }
+                            continue;
+                        }
+
+                        let handler = event_handler.lock().await;
+                        handler(user_id.clone(), action.clone()).instrument(span).await;
                     }
-                    
-                    println!("Triggering event handler for expired timer - User: {}, Role: {}", user_id, role_id);
-                    let handler = event_handler.lock().await;
-                    handler(user_id.clone(), role_id.clone()).await;
-                }
 
-                // Remove expired timers from memory
-                for (user_id, timer_id, _) in expired_timers {
-                    if let Some(user_timers) = timers.get_mut(&user_id) {
-                        user_timers.remove(&timer_id);
-                        println!("Removed expired timer from memory - User: {}, Timer: {}", user_id, timer_id);
-                        if user_timers.is_empty() {
-                            timers.remove(&user_id);
-                            println!("Removed user entry as no timers remain - User: {}", user_id);
+                    // Remove expired timers from memory
+                    for (user_id, timer_id, _) in expired_timers {
+                        if let Some(user_timers) = timers.get_mut(&user_id) {
+                            user_timers.remove(&timer_id);
+                            if user_timers.is_empty() {
+                                timers.remove(&user_id);
+                            }
                         }
                     }
-                }
 
-                // Update remaining timers in the database
-                for (user_id, user_timers) in timers.iter() {
-                    for (timer_id, timer) in user_timers.iter() {
-                        if timer.paused_at.is_none() && timer.end_time > now {
-                            let remaining = timer.end_time - now;
-                            let end_timestamp = std::time::SystemTime::now()
-                                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs() + remaining.as_secs();
-
-                            let timer_data = TimerData {
-                                timer_id: timer_id.clone(),
-                                role_id: timer.role_id.clone(),
-                                end_timestamp,
-                                is_paused: false,
-                                paused_duration: timer.paused_duration.as_secs(),
-                                schema_version: 2,
-                                delete_on_ban: timer.delete_on_ban
-                            };
-
-                            let db_key = format!("{}:{}", user_id, timer_id);
-                            let db_value = bincode::serialize(&timer_data).unwrap();
-                            db.insert(db_key.as_bytes(), db_value).unwrap();
+                    // Update remaining timers in the database
+                    for (user_id, user_timers) in timers.iter() {
+                        for (timer_id, timer) in user_timers.iter() {
+                            if timer.paused_at.is_none() && timer.end_time > now {
+                                let remaining = timer.end_time - now;
+                                let end_timestamp = std::time::SystemTime::now()
+                                    .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                                    .unwrap()
+                                    .as_secs() + remaining.as_secs();
+
+                                let timer_data = TimerData {
+                                    timer_id: timer_id.clone(),
+                                    action: timer.action.clone(),
+                                    end_timestamp,
+                                    is_paused: false,
+                                    paused_duration: timer.paused_duration.as_secs(),
+                                    schema_version: SCHEMA_VERSION,
+                                    delete_on_ban: timer.delete_on_ban
+                                };
+
+                                let db_key = format!("{}:{}", user_id, timer_id);
+                                let db_value = bincode::serialize(&timer_data).unwrap();
+                                db.insert(db_key.as_bytes(), db_value).unwrap();
+                            }
                         }
                     }
                 }
+                .instrument(debug_span!("timer_tick"))
+                .await;
             }
         });
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct StoredV2 {
+        timer_id: String,
+        role_id: String,
+        end_timestamp: u64,
+        is_paused: bool,
+        paused_duration: u64,
+        schema_version: u32,
+        delete_on_ban: bool,
+    }
+
+    #[tokio::test]
+    async fn test_v2_timers_become_remove_role() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_str().unwrap();
+        let record = StoredV2 {
+            timer_id: "abc".to_string(),
+            role_id: "42".to_string(),
+            end_timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() + 3600,
+            is_paused: false,
+            paused_duration: 0,
+            schema_version: 2,
+            delete_on_ban: true,
+        };
+        {
+            let db = sled::open(path).unwrap();
+            db.insert("7:abc", bincode::serialize(&record).unwrap()).unwrap();
+        }
+
+        let system = TimerSystem::new(path).await.unwrap();
+        let timers = system.list_user_timers("7").await;
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers[0].action, Action::RemoveRole { role_id: 42 });
+        assert!(timers[0].delete_on_ban);
+    }
+
+    #[tokio::test]
+    async fn test_custom_actions_run_registered_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = TimerSystem::new(dir.path().to_str().unwrap()).await.unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        system
+            .register_custom("reminder", move |user_id, payload| {
+                let sender = sender.clone();
+                async move {
+                    sender.send((user_id, payload)).unwrap();
+                }
+            })
+            .await;
+
+        let action = Action::Custom { key: "reminder".to_string(), payload: "check in".to_string() };
+        system.schedule("7".to_string(), action, 0).await.unwrap();
+        system.start_timer_thread();
+
+        let ran = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap();
+        assert_eq!(ran, Some(("7".to_string(), "check in".to_string())));
+        assert!(system.list_user_timers("7").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_actions_reach_event_handler() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = TimerSystem::new(dir.path().to_str().unwrap()).await.unwrap();
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        system
+            .set_event_handler(move |user_id, action| {
+                let sender = sender.clone();
+                async move {
+                    sender.send((user_id, action)).unwrap();
+                }
+            })
+            .await;
+
+        let actions = [
+            Action::AddRole { role_id: 42 },
+            Action::Unban { guild_id: 1 },
+            Action::Unmute { guild_id: 1 },
+            Action::PostMessage { channel_id: 2, content: "Welcome back".to_string() },
+        ];
+        for action in &actions {
+            system.schedule("7".to_string(), action.clone(), 0).await.unwrap();
+        }
+        system.start_timer_thread();
+
+        let mut ran = Vec::new();
+        while ran.len() < actions.len() {
+            let (user_id, action) = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+            assert_eq!(user_id, "7");
+            ran.push(action);
+        }
+        for action in &actions {
+            assert!(ran.contains(action));
+        }
+        assert!(system.list_user_timers("7").await.is_empty());
+    }
+}
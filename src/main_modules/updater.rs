@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::config::UpdateStep;
+
+const PENDING_KEY: &str = "pending";
+const PREVIOUS_VERSION_KEY: &str = "previous_version";
+
+/// Left behind by `/update` so the restarted bot can report how it went.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingRestart {
+    pub from_version: String,
+    pub channel_id: u64,
+    pub requested_by: u64,
+    pub rollback: bool,
+}
+
+impl PendingRestart {
+    pub fn report(&self, current_version: &str) -> String {
+        let action = if self.rollback {
+            "Rolled back"
+        } else {
+            "Updated"
+        };
+        format!(
+            "{} from v{} to v{}, requested by <@{}>.",
+            action, self.from_version, current_version, self.requested_by
+        )
+    }
+}
+
+/// Where the binary that was replaced by the last update is kept.
+pub fn previous_binary(binary: &Path) -> PathBuf {
+    let mut path = binary.as_os_str().to_owned();
+    path.push(".previous");
+    PathBuf::from(path)
+}
+
+/// Moves the running binary aside for rollback and puts the built one in its place. Renaming
+/// rather than overwriting works while the old binary is still running.
+pub fn swap_binary(built: &Path, binary: &Path) -> io::Result<()> {
+    let previous = previous_binary(binary);
+    std::fs::rename(binary, &previous)?;
+    if let Err(err) = std::fs::copy(built, binary) {
+        std::fs::rename(&previous, binary)?;
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Puts the binary from before the last update back.
+pub fn rollback_binary(binary: &Path) -> io::Result<()> {
+    let previous = previous_binary(binary);
+    if !previous.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "there's no previous binary to roll back to",
+        ));
+    }
+    std::fs::rename(previous, binary)
+}
+
+async fn forward_lines(output: impl AsyncRead + Unpin, lines: UnboundedSender<String>) {
+    let mut reader = BufReader::new(output).lines();
+    while let Ok(Some(line)) = reader.next_line().await {
+        if lines.send(line).is_err() {
+            break;
+        }
+    }
+}
+
+/// Runs an update step, sending its stdout and stderr a line at a time.
+pub async fn run_step(
+    step: &UpdateStep,
+    working_directory: &Path,
+    lines: UnboundedSender<String>,
+) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&step.command)
+        .current_dir(working_directory)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("couldn't start `{}`: {}", step.command, err))?;
+
+    let stdout = tokio::spawn(forward_lines(child.stdout.take().unwrap(), lines.clone()));
+    let stderr = tokio::spawn(forward_lines(child.stderr.take().unwrap(), lines));
+    let status = child
+        .wait()
+        .await
+        .map_err(|err| format!("`{}` didn't finish: {}", step.command, err))?;
+    let _ = tokio::join!(stdout, stderr);
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`{}` exited with {}", step.command, status))
+    }
+}
+
+/// Restarts into the binary at `binary` with the same arguments, only returning if that failed.
+#[cfg(unix)]
+pub fn restart(binary: &Path) -> io::Error {
+    use std::os::unix::process::CommandExt;
+
+    std::process::Command::new(binary)
+        .args(std::env::args_os().skip(1))
+        .exec()
+}
+
+#[cfg(not(unix))]
+pub fn restart(_binary: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "restarting is only supported on unix, restart the bot manually",
+    )
+}
+
+/// Update state that has to survive the restart, plus a flag so only one update runs at a time.
+#[derive(Clone)]
+pub struct UpdateSystem {
+    db: Arc<Db>,
+    state: Tree,
+    running: Arc<AtomicBool>,
+}
+
+impl UpdateSystem {
+    pub fn init(db_path: &str) -> sled::Result<Self> {
+        let db = Arc::new(sled::open(db_path)?);
+        let system = UpdateSystem {
+            state: db.open_tree("state")?,
+            db: Arc::clone(&db),
+            running: Arc::new(AtomicBool::new(false)),
+        };
+
+        Ok(system)
+    }
+
+    pub fn flush(&self) -> sled::Result<()> {
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Claims the update lock, returning false if an update is already running.
+    pub fn try_start(&self) -> bool {
+        !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn finish(&self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+
+    /// Saves what to report after restarting, flushing since the restart won't.
+    pub fn set_pending(&self, pending: &PendingRestart) -> sled::Result<()> {
+        let serialized = bincode::serialize(pending)
+            .map_err(|_| sled::Error::Io(std::io::Error::other("Serialization error")))?;
+        self.state.insert(PENDING_KEY, serialized)?;
+        if !pending.rollback {
+            self.state
+                .insert(PREVIOUS_VERSION_KEY, pending.from_version.as_bytes())?;
+        }
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn take_pending(&self) -> sled::Result<Option<PendingRestart>> {
+        match self.state.remove(PENDING_KEY)? {
+            Some(value) => {
+                let pending = bincode::deserialize(&value)
+                    .map_err(|_| sled::Error::Io(std::io::Error::other("Deserialization error")))?;
+                Ok(Some(pending))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The version running before the last update, which a rollback returns to.
+    pub fn previous_version(&self) -> sled::Result<Option<String>> {
+        Ok(self
+            .state
+            .get(PREVIOUS_VERSION_KEY)?
+            .map(|version| String::from_utf8_lossy(&version).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_step_streams_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let step = |command: &str| UpdateStep {
+            name: "Test".to_string(),
+            command: command.to_string(),
+        };
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        run_step(&step("pwd; echo warning >&2"), dir.path(), sender)
+            .await
+            .unwrap();
+        let mut lines = Vec::new();
+        while let Some(line) = receiver.recv().await {
+            lines.push(line);
+        }
+        lines.sort();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.contains(&"warning".to_string()));
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let err = run_step(&step("exit 3"), dir.path(), sender)
+            .await
+            .unwrap_err();
+        assert!(err.contains("exit status: 3"));
+    }
+
+    #[test]
+    fn test_swap_and_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("bot");
+        let built = dir.path().join("built");
+        std::fs::write(&binary, "v1").unwrap();
+        std::fs::write(&built, "v2").unwrap();
+
+        assert!(rollback_binary(&binary).is_err());
+        swap_binary(&built, &binary).unwrap();
+        assert_eq!(std::fs::read_to_string(&binary).unwrap(), "v2");
+        rollback_binary(&binary).unwrap();
+        assert_eq!(std::fs::read_to_string(&binary).unwrap(), "v1");
+        assert!(!previous_binary(&binary).exists());
+
+        let system = UpdateSystem::init(dir.path().join("db").to_str().unwrap()).unwrap();
+        assert!(system.try_start());
+        assert!(!system.try_start());
+        let pending = PendingRestart {
+            from_version: "3.4.3".to_string(),
+            channel_id: 1,
+            requested_by: 2,
+            rollback: false,
+        };
+        system.set_pending(&pending).unwrap();
+        assert_eq!(system.take_pending().unwrap(), Some(pending));
+        assert_eq!(system.take_pending().unwrap(), None);
+        assert_eq!(system.previous_version().unwrap().as_deref(), Some("3.4.3"));
+    }
+}
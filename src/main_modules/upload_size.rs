@@ -0,0 +1,287 @@
+use serenity::all::{Cache, GuildId, PremiumTier};
+use std::fmt;
+use std::time::Duration;
+
+use super::diagnostics::format_bytes;
+
+/// What can be uploaded in DMs and in servers without enough boosts to raise it.
+const BASE_UPLOAD_LIMIT: u64 = 10 * 1024 * 1024;
+/// How much of the limit an encode aims for, leaving room for the container and for bitrates
+/// overshooting a little.
+const HEADROOM: f64 = 0.95;
+/// Below this a video is more blocks than picture, so there's no point fitting it.
+const MIN_VIDEO_KBPS: u64 = 64;
+
+/// The most a bot can upload to a server with `tier` boosts.
+pub fn upload_limit(tier: PremiumTier) -> u64 {
+    match tier {
+        PremiumTier::Tier2 => 50 * 1024 * 1024,
+        PremiumTier::Tier3 => 100 * 1024 * 1024,
+        _ => BASE_UPLOAD_LIMIT,
+    }
+}
+
+/// The upload limit where a reply is going, going by the boost tier of `guild_id` in the cache.
+pub fn guild_upload_limit(cache: &Cache, guild_id: Option<GuildId>) -> u64 {
+    guild_id
+        .and_then(|guild_id| cache.guild(guild_id).map(|guild| guild.premium_tier))
+        .map_or(BASE_UPLOAD_LIMIT, upload_limit)
+}
+
+/// What a video is re-encoded at, in two passes, to fit an upload limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoTarget {
+    pub video_kbps: u64,
+    /// `None` when there's no audio to keep.
+    pub audio_kbps: Option<u64>,
+    /// The height to scale down to so the bitrate still looks alright, `None` to keep it.
+    pub max_height: Option<u32>,
+}
+
+impl VideoTarget {
+    /// What a `duration` long video fits in `limit` bytes at, `None` if it would have to be
+    /// unwatchable.
+    pub fn new(limit: u64, duration: Duration, has_audio: bool) -> Option<VideoTarget> {
+        let seconds = duration.as_secs_f64();
+        if seconds <= 0.0 {
+            return None;
+        }
+        let total_kbps = (limit as f64 * HEADROOM * 8.0 / 1000.0 / seconds) as u64;
+        let audio_kbps = has_audio.then_some(if total_kbps < 600 { 64 } else { 128 });
+        let video_kbps = total_kbps.checked_sub(audio_kbps.unwrap_or(0))?;
+        VideoTarget::with_video_kbps(video_kbps, audio_kbps)
+    }
+
+    fn with_video_kbps(video_kbps: u64, audio_kbps: Option<u64>) -> Option<VideoTarget> {
+        if video_kbps < MIN_VIDEO_KBPS {
+            return None;
+        }
+        let max_height = match video_kbps {
+            0..=399 => Some(360),
+            400..=799 => Some(480),
+            800..=1499 => Some(720),
+            _ => None,
+        };
+        Some(VideoTarget {
+            video_kbps,
+            audio_kbps,
+            max_height,
+        })
+    }
+
+    /// Aims lower after an encode at this target came out `size` bytes, over `limit`.
+    pub fn shrunk(self, size: u64, limit: u64) -> Option<VideoTarget> {
+        let video_kbps = (self.video_kbps as f64 * limit as f64 * HEADROOM / size as f64) as u64;
+        VideoTarget::with_video_kbps(video_kbps, self.audio_kbps)
+    }
+}
+
+impl fmt::Display for VideoTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.video_kbps >= 1000 {
+            write!(
+                f,
+                "re-encoded at {:.1} Mbps",
+                self.video_kbps as f64 / 1000.0
+            )?;
+        } else {
+            write!(f, "re-encoded at {} kbps", self.video_kbps)?;
+        }
+        if let Some(height) = self.max_height {
+            write!(f, " and scaled down to {}p", height)?;
+        }
+        Ok(())
+    }
+}
+
+/// One rung of the ladder a gif goes down until it fits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GifStep {
+    /// Of the width and height.
+    pub scale: f64,
+    pub colors: u16,
+    pub max_fps: Option<f64>,
+}
+
+const GIF_STEPS: [GifStep; 7] = [
+    GifStep {
+        scale: 1.0,
+        colors: 128,
+        max_fps: None,
+    },
+    GifStep {
+        scale: 0.75,
+        colors: 128,
+        max_fps: Some(20.0),
+    },
+    GifStep {
+        scale: 0.75,
+        colors: 64,
+        max_fps: Some(15.0),
+    },
+    GifStep {
+        scale: 0.5,
+        colors: 64,
+        max_fps: Some(15.0),
+    },
+    GifStep {
+        scale: 0.5,
+        colors: 32,
+        max_fps: Some(12.0),
+    },
+    GifStep {
+        scale: 0.35,
+        colors: 32,
+        max_fps: Some(10.0),
+    },
+    GifStep {
+        scale: 0.25,
+        colors: 16,
+        max_fps: Some(10.0),
+    },
+];
+
+impl GifStep {
+    /// The steps worth trying for a `size` byte gif at `fps` to fit in `limit`, skipping those
+    /// that can't shrink it enough. Always has at least the last step.
+    pub fn ladder(size: u64, fps: Option<f64>, limit: u64) -> Vec<GifStep> {
+        let mut steps: Vec<_> = GIF_STEPS
+            .into_iter()
+            .filter(|step| step.estimate(size, fps) <= limit.saturating_mul(2))
+            .collect();
+        if steps.is_empty() {
+            steps.push(GIF_STEPS[GIF_STEPS.len() - 1]);
+        }
+        steps
+    }
+
+    /// The frame rate this step brings a gif at `fps` down to, if it lowers it at all.
+    fn fps(&self, fps: Option<f64>) -> Option<f64> {
+        match (self.max_fps, fps) {
+            (Some(max_fps), Some(fps)) if fps > max_fps => Some(max_fps),
+            (Some(max_fps), None) => Some(max_fps),
+            _ => None,
+        }
+    }
+
+    /// Roughly how big a `size` byte gif at `fps` comes out. Only pixels and frames count,
+    /// fewer colors shrink it far less.
+    fn estimate(&self, size: u64, fps: Option<f64>) -> u64 {
+        let frames = match (self.fps(fps), fps) {
+            (Some(new_fps), Some(fps)) => new_fps / fps,
+            _ => 1.0,
+        };
+        (size as f64 * self.scale * self.scale * frames) as u64
+    }
+
+    pub fn filter(&self, fps: Option<f64>) -> String {
+        let fps_filter = self
+            .fps(fps)
+            .map(|fps| format!("fps={},", fps))
+            .unwrap_or_default();
+        format!(
+            "{}scale=iw*{}:-1:flags=lanczos,split[a][b];[a]palettegen=max_colors={}:stats_mode=diff[p];[b][p]paletteuse=dither=bayer:bayer_scale=3:diff_mode=rectangle",
+            fps_filter, self.scale, self.colors
+        )
+    }
+
+    pub fn describe(&self, fps: Option<f64>) -> String {
+        let mut description = format!("{} colors", self.colors);
+        if self.scale < 1.0 {
+            description = format!("scaled to {:.0}% with {}", self.scale * 100.0, description);
+        } else {
+            description = format!("reduced to {}", description);
+        }
+        if let Some(fps) = self.fps(fps) {
+            description.push_str(&format!(" at {} fps", fps));
+        }
+        description
+    }
+}
+
+/// How an output ended up fitting in the upload limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fitted {
+    pub size: u64,
+    pub limit: u64,
+    /// What was given up to fit, `None` if it fit as it was.
+    pub compromise: Option<String>,
+}
+
+impl fmt::Display for Fitted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.compromise {
+            Some(compromise) => write!(
+                f,
+                "{}, {} to fit the {} upload limit.",
+                format_bytes(self.size),
+                compromise,
+                format_bytes(self.limit)
+            ),
+            None => write!(f, "{}.", format_bytes(self.size)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_target() {
+        assert_eq!(upload_limit(PremiumTier::Tier1), BASE_UPLOAD_LIMIT);
+        assert_eq!(upload_limit(PremiumTier::Tier3), 100 * 1024 * 1024);
+
+        let target = VideoTarget::new(BASE_UPLOAD_LIMIT, Duration::from_secs(60), true).unwrap();
+        assert_eq!(target.audio_kbps, Some(128));
+        assert_eq!(target.video_kbps, 1200);
+        assert_eq!(target.max_height, Some(720));
+        assert_eq!(
+            target.to_string(),
+            "re-encoded at 1.2 Mbps and scaled down to 720p"
+        );
+
+        let shrunk = target
+            .shrunk(BASE_UPLOAD_LIMIT * 2, BASE_UPLOAD_LIMIT)
+            .unwrap();
+        assert_eq!(shrunk.video_kbps, 570);
+        assert_eq!(shrunk.max_height, Some(480));
+
+        assert_eq!(
+            VideoTarget::new(BASE_UPLOAD_LIMIT, Duration::from_secs(3600), true),
+            None
+        );
+        assert_eq!(
+            VideoTarget::new(BASE_UPLOAD_LIMIT, Duration::from_secs(10), false).unwrap(),
+            VideoTarget {
+                video_kbps: 7969,
+                audio_kbps: None,
+                max_height: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_gif_ladder() {
+        let limit = BASE_UPLOAD_LIMIT;
+        assert_eq!(GifStep::ladder(limit + 1, Some(30.0), limit), GIF_STEPS);
+
+        // Eight times over the limit, only the steps that shrink it at least fourfold are tried.
+        let ladder = GifStep::ladder(limit * 8, Some(30.0), limit);
+        assert_eq!(ladder[0], GIF_STEPS[3]);
+        assert_eq!(ladder.len(), GIF_STEPS.len() - 3);
+
+        assert_eq!(GifStep::ladder(limit * 1000, None, limit), [GIF_STEPS[6]]);
+
+        assert_eq!(
+            GIF_STEPS[3].describe(Some(30.0)),
+            "scaled to 50% with 64 colors at 15 fps"
+        );
+        assert_eq!(GIF_STEPS[0].describe(Some(30.0)), "reduced to 128 colors");
+        assert!(
+            GIF_STEPS[1]
+                .filter(Some(10.0))
+                .starts_with("scale=iw*0.75:-1")
+        );
+    }
+}